@@ -32,9 +32,15 @@ stats = {
         "moves": 0,
         "explored": 0,
         "beta_cuts": 0,
+        "tt_probes": 0,
         "tt_exact": 0,
         "tt_cuts": 0,
         "max_depth": 0,
+        "ponder_hit": 0,
+        "ponder_miss": 0,
+        "eval_hits": 0,
+        "eval_misses": 0,
+        "promotions": 0,
     },
     "player2": {
         "wins": 0,
@@ -43,9 +49,15 @@ stats = {
         "moves": 0,
         "explored": 0,
         "beta_cuts": 0,
+        "tt_probes": 0,
         "tt_exact": 0,
         "tt_cuts": 0,
         "max_depth": 0,
+        "ponder_hit": 0,
+        "ponder_miss": 0,
+        "eval_hits": 0,
+        "eval_misses": 0,
+        "promotions": 0,
     },
 }
 
@@ -56,9 +68,15 @@ def update_player_stats(stats, game, player):
     stats[player]["moves"] += int(game[player]["moves"])
     stats[player]["explored"] += int(game[player]["explored"])
     stats[player]["beta_cuts"] += int(game[player]["beta_cuts"])
+    stats[player]["tt_probes"] += int(game[player]["tt_probes"])
     stats[player]["tt_exact"] += int(game[player]["tt_exact"])
     stats[player]["tt_cuts"] += int(game[player]["tt_cuts"])
     stats[player]["max_depth"] += int(game[player]["max_depth"])
+    stats[player]["ponder_hit"] += int(game[player]["ponder_hit"])
+    stats[player]["ponder_miss"] += int(game[player]["ponder_miss"])
+    stats[player]["eval_hits"] += int(game[player]["eval_hits"])
+    stats[player]["eval_misses"] += int(game[player]["eval_misses"])
+    stats[player]["promotions"] += int(game[player]["promotions"])
 
 
 for gid in data["game"].keys():
@@ -81,9 +99,17 @@ def average_player_stats(stats, total_games, player):
     stats[player]["moves"] = stats[player]["moves"] / total_games
     stats[player]["explored"] = stats[player]["explored"] / total_games
     stats[player]["beta_cuts"] = stats[player]["beta_cuts"] / total_games
+    stats[player]["tt_probes"] = stats[player]["tt_probes"] / total_games
     stats[player]["tt_exact"] = stats[player]["tt_exact"] / total_games
     stats[player]["tt_cuts"] = stats[player]["tt_cuts"] / total_games
     stats[player]["max_depth"] = stats[player]["max_depth"] / total_games
+    stats[player]["ponder_hit"] = stats[player]["ponder_hit"] / total_games
+    stats[player]["ponder_miss"] = stats[player]["ponder_miss"] / total_games
+    stats[player]["eval_hits"] = stats[player]["eval_hits"] / total_games
+    stats[player]["eval_misses"] = stats[player]["eval_misses"] / total_games
+    stats[player]["promotions"] = stats[player]["promotions"] / total_games
+    evals = stats[player]["eval_hits"] + stats[player]["eval_misses"]
+    stats[player]["eval_hit_rate"] = stats[player]["eval_hits"] / evals if evals else 0
 
 
 average_player_stats(stats, total_games, "player1")
@@ -1,11 +1,11 @@
 // This module contains the main data structures that represent board state in the Checkers engine.
 
 use clap::ValueEnum;
-use rand::{thread_rng, Rng};
-use std::fmt;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{cmp::Ordering, fmt, sync::OnceLock};
 
 // Define the two players of a Checkers game.
-#[derive(Debug, PartialEq, Clone, Copy, ValueEnum, Eq, Hash)]
+#[derive(Debug, PartialEq, Clone, Copy, ValueEnum, Eq, Hash, PartialOrd, Ord)]
 pub enum Player {
     Player1,
     Player2,
@@ -19,10 +19,58 @@ impl Player {
             Self::Player2 => Self::Player1,
         }
     }
+
+    // Returns 1 for Player 1 and -1 for Player 2: multiplying a count or score from Player 1's
+    // perspective by the sign of `player` gives it from the perspective of `player`.
+    pub fn sign(&self) -> i32 {
+        match self {
+            Self::Player1 => 1,
+            Self::Player2 => -1,
+        }
+    }
+
+    // Returns the direction, along the rows numbered by [square_coords], in which the pawns of
+    // the player move: 1 for Player 1 and -1 for Player 2.
+    pub fn forward_direction(&self) -> i32 {
+        match self {
+            Self::Player1 => 1,
+            Self::Player2 => -1,
+        }
+    }
+
+    // Returns the row of `row`, as numbered by [square_coords], counted from the player's own
+    // back row.
+    pub fn relative_row(&self, row: i32) -> i32 {
+        (row - promotion_row(self.other(), ROWS)) * self.forward_direction()
+    }
+}
+
+// The error returned when a string does not name a [Player].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ParsePlayerError;
+
+impl fmt::Display for ParsePlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown player")
+    }
+}
+
+// Parse a [Player] from `player1`, `1` or `black` (Player 1 moves first) and `player2`, `2` or
+// `white`, ignoring case.
+impl TryFrom<&str> for Player {
+    type Error = ParsePlayerError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "player1" | "1" | "black" => Ok(Self::Player1),
+            "player2" | "2" | "white" => Ok(Self::Player2),
+            _ => Err(ParsePlayerError),
+        }
+    }
 }
 
 // Define the types of pieces in a Checkers game.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Piece {
     // What player the piece belongs to.
     player: Player,
@@ -39,6 +87,7 @@ impl Piece {
         Self::new(Player::Player1, false)
     }
 
+    #[allow(dead_code)]
     pub fn player1_king() -> Self {
         Self::new(Player::Player1, true)
     }
@@ -47,6 +96,7 @@ impl Piece {
         Self::new(Player::Player2, false)
     }
 
+    #[allow(dead_code)]
     pub fn player2_king() -> Self {
         Self::new(Player::Player2, true)
     }
@@ -56,7 +106,7 @@ impl Piece {
     }
 
     // Return a slice of possible moves given the current state of the piece.
-    pub fn movements(&self) -> &[i32] {
+    pub fn movements(&self) -> &'static [i32] {
         if self.king {
             return &[-4, -5, 4, 5];
         }
@@ -73,6 +123,16 @@ impl Piece {
     pub fn is_king(&self) -> bool {
         self.king
     }
+
+    // Return the piece crowned as a king. A king stays a king.
+    pub fn promote(self) -> Piece {
+        Self::new(self.player, true)
+    }
+
+    // Return the piece as a pawn, reverting a promotion. A pawn stays a pawn.
+    pub fn demote(self) -> Piece {
+        Self::new(self.player, false)
+    }
 }
 
 impl fmt::Display for Piece {
@@ -121,7 +181,7 @@ impl fmt::Display for Square {
 // [SquareState] is used in [Movement] to represent a location on the [Board] and what
 // piece is there are the time of constructing a [Movement]. The piece state is saved
 // in order to undo movements.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct SquareState {
     // The location on the [Board].
     pub id: usize,
@@ -143,7 +203,7 @@ impl SquareState {
 }
 
 // Define the information required to move a piece on the board.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Movement {
     // From which square the piece is moving.
     from: SquareState,
@@ -196,14 +256,374 @@ impl Movement {
         self.jumped.is_some()
     }
 
+    // A movement to an adjacent square, without a capture.
+    #[allow(dead_code)]
+    pub fn is_simple(&self) -> bool {
+        self.jumped.is_none()
+    }
+
+    // A jump capturing a single piece.
+    #[allow(dead_code)]
+    pub fn is_single_jump(&self) -> bool {
+        self.jumped.is_some() && self.next.is_none()
+    }
+
+    // A jump continuing with further jumps.
+    #[allow(dead_code)]
+    pub fn is_multi_jump(&self) -> bool {
+        self.next.is_some()
+    }
+
     pub fn from(&self) -> SquareState {
         self.from
     }
+
+    // The square of the first step of the movement.
+    pub fn to(&self) -> SquareState {
+        self.to
+    }
+
+    // The square of the last step of the movement.
+    pub fn end(&self) -> SquareState {
+        let mut last = self;
+        while let Some(next) = &last.next {
+            last = next;
+        }
+        last.to
+    }
+
+    // The number of pieces captured by the movement.
+    pub fn captures(&self) -> usize {
+        let mut captures = 0;
+        let mut movement = Some(self);
+        while let Some(m) = movement {
+            if m.is_jump() {
+                captures += 1;
+            }
+            movement = m.next.as_deref();
+        }
+        captures
+    }
+
+    // The movement in standard checkers notation, e.g. `11-15` or `11x18x25`. A jump with more
+    // than `max_captures` captures is abbreviated to its start and end squares, e.g.
+    // `10x ... x10 (4 captures)`; with `None` every square is written.
+    pub fn notation(&self, max_captures: Option<usize>) -> String {
+        let mut notation = square_number(self.from.id).to_string();
+        let mut end = self.to;
+        let mut movement = Some(self);
+        while let Some(m) = movement {
+            let sep = if m.is_jump() { "x" } else { "-" };
+            notation.push_str(&format!("{}{}", sep, square_number(m.to.id)));
+            end = m.to;
+            movement = m.next.as_deref();
+        }
+        let captures = self.captures();
+        if max_captures.is_some_and(|max| captures > max) {
+            return format!(
+                "{}x ... x{} ({} captures)",
+                square_number(self.from.id),
+                square_number(end.id),
+                captures
+            );
+        }
+        notation
+    }
+
+    // Encode the movement in a compact binary form read back by [Movement::decode]. Every step
+    // of the movement takes [MOVEMENT_STEP_BYTES] bytes: the standard numbers of the from, to
+    // and jumped squares (0 for no jump), each followed by the piece on it (0 for none, 1-4 for
+    // a Player 1 pawn, Player 1 king, Player 2 pawn and Player 2 king).
+    #[allow(dead_code)]
+    pub fn encode(&self) -> Vec<u8> {
+        let state = |s: Option<SquareState>| match s {
+            Some(s) => {
+                let piece = match s.piece.map(|p| (p.player, p.king)) {
+                    None => 0,
+                    Some((Player::Player1, false)) => 1,
+                    Some((Player::Player1, true)) => 2,
+                    Some((Player::Player2, false)) => 3,
+                    Some((Player::Player2, true)) => 4,
+                };
+                [square_number(s.id) as u8, piece]
+            }
+            None => [0, 0],
+        };
+        let mut bytes = Vec::new();
+        let mut movement = Some(self);
+        while let Some(m) = movement {
+            bytes.extend(state(Some(m.from)));
+            bytes.extend(state(Some(m.to)));
+            bytes.extend(state(m.jumped));
+            movement = m.next.as_deref();
+        }
+        bytes
+    }
+
+    // Decode a movement encoded with [Movement::encode].
+    #[allow(dead_code)]
+    pub fn decode(bytes: &[u8]) -> Result<Movement, DecodeError> {
+        if bytes.is_empty() || !bytes.len().is_multiple_of(MOVEMENT_STEP_BYTES) {
+            return Err(DecodeError::Length(bytes.len()));
+        }
+        let state = |pair: &[u8]| -> Result<Option<SquareState>, DecodeError> {
+            let piece = match pair[1] {
+                0 => None,
+                1 => Some(Piece::player1_pawn()),
+                2 => Some(Piece::new(Player::Player1, true)),
+                3 => Some(Piece::player2_pawn()),
+                4 => Some(Piece::new(Player::Player2, true)),
+                b => return Err(DecodeError::Piece(b)),
+            };
+            match (pair[0], square_id(pair[0] as usize)) {
+                (0, _) if piece.is_none() => Ok(None),
+                (_, Some(id)) => Ok(Some(SquareState { id, piece })),
+                (b, _) => Err(DecodeError::Square(b)),
+            }
+        };
+        let mut next: Option<Box<Movement>> = None;
+        for (i, step) in bytes.chunks(MOVEMENT_STEP_BYTES).enumerate().rev() {
+            let from = state(&step[0..2])?.ok_or(DecodeError::Square(0))?;
+            let to = state(&step[2..4])?.ok_or(DecodeError::Square(0))?;
+            let jumped = state(&step[4..6])?;
+            if let Some(n) = &next {
+                if jumped.is_none() || n.jumped.is_none() || n.from.id != to.id {
+                    return Err(DecodeError::Step(i + 1));
+                }
+            }
+            next = Some(Box::new(Movement {
+                from,
+                to,
+                jumped,
+                next,
+            }));
+        }
+        Ok(*next.unwrap())
+    }
 }
 
-// Define the Zobrist hash data structure for a [Board].
+// The number of bytes taken by each step of a [Movement] encoded with [Movement::encode].
+const MOVEMENT_STEP_BYTES: usize = 6;
+
+// The error returned by [Movement::decode] for bytes that do not encode a movement.
 #[derive(Debug, PartialEq, Clone, Copy)]
-struct ZobristHash {
+pub enum DecodeError {
+    // The number of bytes is not a positive multiple of the size of a step.
+    Length(usize),
+    // A byte is not the standard number of a square, or a square is missing.
+    Square(u8),
+    // A byte does not encode a piece.
+    Piece(u8),
+    // The step with this index (from 0) does not continue the movement: only a jump continues a
+    // jump, from the square where it ended.
+    Step(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Length(n) => write!(f, "bad movement length: {} bytes", n),
+            Self::Square(b) => write!(f, "bad square: {}", b),
+            Self::Piece(b) => write!(f, "bad piece: {}", b),
+            Self::Step(i) => write!(f, "bad step: {}", i),
+        }
+    }
+}
+
+// Movements are ordered by the number of pieces they capture, most first, then by the [Board] ids
+// of the square they start from and of the squares of their steps. This is a stable tiebreak
+// giving movements a deterministic order, not a ranking of how good they are.
+impl Ord for Movement {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .captures()
+            .cmp(&self.captures())
+            .then_with(|| self.from.cmp(&other.from))
+            .then_with(|| self.to.cmp(&other.to))
+            .then_with(|| self.jumped.cmp(&other.jumped))
+            .then_with(|| self.next.cmp(&other.next))
+    }
+}
+
+impl PartialOrd for Movement {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Display a [Movement] in full standard checkers notation, see [Movement::notation].
+impl fmt::Display for Movement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.notation(None))
+    }
+}
+
+// The legal movements of a player counted by kind, see [Board::move_summary].
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct MoveSummary {
+    pub simple: usize,
+    pub single_jumps: usize,
+    pub multi_jumps: usize,
+}
+
+// The leaf nodes of a move tree counted by [Board::perft_detailed], broken down by the kind of
+// the movement that reached them.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub promotions: u64,
+    pub multi_jumps: u64,
+}
+
+// Describes the promotion caused by a movement applied with [Board::do_movement_checked].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PromotionInfo {
+    // The square where a pawn was crowned (if any).
+    pub crowned: Option<usize>,
+}
+
+// The record of a movement applied with [Board::do_move], used to revert it with [Board::undo].
+#[derive(Debug, PartialEq, Clone)]
+#[must_use = "the movement can only be reverted with its Undo"]
+pub struct Undo {
+    movement: Movement,
+    // The player who made the movement, and so the side to move once it is undone.
+    player: Player,
+    promotion: PromotionInfo,
+}
+
+impl Undo {
+    #[allow(dead_code)]
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    #[allow(dead_code)]
+    pub fn promotion(&self) -> PromotionInfo {
+        self.promotion
+    }
+}
+
+// The error returned when a [Movement] is not legal for the player making it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct IllegalMove;
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "illegal move")
+    }
+}
+
+// The error returned when a movement in standard notation can not be resolved on a [Board].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MoveError {
+    // The string is not written in standard notation.
+    Malformed,
+    // No legal movement matches the notation.
+    Illegal,
+    // More than one legal movement matches the notation.
+    Ambiguous,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed move"),
+            Self::Illegal => write!(f, "illegal move"),
+            Self::Ambiguous => write!(f, "ambiguous move"),
+        }
+    }
+}
+
+// The error returned when a position in the setup format can not be read.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SetupError {
+    // A line or a square is not written in the setup format.
+    Malformed(String),
+    // A piece is placed on a light square, which is never played on.
+    LightSquare(String),
+    // A square is given more than one piece, or a king is not on a listed piece.
+    Occupied(String),
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed(s) => write!(f, "malformed setup: {}", s),
+            Self::LightSquare(s) => write!(f, "light square: {}", s),
+            Self::Occupied(s) => write!(f, "square taken twice: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for SetupError {}
+
+// The error returned when a handicap can not be applied to the starting position.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HandicapError {
+    // The number is not a square between 1 and 32.
+    UnknownSquare(usize),
+    // The square does not hold a piece of the handicapped side at the start.
+    NotStartingSquare(usize),
+}
+
+impl fmt::Display for HandicapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownSquare(n) => write!(f, "unknown square: {}", n),
+            Self::NotStartingSquare(n) => write!(f, "no starting piece on square: {}", n),
+        }
+    }
+}
+
+impl std::error::Error for HandicapError {}
+
+// The error returned by [Board::from_squares] when the square at `id` does not fit the padded
+// layout: a playable square holds [Square::Invalid], or a padding square anything else.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SquaresError {
+    pub id: usize,
+}
+
+impl fmt::Display for SquaresError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "square {} does not fit the board layout", self.id)
+    }
+}
+
+impl std::error::Error for SquaresError {}
+
+// The error returned by [BoardBuilder::build] when a placement is not legal. Squares are given
+// by their standard numbers.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BuildError {
+    // The number is not a square between 1 and 32.
+    UnknownSquare(usize),
+    // The square is given more than one piece.
+    Occupied(usize),
+    // A pawn is placed on its own promotion row, where it would already be a king.
+    UncrownedPawn(usize),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownSquare(n) => write!(f, "unknown square: {}", n),
+            Self::Occupied(n) => write!(f, "square taken twice: {}", n),
+            Self::UncrownedPawn(n) => write!(f, "pawn on its promotion row: {}", n),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+// The seed of the random keys of the Zobrist hash.
+const ZOBRIST_SEED: u64 = 0x5eed_c4ec_4e25;
+
+// The random keys of the Zobrist hash, shared by every [Board] so that hashes can be compared
+// across boards, and generated from a fixed seed so that they are the same on every run.
+struct ZobristKeys {
     // Each board piece may occupy 4 different states:
     //      * Player 1 pawn
     //      * Player 1 king
@@ -212,6 +632,34 @@ struct ZobristHash {
     // The board is a 46 element padded array. Thus, we use
     // a 46 element array of 4 element array u128 random numbers.
     randoms: [[u128; 4]; 46],
+    // Mixed into a hash when Player 2 is the side to move.
+    side: u128,
+}
+
+fn zobrist_keys() -> &'static ZobristKeys {
+    static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut rng = StdRng::seed_from_u64(ZOBRIST_SEED);
+        let mut randoms = [[0; 4]; 46];
+        for r in &mut randoms {
+            r[0] = rng.gen();
+            r[1] = rng.gen();
+            r[2] = rng.gen();
+            r[3] = rng.gen();
+        }
+        ZobristKeys {
+            randoms,
+            side: rng.gen(),
+        }
+    })
+}
+
+// Define the Zobrist hash data structure for a [Board].
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct ZobristHash {
+    // If the side key is mixed into `hash` because the turn was passed with
+    // [Board::make_null_move].
+    passed: bool,
     // The currenty hash of the board that the [ZobristHash] is
     // hashing.
     hash: u128,
@@ -219,14 +667,10 @@ struct ZobristHash {
 
 impl ZobristHash {
     fn new() -> Self {
-        let mut randoms = [[0; 4]; 46];
-        for r in &mut randoms {
-            r[0] = thread_rng().gen();
-            r[1] = thread_rng().gen();
-            r[2] = thread_rng().gen();
-            r[3] = thread_rng().gen();
+        Self {
+            passed: false,
+            hash: 0,
         }
-        Self { randoms, hash: 0 }
     }
 
     fn piece_id(piece: Piece) -> usize {
@@ -249,7 +693,7 @@ impl ZobristHash {
     }
 
     fn flip(&mut self, pos: usize, piece: usize) {
-        self.hash ^= self.randoms[pos][piece];
+        self.hash ^= zobrist_keys().randoms[pos][piece];
     }
 }
 
@@ -257,442 +701,2812 @@ pub const VALID_SQUARES: [usize; 32] = [
     5, 6, 7, 8, 10, 11, 12, 13, 14, 15, 16, 17, 19, 20, 21, 22, 23, 24, 25, 26, 28, 29, 30, 31, 32,
     33, 34, 35, 37, 38, 39, 40,
 ];
+// The playable squares in standard checkers numbering, where square 1 is on Player 1's back
+// row. `STANDARD_SQUARES[n - 1]` is the [Board] id of square `n`.
+const STANDARD_SQUARES: [usize; 32] = [
+    8, 7, 6, 5, 13, 12, 11, 10, 17, 16, 15, 14, 22, 21, 20, 19, 26, 25, 24, 23, 31, 30, 29, 28, 35,
+    34, 33, 32, 40, 39, 38, 37,
+];
 const PLAYER1_START: [usize; 12] = [5, 6, 7, 8, 10, 11, 12, 13, 14, 15, 16, 17];
 const PLAYER2_START: [usize; 12] = [28, 29, 30, 31, 32, 33, 34, 35, 37, 38, 39, 40];
 const EMPTY_START: [usize; 8] = [19, 20, 21, 22, 23, 24, 25, 26];
-const PLAYER1_KINGS: [usize; 4] = [37, 38, 39, 40];
-const PLAYER2_KINGS: [usize; 4] = [5, 6, 7, 8];
+// The number of rows of the board.
+pub const ROWS: i32 = 8;
+// The plies of captures played out by [Board::forced_loss_after_capture].
+const SHOT_PLIES: u32 = 8;
 
-#[derive(Debug)]
-pub struct Board {
-    // # https://3dkingdoms.com/checkers/bitboards.htm by Jonathan Kreuzer
-    // #
-    // #    37  38  39  40      |- Player 2 start (X)
-    // #  32  33  34  35        |-
-    // #    28  29  30  31      |-
-    // #  23  24  25  26
-    // #    19  20  21  22
-    // #  14  15  16  17        |-
-    // #    10  11  12  13      |-
-    // #  05  06  07  08        |- Player 1 start (O)
-    //
-    squares: [Square; 46],
-    // The current Zobrist hash of the board state.
-    zobrist: ZobristHash,
+// Returns the standard number (1-32) of the square with the given [Board] id.
+pub fn square_number(id: usize) -> usize {
+    STANDARD_SQUARES.iter().position(|s| *s == id).unwrap() + 1
 }
 
-impl Board {
-    pub fn new() -> Self {
-        let mut zobrist = ZobristHash::new();
-        let mut squares = [Square::Invalid; 46];
-        for id in PLAYER1_START {
-            let p = Piece::player1_pawn();
-            squares[id] = Square::Taken(p);
-            zobrist.flip(id, p.id())
-        }
-        for id in EMPTY_START {
-            squares[id] = Square::Empty;
-        }
-        for id in PLAYER2_START {
-            let p = Piece::player2_pawn();
-            squares[id] = Square::Taken(p);
-            zobrist.flip(id, p.id())
-        }
-        Self { squares, zobrist }
+// Returns the [Board] id of the square with the given standard number (1-32).
+pub fn square_id(number: usize) -> Option<usize> {
+    if number == 0 {
+        return None;
     }
+    STANDARD_SQUARES.get(number - 1).copied()
+}
 
-    pub fn hash(&self) -> u128 {
-        self.zobrist.hash
-    }
+// A playable square in standard checkers numbering (1-32), the numbering used by FEN and by
+// movements in notation, and by the board queries meant for frontends and analysis. The engine
+// itself (move generation, the evaluations and [Board::get], [SquareState] and [VALID_SQUARES])
+// works with the [Board] ids of the padded array, which a `SquareId` converts to and from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SquareId(u8);
 
-    #[allow(dead_code)]
-    pub fn empty() -> Self {
-        let zobrist = ZobristHash::new();
-        let mut squares = [Square::Invalid; 46];
-        for id in VALID_SQUARES {
-            squares[id] = Square::Empty;
-        }
-        Self { squares, zobrist }
+#[allow(dead_code)]
+impl SquareId {
+    // The square with the standard number `number`, if there is one.
+    pub fn new(number: usize) -> Option<Self> {
+        square_id(number).map(|_| Self(number as u8))
     }
 
-    pub fn get(&self, id: usize) -> Square {
-        self.squares[id]
+    // The square with the given [Board] id, if it is playable.
+    pub fn from_board_id(id: usize) -> Option<Self> {
+        STANDARD_SQUARES
+            .iter()
+            .position(|s| *s == id)
+            .map(|i| Self(i as u8 + 1))
     }
 
-    #[allow(dead_code)]
-    pub fn set(&mut self, id: usize, square: Square) {
-        self.squares[id] = square;
+    // The standard number of the square.
+    pub fn number(self) -> usize {
+        self.0 as usize
     }
 
-    pub fn movements(&self, player: Player) -> Vec<Movement> {
-        let jumps = self.jump_moves(player);
-        if !jumps.is_empty() {
-            return jumps;
-        }
-        self.simple_moves(player)
+    // The [Board] id of the square.
+    pub fn board_id(self) -> usize {
+        STANDARD_SQUARES[self.0 as usize - 1]
     }
 
-    fn simple_moves(&self, player: Player) -> Vec<Movement> {
-        let mut movements = Vec::new();
-        for id in VALID_SQUARES {
-            if let Square::Taken(piece) = self.squares[id] {
-                if piece.player == player {
-                    for m in piece.movements() {
-                        let id_to = (id as i32 + m) as usize;
-                        if Square::Empty == self.squares[id_to] {
-                            let from = SquareState::piece(id, piece);
-                            let to = SquareState::empty(id_to);
-                            let movement = Movement::simple(from, to);
-                            movements.push(movement);
-                        }
-                    }
-                }
-            }
-        }
-        movements
+    // The 32 playable squares, from 1 to 32.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (1..=32).map(Self)
     }
+}
 
-    fn jump_moves(&self, player: Player) -> Vec<Movement> {
-        let mut movements = Vec::new();
-        for id in VALID_SQUARES {
-            if let Square::Taken(piece) = self.squares[id] {
-                if piece.player == player {
-                    movements.append(&mut self.jump_moves_at(
-                        player,
-                        piece,
-                        id,
-                        id,
-                        &mut Vec::new(),
-                    ));
-                }
-            }
-        }
-        movements
+impl fmt::Display for SquareId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
+}
 
-    fn jump_moves_at(
-        &self,
+// Returns the (row, column) of the square with the given [Board] id, both in 0..8. Row 0 is
+// Player 1's back row.
+pub fn square_coords(id: usize) -> (i32, i32) {
+    let index = square_number(id) as i32 - 1;
+    let row = index / 4;
+    let col = 2 * (index % 4) + if row % 2 == 0 { 1 } else { 0 };
+    (row, col)
+}
+
+// Returns the row, as numbered by [square_coords], on which the pawns of `player` are crowned on
+// a board with `rows` rows: the back row of the opponent.
+pub fn promotion_row(player: Player, rows: i32) -> i32 {
+    match player {
+        Player::Player1 => rows - 1,
+        Player::Player2 => 0,
+    }
+}
+
+// Remove the jumps of a single piece that capture the same pieces and land on the same square as
+// an earlier one, e.g. a king going around a circle of pieces either way. They leave the same
+// position behind, so searching more than one of them is wasted work. The first is kept.
+fn dedup_jumps(mut movements: Vec<Movement>) -> Vec<Movement> {
+    let mut seen = Vec::new();
+    movements.retain(|movement| {
+        let mut captured = Vec::new();
+        let mut end = movement.to.id;
+        let mut next = Some(movement);
+        while let Some(m) = next {
+            captured.extend(m.jumped.map(|j| j.id));
+            end = m.to.id;
+            next = m.next.as_deref();
+        }
+        captured.sort_unstable();
+        let key = (end, captured);
+        if seen.contains(&key) {
+            false
+        } else {
+            seen.push(key);
+            true
+        }
+    });
+    movements
+}
+
+// Returns true if a pawn of `player` is crowned on the square with the given [Board] id.
+pub fn is_promotion_square(id: usize, player: Player) -> bool {
+    square_coords(id).0 == promotion_row(player, ROWS)
+}
+
+// The central squares of the board, by [Board] id.
+pub const CENTER: [usize; 6] = [15, 16, 20, 21, 24, 25];
+
+// Counts of the pieces of a player on a [Board] by kind and placement, kept up to date as pieces
+// are moved, captured and crowned so that evaluation functions can read them without a scan.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PieceFeatures {
+    pub pawns: i32,
+    pub kings: i32,
+    // Kings on the [CENTER] squares.
+    pub center_kings: i32,
+    // Pawns still on their own back row, guarding it against the opponent's promotions.
+    pub back_pawns: i32,
+    // Pawns within two rows of promotion.
+    pub advanced_pawns: i32,
+}
+
+impl PieceFeatures {
+    // Add the counts of `piece` on square `id` to the features, or remove them if `sign` is -1.
+    fn add(&mut self, id: usize, piece: Piece, sign: i32) {
+        if piece.king {
+            self.kings += sign;
+            if CENTER.contains(&id) {
+                self.center_kings += sign;
+            }
+        } else {
+            self.pawns += sign;
+            if is_promotion_square(id, piece.player.other()) {
+                self.back_pawns += sign;
+            }
+            if piece.player.relative_row(square_coords(id).0) >= ROWS - 3 {
+                self.advanced_pawns += sign;
+            }
+        }
+    }
+}
+
+// Returns the [Board] ids of the squares on which the pawns of `player` are crowned.
+#[cfg(test)]
+pub fn promotion_squares(player: Player) -> impl Iterator<Item = usize> {
+    VALID_SQUARES
+        .into_iter()
+        .filter(move |&id| is_promotion_square(id, player))
+}
+
+// Returns the [Board] id of the square named by its file (`a`-`h`) and rank (`1`-`8`), with rank
+// 8 on Player 1's back row as in the terminal interface, e.g. `a8` or `D5`. The error tells a
+// light square apart from a name that is not a square at all.
+fn square_at(name: &str) -> Result<usize, SetupError> {
+    let lower = name.to_ascii_lowercase();
+    let mut chars = lower.chars();
+    let (col, rank) = match (chars.next(), chars.next(), chars.next()) {
+        (Some(file @ 'a'..='h'), Some(rank @ '1'..='8'), None) => {
+            (file as usize - 'a' as usize, rank as usize - '0' as usize)
+        }
+        _ => return Err(SetupError::Malformed(name.to_string())),
+    };
+    let row = 8 - rank;
+    if (row + col) % 2 == 1 {
+        return Err(SetupError::LightSquare(name.to_string()));
+    }
+    Ok(5 + (row / 2) * 9 + (row % 2) * 5 + col / 2)
+}
+
+// Returns the name of the square with the given [Board] id, the inverse of [square_at].
+pub fn square_name(id: usize) -> String {
+    let (row, index) = match id {
+        5..=8 => (0, id - 5),
+        10..=13 => (1, id - 10),
+        14..=17 => (2, id - 14),
+        19..=22 => (3, id - 19),
+        23..=26 => (4, id - 23),
+        28..=31 => (5, id - 28),
+        32..=35 => (6, id - 32),
+        _ => (7, id - 37),
+    };
+    let col = 2 * index + row % 2;
+    format!("{}{}", (b'a' + col as u8) as char, 8 - row)
+}
+
+// The number of pieces on the board at the start of a game.
+pub const STARTING_PIECES: i32 = 24;
+
+// Define the rules variant a [Board] is played under.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rules {
+    // If a player who can jump must do so. When captures are optional, jumps and simple
+    // movements are offered together.
+    pub captures_forced: bool,
+    // If a king ending with one or two kings against a lone king is drawn after a shorter run of
+    // movements without a capture or promotion, as the side with the kings can rarely force a win.
+    pub king_ending_draw: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            captures_forced: true,
+            king_ending_draw: false,
+        }
+    }
+}
+
+// Builds a [Board] and the side to move from individual piece placements, checking that the
+// position is legal. Squares are given by their standard numbers (1-32).
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BoardBuilder {
+    pieces: Vec<(usize, Piece)>,
+    player: Player,
+    rules: Rules,
+}
+
+#[allow(dead_code)]
+impl BoardBuilder {
+    // An empty board with Player 1 to move under the default rules.
+    pub fn new() -> Self {
+        Self {
+            pieces: Vec::new(),
+            player: Player::Player1,
+            rules: Rules::default(),
+        }
+    }
+
+    // Place `piece` on the square with the standard number `square`.
+    pub fn add(mut self, square: usize, piece: Piece) -> Self {
+        self.pieces.push((square, piece));
+        self
+    }
+
+    // Set the side to move.
+    pub fn side_to_move(mut self, player: Player) -> Self {
+        self.player = player;
+        self
+    }
+
+    // Set the rules variant of the board.
+    pub fn rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    // Build the board, or return the first placement that is not legal.
+    pub fn build(self) -> Result<(Board, Player), BuildError> {
+        let mut board = Board::empty().with_rules(self.rules);
+        for (number, piece) in self.pieces {
+            let id = square_id(number).ok_or(BuildError::UnknownSquare(number))?;
+            if board.squares[id] != Square::Empty {
+                return Err(BuildError::Occupied(number));
+            }
+            if !piece.king && is_promotion_square(id, piece.player) {
+                return Err(BuildError::UncrownedPawn(number));
+            }
+            board.set(id, Square::Taken(piece));
+        }
+        debug_assert!(board.check_hash());
+        Ok((board, self.player))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Board {
+    // # https://3dkingdoms.com/checkers/bitboards.htm by Jonathan Kreuzer
+    // #
+    // #    37  38  39  40      |- Player 2 start (X)
+    // #  32  33  34  35        |-
+    // #    28  29  30  31      |-
+    // #  23  24  25  26
+    // #    19  20  21  22
+    // #  14  15  16  17        |-
+    // #    10  11  12  13      |-
+    // #  05  06  07  08        |- Player 1 start (O)
+    //
+    squares: [Square; 46],
+    // The current Zobrist hash of the board state.
+    zobrist: ZobristHash,
+    // The rules variant used for move generation.
+    rules: Rules,
+    // The number of kings of each player, indexed by [king_index], kept up to date as pieces
+    // are moved, captured and crowned.
+    kings: [u8; 2],
+    // The piece features of each player, indexed by [king_index] and kept up to date like
+    // `kings`.
+    features: [PieceFeatures; 2],
+}
+
+// Undoes a movement when dropped, for [Board::simulate].
+struct UndoGuard<'a> {
+    board: &'a mut Board,
+    movement: &'a Movement,
+}
+
+impl Drop for UndoGuard<'_> {
+    fn drop(&mut self) {
+        self.board.undo_movement(self.movement);
+    }
+}
+
+// The index of the king count and the piece features of `player` in [Board].
+fn king_index(player: Player) -> usize {
+    match player {
+        Player::Player1 => 0,
+        Player::Player2 => 1,
+    }
+}
+
+impl Board {
+    pub fn new() -> Self {
+        let mut zobrist = ZobristHash::new();
+        let mut squares = [Square::Invalid; 46];
+        for id in PLAYER1_START {
+            let p = Piece::player1_pawn();
+            squares[id] = Square::Taken(p);
+            zobrist.flip(id, p.id())
+        }
+        for id in EMPTY_START {
+            squares[id] = Square::Empty;
+        }
+        for id in PLAYER2_START {
+            let p = Piece::player2_pawn();
+            squares[id] = Square::Taken(p);
+            zobrist.flip(id, p.id())
+        }
+        let mut board = Self {
+            squares,
+            zobrist,
+            rules: Rules::default(),
+            kings: [0; 2],
+            features: [PieceFeatures::default(); 2],
+        };
+        board.features = board.compute_features();
+        board
+    }
+
+    // Play the board under the given rules variant.
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    // The rules variant the board is played under.
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn hash(&self) -> u128 {
+        self.zobrist.hash
+    }
+
+    // The hash of the position with `player` to move. The board does not track the side to move,
+    // so [Board::hash] is the same for both sides; tables whose entries depend on whose turn it
+    // is, like the transposition table, are keyed on this instead.
+    pub fn hash_with_turn(&self, player: Player) -> u128 {
+        match player {
+            Player::Player1 => self.zobrist.hash,
+            Player::Player2 => self.zobrist.hash ^ zobrist_keys().side,
+        }
+    }
+
+    // The board turned half way around with the colors of the pieces swapped, which is the same
+    // position for the other side. The piece on square `n` moves to square `33 - n`.
+    pub fn rotated(&self) -> Board {
+        let mut board = self.clone();
+        for id in VALID_SQUARES {
+            let rotated = square_id(33 - square_number(id)).unwrap();
+            board.squares[rotated] = match self.squares[id] {
+                Square::Taken(piece) => Square::Taken(Piece::new(piece.player.other(), piece.king)),
+                square => square,
+            };
+        }
+        board.zobrist.hash = board.recompute_hash();
+        board.kings = [self.kings[1], self.kings[0]];
+        board.features = board.compute_features();
+        board
+    }
+
+    // A hash of the position with `player` to move that is the same for the position and its
+    // rotation with the other side to move (see [Board::rotated]), so that both can share an
+    // entry in a table keyed on it.
+    pub fn canonical_hash(&self, player: Player) -> u128 {
+        self.hash_with_turn(player)
+            .min(self.rotated().hash_with_turn(player.other()))
+    }
+
+    pub fn empty() -> Self {
+        let zobrist = ZobristHash::new();
+        let mut squares = [Square::Invalid; 46];
+        for id in VALID_SQUARES {
+            squares[id] = Square::Empty;
+        }
+        Self {
+            squares,
+            zobrist,
+            rules: Rules::default(),
+            kings: [0; 2],
+            features: [PieceFeatures::default(); 2],
+        }
+    }
+
+    // Construct a [Board] from the raw padded squares array, e.g. one read with
+    // [Board::squares]. Only the playable squares may be empty or taken.
+    #[allow(dead_code)]
+    pub fn from_squares(squares: [Square; 46]) -> Result<Board, SquaresError> {
+        let mut board = Board::empty();
+        for (id, square) in squares.into_iter().enumerate() {
+            let playable = VALID_SQUARES.contains(&id);
+            match square {
+                Square::Invalid if !playable => {}
+                Square::Empty if playable => {}
+                Square::Taken(_) if playable => board.set(id, square),
+                _ => return Err(SquaresError { id }),
+            }
+        }
+        debug_assert!(board.check_hash());
+        Ok(board)
+    }
+
+    // The raw padded squares array of the board.
+    #[allow(dead_code)]
+    pub fn squares(&self) -> &[Square; 46] {
+        &self.squares
+    }
+
+    // Construct the starting position with the pieces of `side` on the squares `remove_squares`
+    // (in standard numbering) taken off, so that `side` plays a piece or more down.
+    #[allow(dead_code)]
+    pub fn new_with_handicap(
+        side: Player,
+        remove_squares: &[usize],
+    ) -> Result<Board, HandicapError> {
+        let start = match side {
+            Player::Player1 => PLAYER1_START,
+            Player::Player2 => PLAYER2_START,
+        };
+        let mut board = Board::new();
+        for number in remove_squares {
+            let id = square_id(*number).ok_or(HandicapError::UnknownSquare(*number))?;
+            if !start.contains(&id) || board.squares[id] == Square::Empty {
+                return Err(HandicapError::NotStartingSquare(*number));
+            }
+            board.set(id, Square::Empty);
+        }
+        debug_assert!(board.check_hash());
+        Ok(board)
+    }
+
+    // Construct a [Board] and the side to move from a position in FEN notation, e.g.
+    // `B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12`. Player 1 is black
+    // and Player 2 is white. Kings are prefixed with `K` and ranges such as `1-12` are allowed.
+    pub fn from_fen(fen: &str) -> Option<(Board, Player)> {
+        let mut fields = fen.trim().trim_end_matches('.').split(':');
+        let player = match fields.next()?.trim() {
+            "B" | "b" => Player::Player1,
+            "W" | "w" => Player::Player2,
+            _ => return None,
+        };
+        let mut board = Board::empty();
+        for field in fields {
+            let field = field.trim();
+            let owner = match field.get(..1)? {
+                "B" | "b" => Player::Player1,
+                "W" | "w" => Player::Player2,
+                _ => return None,
+            };
+            for entry in field[1..].split(',').map(str::trim) {
+                if entry.is_empty() {
+                    continue;
+                }
+                let (king, squares) = match entry.strip_prefix(['K', 'k']) {
+                    Some(squares) => (true, squares),
+                    None => (false, entry),
+                };
+                let (first, last) = match squares.split_once('-') {
+                    Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+                    None => (squares.parse().ok()?, squares.parse().ok()?),
+                };
+                for number in first..=last {
+                    let id = square_id(number)?;
+                    if board.squares[id] != Square::Empty {
+                        return None;
+                    }
+                    let piece = Piece::new(owner, king);
+                    board.squares[id] = Square::Taken(piece);
+                    board.zobrist.flip(id, piece.id());
+                    board.features[king_index(owner)].add(id, piece, 1);
+                    if king {
+                        board.kings[king_index(owner)] += 1;
+                    }
+                }
+            }
+        }
+        Some((board, player))
+    }
+
+    // A compact, one-line diagram of the position with `player` to move, for logging. Each of the
+    // 32 squares in standard order is `.` when empty, `b`/`B` for a Player 1 pawn/king and `w`/`W`
+    // for a Player 2 pawn/king, followed by a space and the side to move as in FEN (`B` or `W`).
+    #[allow(dead_code)]
+    pub fn diagram(&self, player: Player) -> String {
+        let mut diagram: String = STANDARD_SQUARES
+            .iter()
+            .map(|&id| match self.squares[id] {
+                Square::Taken(piece) => match (piece.get_player(), piece.is_king()) {
+                    (Player::Player1, false) => 'b',
+                    (Player::Player1, true) => 'B',
+                    (Player::Player2, false) => 'w',
+                    (Player::Player2, true) => 'W',
+                },
+                _ => '.',
+            })
+            .collect();
+        diagram.push_str(match player {
+            Player::Player1 => " B",
+            Player::Player2 => " W",
+        });
+        diagram
+    }
+
+    // Construct a [Board] and the side to move from a position in the setup format, which lists
+    // the squares of each side by name, the squares holding kings and the side to move:
+    //
+    //      White: b1 d1 f3
+    //      Black: a8 c6
+    //      Kings: f3
+    //      Turn: black
+    //
+    // Player 1 is black. Keys and squares are case-insensitive, squares may be separated by
+    // spaces or commas, and blank lines are ignored.
+    #[allow(dead_code)]
+    pub fn from_setup(setup: &str) -> Result<(Board, Player), SetupError> {
+        let mut pieces: Vec<(usize, Player, &str)> = Vec::new();
+        let mut kings = Vec::new();
+        let mut player = None;
+        for line in setup.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (key, values) = line
+                .split_once(':')
+                .ok_or_else(|| SetupError::Malformed(line.to_string()))?;
+            let mut names = values
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|n| !n.is_empty());
+            match key.trim().to_ascii_lowercase().as_str() {
+                "white" => {
+                    for name in names {
+                        pieces.push((square_at(name)?, Player::Player2, name));
+                    }
+                }
+                "black" => {
+                    for name in names {
+                        pieces.push((square_at(name)?, Player::Player1, name));
+                    }
+                }
+                "kings" => {
+                    for name in names {
+                        kings.push((square_at(name)?, name));
+                    }
+                }
+                "turn" => {
+                    player = match (names.next().map(str::to_ascii_lowercase), names.next()) {
+                        (Some(side), None) if side == "black" || side == "b" => {
+                            Some(Player::Player1)
+                        }
+                        (Some(side), None) if side == "white" || side == "w" => {
+                            Some(Player::Player2)
+                        }
+                        _ => return Err(SetupError::Malformed(line.to_string())),
+                    }
+                }
+                _ => return Err(SetupError::Malformed(line.to_string())),
+            }
+        }
+        let player = player.ok_or_else(|| SetupError::Malformed("missing turn".to_string()))?;
+
+        let mut board = Board::empty();
+        for (id, owner, name) in pieces {
+            if board.squares[id] != Square::Empty {
+                return Err(SetupError::Occupied(name.to_string()));
+            }
+            board.set(id, Square::Taken(Piece::new(owner, false)));
+        }
+        for (id, name) in kings {
+            match board.squares[id] {
+                Square::Taken(piece) if !piece.king => {
+                    board.set(id, Square::Taken(Piece::new(piece.player, true)))
+                }
+                _ => return Err(SetupError::Occupied(name.to_string())),
+            }
+        }
+        debug_assert!(board.check_hash());
+        Ok((board, player))
+    }
+
+    // Write the position in the setup format read by [Board::from_setup], with `player` to move.
+    #[allow(dead_code)]
+    pub fn to_setup(&self, player: Player) -> String {
+        let names = |keep: &dyn Fn(Piece) -> bool| {
+            let mut ids: Vec<usize> = VALID_SQUARES
+                .into_iter()
+                .filter(|id| matches!(self.squares[*id], Square::Taken(piece) if keep(piece)))
+                .collect();
+            ids.sort_by_key(|id| square_name(*id));
+            ids.into_iter()
+                .map(square_name)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let turn = match player {
+            Player::Player1 => "black",
+            Player::Player2 => "white",
+        };
+        format!(
+            "White: {}\nBlack: {}\nKings: {}\nTurn: {}\n",
+            names(&|p| p.player == Player::Player2),
+            names(&|p| p.player == Player::Player1),
+            names(&|p| p.king),
+            turn
+        )
+    }
+
+    pub fn get(&self, id: usize) -> Square {
+        self.squares[id]
+    }
+
+    // The piece on the square with standard number `square`, if any.
+    #[allow(dead_code)]
+    pub fn piece_on(&self, square: SquareId) -> Option<Piece> {
+        self.piece_at(square.board_id())
+    }
+
+    // The legal movements of `player` starting from the square with standard number `square`.
+    #[allow(dead_code)]
+    pub fn legal_moves_for_square(&self, player: Player, square: SquareId) -> Vec<Movement> {
+        let id = square.board_id();
+        self.legal_moves(player)
+            .filter(|m| m.from().id == id)
+            .collect()
+    }
+
+    // The piece on square `id`, if any. Prefer this to matching on [Board::get] when empty and
+    // invalid squares are treated alike.
+    pub fn piece_at(&self, id: usize) -> Option<Piece> {
+        match self.squares[id] {
+            Square::Taken(piece) => Some(piece),
+            Square::Empty | Square::Invalid => None,
+        }
+    }
+
+    // Change the square at `id`. Updates the [ZobristHash].
+    #[allow(dead_code)]
+    pub fn set(&mut self, id: usize, square: Square) {
+        if let Square::Taken(piece) = self.squares[id] {
+            self.zobrist.flip(id, piece.id());
+            self.features[king_index(piece.player)].add(id, piece, -1);
+            if piece.king {
+                self.kings[king_index(piece.player)] -= 1;
+            }
+        }
+        if let Square::Taken(piece) = square {
+            self.zobrist.flip(id, piece.id());
+            self.features[king_index(piece.player)].add(id, piece, 1);
+            if piece.king {
+                self.kings[king_index(piece.player)] += 1;
+            }
+        }
+        self.squares[id] = square;
+    }
+
+    // Rebuild the Zobrist hash of the board from scratch.
+    pub fn recompute_hash(&self) -> u128 {
+        let mut hash = 0;
+        for id in VALID_SQUARES {
+            if let Square::Taken(piece) = self.squares[id] {
+                hash ^= zobrist_keys().randoms[id][piece.id()];
+            }
+        }
+        if self.zobrist.passed {
+            hash ^= zobrist_keys().side;
+        }
+        hash
+    }
+
+    // Pass the turn without moving a piece, e.g. for null-move pruning. The board does not track
+    // the side to move, but the turn component of the hash is flipped so that the position with
+    // the turn passed is not confused with the same pieces before the pass.
+    #[allow(dead_code)]
+    pub fn make_null_move(&mut self) {
+        self.zobrist.hash ^= zobrist_keys().side;
+        self.zobrist.passed = !self.zobrist.passed;
+    }
+
+    // Take back a pass made with [Board::make_null_move].
+    #[allow(dead_code)]
+    pub fn unmake_null_move(&mut self) {
+        self.make_null_move();
+    }
+
+    // Returns true if the incrementally updated hash matches the board.
+    pub fn check_hash(&self) -> bool {
+        self.recompute_hash() == self.zobrist.hash
+    }
+
+    // The piece features of `player`, kept up to date incrementally.
+    pub fn features(&self, player: Player) -> PieceFeatures {
+        self.features[king_index(player)]
+    }
+
+    // Count the piece features of both players by scanning the board, indexed by [king_index].
+    pub fn compute_features(&self) -> [PieceFeatures; 2] {
+        let mut features = [PieceFeatures::default(); 2];
+        for id in VALID_SQUARES {
+            if let Square::Taken(piece) = self.squares[id] {
+                features[king_index(piece.player)].add(id, piece, 1);
+            }
+        }
+        features
+    }
+
+    // Returns true if the incrementally updated piece features match the board.
+    pub fn check_features(&self) -> bool {
+        self.compute_features() == self.features
+    }
+
+    // If the two boards have the same pieces on the same squares, compared square by square
+    // rather than by hash, e.g. to check that distinct positions do not share a hash.
+    #[allow(dead_code)]
+    pub fn position_eq(&self, other: &Board) -> bool {
+        self.squares == other.squares
+    }
+
+    // Collect the hashes of every distinct position reachable within `depth` plies, starting
+    // with `player` to move and including the current position. The hash only covers the pieces
+    // on the board, so the same arrangement reached with either side to move counts once.
+    #[cfg(test)]
+    pub fn reachable_hashes(
+        &mut self,
         player: Player,
-        piece: Piece,
-        id: usize,
-        start: usize,
-        prev_jumped: &mut Vec<usize>,
-    ) -> Vec<Movement> {
-        let mut movements = Vec::new();
-        for m in piece.movements() {
-            let id_jumped = (id as i32 + m) as usize;
-            let id_to = (id_jumped as i32 + m) as usize;
-            if prev_jumped.iter().any(|j| *j == id_jumped) {
-                continue;
+        depth: u32,
+    ) -> std::collections::HashSet<u128> {
+        let mut hashes = std::collections::HashSet::new();
+        self.visit_reachable(player, depth, &mut |board| {
+            hashes.insert(board.hash());
+        });
+        hashes
+    }
+
+    // Call `visit` on every position reachable within `depth` plies, starting with `player` to
+    // move and including the current position. As in the perft counts of the tests, pawns
+    // reaching the king row are not crowned.
+    #[cfg(test)]
+    fn visit_reachable(&mut self, player: Player, depth: u32, visit: &mut dyn FnMut(&Board)) {
+        visit(self);
+        if depth == 0 {
+            return;
+        }
+        for movement in self.movements(player) {
+            self.do_movement(&movement);
+            self.visit_reachable(player.other(), depth - 1, visit);
+            self.undo_movement(&movement);
+        }
+    }
+
+    // The legal movements of `player`. When captures are forced, whether a jump exists is
+    // checked with [Board::legal_jump_exists] first, so the multi-jump chains are only built
+    // when they are the movements returned.
+    pub fn movements(&self, player: Player) -> Vec<Movement> {
+        if !self.rules.captures_forced {
+            let mut movements = self.jump_moves(player);
+            movements.append(&mut self.simple_moves(player));
+            return movements;
+        }
+        if self.legal_jump_exists(player) {
+            return self.jump_moves(player);
+        }
+        self.simple_moves(player)
+    }
+
+    // Returns true if `movement` is one of the legal movements of `player`. Only the movements
+    // of the moving piece are generated, plus a check for a forced jump for a simple movement.
+    pub fn is_legal(&self, movement: &Movement, player: Player) -> bool {
+        let piece = match (movement.from.piece, self.squares[movement.from.id]) {
+            (Some(moving), Square::Taken(piece)) if moving == piece && piece.player == player => {
+                piece
+            }
+            _ => return false,
+        };
+        if movement.is_jump() {
+            return self
+                .jump_moves_at(
+                    player,
+                    piece,
+                    movement.from.id,
+                    movement.from.id,
+                    &mut Vec::new(),
+                )
+                .contains(movement);
+        }
+        if self.rules.captures_forced && self.legal_jump_exists(player) {
+            return false;
+        }
+        let step = movement.to.id as i32 - movement.from.id as i32;
+        piece.movements().contains(&step)
+            && self.squares[movement.to.id] == Square::Empty
+            && *movement == Movement::simple(movement.from, SquareState::empty(movement.to.id))
+    }
+
+    // Count the legal movements of `player` by kind. When captures are forced and a jump exists
+    // there are no simple movements.
+    #[allow(dead_code)]
+    pub fn move_summary(&self, player: Player) -> MoveSummary {
+        let mut summary = MoveSummary::default();
+        for movement in self.legal_moves(player) {
+            if movement.is_multi_jump() {
+                summary.multi_jumps += 1;
+            } else if movement.is_single_jump() {
+                summary.single_jumps += 1;
+            } else {
+                summary.simple += 1;
+            }
+        }
+        summary
+    }
+
+    // A lazy alternative to [Board::movements] for callers that only walk the movements once.
+    // Whether a jump exists is checked up front, then only the allowed kind of movement is
+    // generated as the iterator is consumed.
+    pub fn legal_moves(&self, player: Player) -> impl Iterator<Item = Movement> + '_ {
+        let jumps = !self.rules.captures_forced || self.legal_jump_exists(player);
+        let simple = !self.rules.captures_forced || !jumps;
+        let jump_moves = jumps.then(|| {
+            self.pieces_of(player).flat_map(move |(id, piece)| {
+                dedup_jumps(self.jump_moves_at(player, piece, id, id, &mut Vec::new()))
+            })
+        });
+        let simple_moves = simple.then(|| {
+            self.pieces_of(player).flat_map(move |(id, piece)| {
+                piece.movements().iter().filter_map(move |m| {
+                    let id_to = (id as i32 + m) as usize;
+                    (Square::Empty == self.squares[id_to]).then(|| {
+                        Movement::simple(SquareState::piece(id, piece), SquareState::empty(id_to))
+                    })
+                })
+            })
+        });
+        jump_moves
+            .into_iter()
+            .flatten()
+            .chain(simple_moves.into_iter().flatten())
+    }
+
+    // The squares and pieces of `player`.
+    fn pieces_of(&self, player: Player) -> impl Iterator<Item = (usize, Piece)> + '_ {
+        VALID_SQUARES.into_iter().filter_map(move |id| {
+            self.piece_at(id)
+                .filter(|piece| piece.player == player)
+                .map(|piece| (id, piece))
+        })
+    }
+
+    // Returns true if `player` has any jump available, without generating the movements.
+    pub fn legal_jump_exists(&self, player: Player) -> bool {
+        for id in VALID_SQUARES {
+            if let Square::Taken(piece) = self.squares[id] {
+                if piece.player == player {
+                    for m in piece.movements() {
+                        let id_jumped = (id as i32 + m) as usize;
+                        let id_to = (id_jumped as i32 + m) as usize;
+                        if let Square::Taken(jumped_piece) = self.squares[id_jumped] {
+                            if jumped_piece.player != player && Square::Empty == self.squares[id_to]
+                            {
+                                return true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // Returns true if neither player has a jump available, so that the position can be
+    // evaluated without a pending exchange distorting the score.
+    pub fn is_quiet(&self) -> bool {
+        !self.legal_jump_exists(Player::Player1) && !self.legal_jump_exists(Player::Player2)
+    }
+
+    // Returns the squares of the pieces of `by` that can jump the opposing piece on `square`.
+    pub fn attackers_of(&self, square: SquareId, by: Player) -> Vec<SquareId> {
+        let id = square.board_id();
+        let mut attackers = Vec::new();
+        match self.squares[id] {
+            Square::Taken(piece) if piece.player != by => {}
+            _ => return attackers,
+        }
+        for m in [-4, -5, 4, 5] {
+            let id_from = (id as i32 - m) as usize;
+            let id_to = (id as i32 + m) as usize;
+            if let Square::Taken(attacker) = self.squares[id_from] {
+                if attacker.player == by
+                    && attacker.movements().contains(&m)
+                    && Square::Empty == self.squares[id_to]
+                {
+                    attackers.extend(SquareId::from_board_id(id_from));
+                }
+            }
+        }
+        attackers
+    }
+
+    // Returns the squares that `piece` standing on square `id` attacks: the squares next to it in
+    // the directions it moves whose square beyond is empty, so that an opposing piece on them
+    // could be jumped. The squares are returned whether or not an opposing piece stands on them.
+    pub fn attacks_from(&self, piece: Piece, id: usize) -> impl Iterator<Item = usize> + '_ {
+        piece.movements().iter().filter_map(move |m| {
+            let attacked = (id as i32 + m) as usize;
+            if self.squares[attacked] == Square::Invalid {
+                return None;
+            }
+            let land = (attacked as i32 + m) as usize;
+            (self.squares[land] == Square::Empty).then_some(attacked)
+        })
+    }
+
+    // Returns the squares attacked by the pieces of `by` (see [Board::attacks_from]), indexed by
+    // [Board] id. An opposing piece on an attacked square can be jumped, and one moving there
+    // could be jumped on the next move.
+    pub fn threat_map(&self, by: Player) -> [bool; 46] {
+        let mut threats = [false; 46];
+        for id in VALID_SQUARES {
+            if let Square::Taken(piece) = self.squares[id] {
+                if piece.player == by {
+                    for attacked in self.attacks_from(piece, id) {
+                        threats[attacked] = true;
+                    }
+                }
+            }
+        }
+        threats
+    }
+
+    // Returns the landing squares of the next hops of a capture in progress, for entering a
+    // multi-jump one hop at a time. The hops made so far must have been played on the board so
+    // that the jumping piece of `player` stands on `square`. The pieces on `already_jumped`
+    // cannot be jumped again, whether or not they have been removed from the board yet. An empty
+    // result means the capture is complete.
+    #[allow(dead_code)]
+    pub fn jump_targets_from(
+        &self,
+        square: SquareId,
+        player: Player,
+        already_jumped: &[SquareId],
+    ) -> Vec<SquareId> {
+        let id = square.board_id();
+        let piece = match self.squares[id] {
+            Square::Taken(piece) if piece.player == player => piece,
+            _ => return Vec::new(),
+        };
+        let mut jumped = already_jumped.iter().map(|s| s.board_id()).collect();
+        let mut targets: Vec<SquareId> = self
+            .jump_moves_at(player, piece, id, id, &mut jumped)
+            .iter()
+            .filter_map(|movement| SquareId::from_board_id(movement.to.id))
+            .collect();
+        targets.dedup();
+        targets
+    }
+
+    // Returns true if the capture in progress of the piece of `player` that landed on square
+    // `landing` must go on, see [Board::jump_targets_from]. A multi-jump has to be completed, so
+    // while this holds the turn cannot end.
+    #[allow(dead_code)]
+    pub fn must_continue_jump(
+        &self,
+        landing: SquareId,
+        player: Player,
+        already_jumped: &[SquareId],
+    ) -> bool {
+        !self
+            .jump_targets_from(landing, player, already_jumped)
+            .is_empty()
+    }
+
+    // Returns how many pieces of `player` the opponent could jump right after `player` makes
+    // `movement`. The board is left unchanged.
+    pub fn pieces_hanging_after(&mut self, movement: &Movement, player: Player) -> usize {
+        self.simulate(movement, player, |board| {
+            SquareId::all()
+                .filter(|square| !board.attackers_of(*square, player.other()).is_empty())
+                .count()
+        })
+    }
+
+    // When every legal movement of `player` is a capture, returns the change in material, in
+    // pieces from the perspective of `player`, once the best of the captures has been answered:
+    // both sides keep capturing, as well as they can, for as long as they have a capture, up to
+    // [SHOT_PLIES] plies. A negative result means the opponent has set up a shot, offering pieces
+    // to win more back, as in the 2-for-1 and 3-for-2 shots. Returns `None` when `player` is not
+    // forced to capture. The board is left unchanged.
+    pub fn forced_loss_after_capture(&mut self, player: Player) -> Option<i32> {
+        if !self.rules.captures_forced || !self.legal_jump_exists(player) {
+            return None;
+        }
+        Some(self.exchange(player, SHOT_PLIES))
+    }
+
+    // The best material change for `player`, in pieces, from capturing while it has a capture,
+    // with the opponent doing the same in reply.
+    fn exchange(&mut self, player: Player, plies: u32) -> i32 {
+        if plies == 0 || !self.legal_jump_exists(player) {
+            return 0;
+        }
+        let mut best = i32::MIN;
+        for movement in self.jump_moves(player) {
+            let undo = self.do_move(&movement, player);
+            let swing = movement.captures() as i32 - self.exchange(player.other(), plies - 1);
+            self.undo(undo);
+            best = best.max(swing);
+        }
+        best
+    }
+
+    // The number of pawns of `player` that could be crowned within `plies` moves of their own if
+    // the opponent stood still, a lookahead for valuing advanced pawns. Each pawn is searched on
+    // its own, stepping or jumping as the rules allow with every other piece left in place. The
+    // search is bounded by [ROWS] plies, as no pawn needs more moves than that.
+    #[allow(dead_code)]
+    pub fn promotion_potential(&mut self, player: Player, plies: u32) -> u32 {
+        let plies = plies.min(ROWS as u32);
+        let pawns: Vec<(usize, Piece)> = self
+            .pieces_of(player)
+            .filter(|(_, piece)| !piece.king)
+            .collect();
+        pawns
+            .into_iter()
+            .filter(|&(id, piece)| self.can_promote(player, piece, id, plies))
+            .count() as u32
+    }
+
+    // If the pawn `piece` on `id` can reach the king row within `plies` moves of its own.
+    fn can_promote(&mut self, player: Player, piece: Piece, id: usize, plies: u32) -> bool {
+        if plies == 0 {
+            return false;
+        }
+        let mut movements = dedup_jumps(self.jump_moves_at(player, piece, id, id, &mut Vec::new()));
+        if movements.is_empty() || !self.rules.captures_forced {
+            for m in piece.movements() {
+                let to = (id as i32 + m) as usize;
+                if self.squares[to] == Square::Empty {
+                    movements.push(Movement::simple(
+                        SquareState::piece(id, piece),
+                        SquareState::empty(to),
+                    ));
+                }
+            }
+        }
+        movements.iter().any(|movement| {
+            let undo = self.do_move(movement, player);
+            let promotes = undo.promotion().crowned.is_some()
+                || self.can_promote(player, piece, movement.end().id, plies - 1);
+            self.undo(undo);
+            promotes
+        })
+    }
+
+    // The legal replies of the opponent after `player` makes `movement`, for looking ahead at
+    // shots and traps. The board is left as it was.
+    #[allow(dead_code)]
+    pub fn replies_to(&mut self, movement: &Movement, player: Player) -> Vec<Movement> {
+        self.simulate(movement, player, |board| {
+            board.legal_moves(player.other()).collect()
+        })
+    }
+
+    // Run `f` on the board after `player` makes `movement`, then undo the movement. The movement
+    // is undone even if `f` panics, so the board is always left as it was.
+    pub fn simulate<R>(
+        &mut self,
+        movement: &Movement,
+        player: Player,
+        f: impl FnOnce(&Board) -> R,
+    ) -> R {
+        debug_assert_eq!(movement.from.piece.map(|piece| piece.player), Some(player));
+        self.do_movement(movement);
+        let guard = UndoGuard {
+            board: self,
+            movement,
+        };
+        f(guard.board)
+    }
+
+    fn simple_moves(&self, player: Player) -> Vec<Movement> {
+        let mut movements = Vec::new();
+        for (id, piece) in self.pieces_of(player) {
+            for m in piece.movements() {
+                let id_to = (id as i32 + m) as usize;
+                if Square::Empty == self.squares[id_to] {
+                    let from = SquareState::piece(id, piece);
+                    let to = SquareState::empty(id_to);
+                    let movement = Movement::simple(from, to);
+                    movements.push(movement);
+                }
+            }
+        }
+        movements
+    }
+
+    fn jump_moves(&self, player: Player) -> Vec<Movement> {
+        let mut movements = Vec::new();
+        for (id, piece) in self.pieces_of(player) {
+            let mut jumps = dedup_jumps(self.jump_moves_at(player, piece, id, id, &mut Vec::new()));
+            movements.append(&mut jumps);
+        }
+        movements
+    }
+
+    fn jump_moves_at(
+        &self,
+        player: Player,
+        piece: Piece,
+        id: usize,
+        start: usize,
+        prev_jumped: &mut Vec<usize>,
+    ) -> Vec<Movement> {
+        // every hop captures a different piece, so a jump ends at the latest once it has captured
+        // all of the opponent's pieces
+        let features = self.features(player.other());
+        let max_captures = prev_jumped.len() + (features.pawns + features.kings) as usize;
+        self.jump_moves_within(player, piece, id, start, prev_jumped, max_captures)
+    }
+
+    // The jumps of [Board::jump_moves_at], capturing at most `max_captures` pieces in all.
+    fn jump_moves_within(
+        &self,
+        player: Player,
+        piece: Piece,
+        id: usize,
+        start: usize,
+        prev_jumped: &mut Vec<usize>,
+        max_captures: usize,
+    ) -> Vec<Movement> {
+        let mut movements = Vec::new();
+        if prev_jumped.len() >= max_captures {
+            return movements;
+        }
+        for m in piece.movements() {
+            let id_jumped = (id as i32 + m) as usize;
+            let id_to = (id_jumped as i32 + m) as usize;
+            if prev_jumped.contains(&id_jumped) {
+                continue;
+            }
+            if let Square::Taken(jumped_piece) = self.squares[id_jumped] {
+                // the start square is empty once the piece has left it, which allows a king to
+                // finish a circular multi-jump where it began
+                if jumped_piece.player != player
+                    && (Square::Empty == self.squares[id_to] || id_to == start)
+                {
+                    let from = SquareState::piece(id, piece);
+                    let to = SquareState::empty(id_to);
+                    let jumped = SquareState::piece(id_jumped, jumped_piece);
+                    prev_jumped.push(id_jumped);
+                    let multi_jumps = self.jump_moves_within(
+                        player,
+                        piece,
+                        id_to,
+                        start,
+                        prev_jumped,
+                        max_captures,
+                    );
+                    prev_jumped.pop();
+                    if multi_jumps.is_empty() {
+                        let movement = Movement::jump(from, to, jumped);
+                        movements.push(movement);
+                    } else {
+                        for mj in multi_jumps {
+                            let movement = Movement::multi_jump(from, to, jumped, Box::new(mj));
+                            movements.push(movement);
+                        }
+                    }
+                }
+            }
+        }
+        movements
+    }
+
+    // Change the board state based on the given [Movement]. Updates the [ZobristHash]. This is
+    // the unchecked fast path used by the search, so the movement must be one generated for this
+    // board; movements from outside the engine go through [Board::do_movement_checked].
+    pub fn do_movement(&mut self, movement: &Movement) {
+        let piece = movement.from.piece.unwrap();
+        // a movement generated for another board would corrupt the board and its hash
+        debug_assert_eq!(self.squares[movement.from.id], Square::Taken(piece));
+        self.squares[movement.to.id] = self.squares[movement.from.id];
+        self.zobrist.flip(movement.to.id, piece.id());
+        self.squares[movement.from.id] = Square::Empty;
+        self.zobrist.flip(movement.from.id, piece.id());
+        let features = &mut self.features[king_index(piece.player)];
+        features.add(movement.from.id, piece, -1);
+        features.add(movement.to.id, piece, 1);
+        if let Some(jumped_state) = &movement.jumped {
+            let jumped = jumped_state.piece.unwrap();
+            debug_assert_eq!(self.squares[jumped_state.id], Square::Taken(jumped));
+            self.squares[jumped_state.id] = Square::Empty;
+            self.zobrist.flip(jumped_state.id, jumped.id());
+            self.features[king_index(jumped.player)].add(jumped_state.id, jumped, -1);
+            if jumped.king {
+                self.kings[king_index(jumped.player)] -= 1;
+            }
+            if let Some(next_movement) = &movement.next {
+                self.do_movement(next_movement);
+            }
+        }
+        debug_assert!(self.check_hash());
+        debug_assert!(self.check_features());
+    }
+
+    // Undo the board state based on the given [Movement]. Updates the [ZobristHash].
+    pub fn undo_movement(&mut self, movement: &Movement) {
+        if let Some(next_movement) = &movement.next {
+            self.undo_movement(next_movement);
+        }
+        let piece = movement.from.piece.unwrap();
+        self.squares[movement.from.id] = self.squares[movement.to.id];
+        self.zobrist.flip(movement.from.id, piece.id());
+        self.squares[movement.to.id] = Square::Empty;
+        self.zobrist.flip(movement.to.id, piece.id());
+        let features = &mut self.features[king_index(piece.player)];
+        features.add(movement.to.id, piece, -1);
+        features.add(movement.from.id, piece, 1);
+        if let Some(jumped_state) = &movement.jumped {
+            let jumped = jumped_state.piece.unwrap();
+            self.squares[jumped_state.id] = Square::Taken(jumped);
+            self.zobrist.flip(jumped_state.id, jumped.id());
+            self.features[king_index(jumped.player)].add(jumped_state.id, jumped, 1);
+            if jumped.king {
+                self.kings[king_index(jumped.player)] += 1;
+            }
+        }
+        debug_assert!(self.check_hash());
+        debug_assert!(self.check_features());
+    }
+
+    // A mask of the occupied squares, with bit `n - 1` set when the square with the standard
+    // number `n` holds a piece. Only the low 32 bits are used.
+    #[allow(dead_code)]
+    pub fn occupied_mask(&self) -> u64 {
+        let mut mask = 0;
+        for id in VALID_SQUARES {
+            if let Square::Taken(_) = self.squares[id] {
+                mask |= 1 << (square_number(id) - 1);
+            }
+        }
+        mask
+    }
+
+    // A mask of the empty squares, laid out like [Board::occupied_mask].
+    #[allow(dead_code)]
+    pub fn empty_mask(&self) -> u64 {
+        !self.occupied_mask() & 0xffff_ffff
+    }
+
+    pub fn piece_count(&self) -> (u8, u8) {
+        let mut p1 = 0;
+        let mut p2 = 0;
+        for id in VALID_SQUARES {
+            if let Square::Taken(piece) = self.squares[id] {
+                if piece.player == Player::Player1 {
+                    p1 += 1;
+                } else {
+                    p2 += 1;
+                }
+            }
+        }
+        (p1, p2)
+    }
+
+    // The phase of the game from the material left on the board, from 0.0 with the 24 pieces of
+    // the opening to 1.0 with the board empty. Kings count as much material as pawns.
+    pub fn phase(&self) -> f32 {
+        let (p1, p2) = self.piece_count();
+        let pieces = (p1 + p2) as i32;
+        (STARTING_PIECES - pieces).max(0) as f32 / STARTING_PIECES as f32
+    }
+
+    // The number of kings of `player`, kept up to date incrementally.
+    pub fn king_count(&self, player: Player) -> u8 {
+        self.kings[king_index(player)]
+    }
+
+    // Crown every pawn standing on its king row. Returns the number of pawns crowned. Games crown
+    // as they move with [Board::apply_and_promote]; this is kept for tests that place pawns.
+    #[cfg(test)]
+    #[must_use]
+    pub fn mark_kings(&mut self) -> u32 {
+        let mut kings = 0;
+        for id in promotion_squares(Player::Player1).chain(promotion_squares(Player::Player2)) {
+            if self.crown(id) {
+                kings += 1;
+            }
+        }
+        debug_assert!(self.check_hash());
+        kings
+    }
+
+    // Crown the pawn at `id` if it has reached the opponent's back row. Updates the
+    // [ZobristHash]. Returns true if a pawn was crowned.
+    fn crown(&mut self, id: usize) -> bool {
+        if let Square::Taken(piece) = self.squares[id] {
+            if !piece.king && is_promotion_square(id, piece.player) {
+                let king = piece.promote();
+                self.squares[id] = Square::Taken(king);
+                self.zobrist.flip(id, piece.id());
+                self.zobrist.flip(id, king.id());
+                let features = &mut self.features[king_index(piece.player)];
+                features.add(id, piece, -1);
+                features.add(id, king, 1);
+                self.kings[king_index(piece.player)] += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    // Resolve a movement for `player` written in standard notation, e.g. `11-15` or `11x18x25`.
+    // A multi-jump may be abbreviated to its start and end squares (`11x25`) when only one legal
+    // jump connects them. Every route of a jump is accepted, including those [Board::movements]
+    // leaves out for capturing the same pieces as another.
+    pub fn parse_move(&self, player: Player, s: &str) -> Result<Movement, MoveError> {
+        let s = s.trim();
+        let jump = s.contains('x');
+        let numbers = s
+            .split(if jump { 'x' } else { '-' })
+            .map(|n| n.parse::<usize>().ok().and_then(square_id))
+            .collect::<Option<Vec<usize>>>()
+            .ok_or(MoveError::Malformed)?;
+        if numbers.len() < 2 || (!jump && numbers.len() > 2) {
+            return Err(MoveError::Malformed);
+        }
+
+        let movements = if jump {
+            self.pieces_of(player)
+                .flat_map(|(id, piece)| self.jump_moves_at(player, piece, id, id, &mut Vec::new()))
+                .collect()
+        } else if self.rules.captures_forced && self.legal_jump_exists(player) {
+            Vec::new()
+        } else {
+            self.simple_moves(player)
+        };
+        let matches = movements.into_iter().filter(|m| {
+            let mut path = vec![m.from.id];
+            let mut movement = Some(m);
+            while let Some(m) = movement {
+                path.push(m.to.id);
+                movement = m.next.as_deref();
+            }
+            m.is_jump() == jump
+                && (path == numbers
+                    || (numbers.len() == 2
+                        && path[0] == numbers[0]
+                        && path[path.len() - 1] == numbers[1]))
+        });
+        // the matches all start from the same square, so the routes of the same jump are dropped
+        let mut matches = dedup_jumps(matches.collect()).into_iter();
+        match (matches.next(), matches.next()) {
+            (Some(m), None) => Ok(m),
+            (Some(_), Some(_)) => Err(MoveError::Ambiguous),
+            (None, _) => Err(MoveError::Illegal),
+        }
+    }
+
+    // Play the movement of `player` written in standard notation (see [Board::parse_move]) on a
+    // copy of the board, crowning the moving piece if it reaches the king row. The board does not
+    // track the side to move, so the copy is returned along with the side to move next, which
+    // lets calls chain:
+    //
+    //      let (board, player) = board.play(player, "11-15")?;
+    //      let (board, player) = board.play(player, "23-19")?;
+    #[allow(dead_code)]
+    pub fn play(&self, player: Player, notation: &str) -> Result<(Board, Player), MoveError> {
+        let movement = self.parse_move(player, notation)?;
+        let mut board = self.clone();
+        board
+            .do_movement_checked(&movement, player)
+            .expect("parsed movements are legal");
+        Ok((board, player.other()))
+    }
+
+    // Describe `movement`, about to be played on the board, in words for logs and narration, with
+    // the squares named as in the terminal interface, e.g. `Player1 pawn moves C3 to D4` or
+    // `Player2 king captures 2 pieces from C3 ending on A5`.
+    #[allow(dead_code)]
+    pub fn describe_move(&self, movement: &Movement) -> String {
+        let piece = match self.squares[movement.from.id] {
+            Square::Taken(piece) => piece,
+            _ => movement.from.piece.expect("movements start from a piece"),
+        };
+        let player = match piece.player {
+            Player::Player1 => "Player1",
+            Player::Player2 => "Player2",
+        };
+        let kind = if piece.king { "king" } else { "pawn" };
+        let from = square_name(movement.from.id).to_ascii_uppercase();
+        let end = movement.end().id;
+        let to = square_name(end).to_ascii_uppercase();
+        let mut description = match movement.captures() {
+            0 => format!("{} {} moves {} to {}", player, kind, from, to),
+            1 => format!(
+                "{} {} captures 1 piece from {} ending on {}",
+                player, kind, from, to
+            ),
+            n => format!(
+                "{} {} captures {} pieces from {} ending on {}",
+                player, kind, n, from, to
+            ),
+        };
+        if !piece.king && is_promotion_square(end, piece.player) {
+            description.push_str(" and is crowned");
+        }
+        description
+    }
+
+    // Validate that `movement` is legal for `player`, then apply it and crown the moving piece
+    // if it reaches the king row. The board is left untouched when the movement is illegal.
+    // The returned [PromotionInfo] allows the movement to be undone with
+    // [Board::undo_movement_checked].
+    #[must_use = "the movement is not applied when it is illegal"]
+    pub fn do_movement_checked(
+        &mut self,
+        movement: &Movement,
+        player: Player,
+    ) -> Result<PromotionInfo, IllegalMove> {
+        if !self.is_legal(movement, player) {
+            return Err(IllegalMove);
+        }
+        self.do_movement(movement);
+        Ok(self.crown_after(movement))
+    }
+
+    // Crown the piece moved by `movement` if it has just reached the king row.
+    fn crown_after(&mut self, movement: &Movement) -> PromotionInfo {
+        let end = movement.end().id;
+        let crowned = if self.crown(end) { Some(end) } else { None };
+        PromotionInfo { crowned }
+    }
+
+    // Apply `movement` for `player`, crowning the moved piece if it reaches the king row. The
+    // returned [Undo] holds everything needed to revert the movement with [Board::undo], so the
+    // caller does not have to keep the movement around. Like [Board::do_movement], the movement
+    // must be one generated for this board.
+    #[allow(dead_code)]
+    pub fn do_move(&mut self, movement: &Movement, player: Player) -> Undo {
+        self.do_movement(movement);
+        let promotion = self.crown_after(movement);
+        Undo {
+            movement: movement.clone(),
+            player,
+            promotion,
+        }
+    }
+
+    // Apply `movement` for `player` and crown the moved piece if it reaches the king row, keeping
+    // the [ZobristHash] up to date. Returns true if a pawn was crowned. Prefer this to
+    // [Board::do_movement] when playing a game, so that a promotion can not be forgotten. Like
+    // [Board::do_movement], the movement must be one generated for this board.
+    pub fn apply_and_promote(&mut self, movement: &Movement, player: Player) -> bool {
+        debug_assert_eq!(movement.from.piece.map(|p| p.player), Some(player));
+        self.do_movement(movement);
+        self.crown_after(movement).crowned.is_some()
+    }
+
+    // Revert a movement applied with [Board::do_move], including any promotion.
+    #[allow(dead_code)]
+    pub fn undo(&mut self, undo: Undo) {
+        self.undo_movement_checked(&undo.movement, undo.promotion);
+    }
+
+    // Count the leaf nodes of the move tree of `player` `depth` plies deep, with the number of
+    // them reached by a capture, a promotion and a multi-jump. Pawns are crowned as in a game.
+    #[allow(dead_code)]
+    pub fn perft_detailed(&mut self, player: Player, depth: u32) -> PerftStats {
+        let mut stats = PerftStats::default();
+        for movement in self.movements(player) {
+            let undo = self.do_move(&movement, player);
+            if depth <= 1 {
+                stats.nodes += 1;
+                stats.captures += movement.is_jump() as u64;
+                stats.promotions += undo.promotion().crowned.is_some() as u64;
+                stats.multi_jumps += movement.is_multi_jump() as u64;
+            } else {
+                let child = self.perft_detailed(player.other(), depth - 1);
+                stats.nodes += child.nodes;
+                stats.captures += child.captures;
+                stats.promotions += child.promotions;
+                stats.multi_jumps += child.multi_jumps;
+            }
+            self.undo(undo);
+        }
+        stats
+    }
+
+    // Undo a movement applied with [Board::do_movement_checked], including any promotion.
+    pub fn undo_movement_checked(&mut self, movement: &Movement, info: PromotionInfo) {
+        if let Some(id) = info.crowned {
+            if let Square::Taken(king) = self.squares[id] {
+                let pawn = king.demote();
+                self.squares[id] = Square::Taken(pawn);
+                self.zobrist.flip(id, king.id());
+                self.zobrist.flip(id, pawn.id());
+                let features = &mut self.features[king_index(king.player)];
+                features.add(id, king, -1);
+                features.add(id, pawn, 1);
+                self.kings[king_index(king.player)] -= 1;
+            }
+        }
+        self.undo_movement(movement);
+    }
+}
+
+impl TryFrom<[Square; 46]> for Board {
+    type Error = SquaresError;
+
+    fn try_from(squares: [Square; 46]) -> Result<Self, Self::Error> {
+        Board::from_squares(squares)
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(
+            f,
+            "1  |   | {} |   | {} |   | {} |   | {} |",
+            self.squares[37], self.squares[38], self.squares[39], self.squares[40]
+        )?;
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(
+            f,
+            "2  | {} |   | {} |   | {} |   | {} |   |",
+            self.squares[32], self.squares[33], self.squares[34], self.squares[35]
+        )?;
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(
+            f,
+            "3  |   | {} |   | {} |   | {} |   | {} |",
+            self.squares[28], self.squares[29], self.squares[30], self.squares[31]
+        )?;
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(
+            f,
+            "4  | {} |   | {} |   | {} |   | {} |   |",
+            self.squares[23], self.squares[24], self.squares[25], self.squares[26]
+        )?;
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(
+            f,
+            "5  |   | {} |   | {} |   | {} |   | {} |",
+            self.squares[19], self.squares[20], self.squares[21], self.squares[22]
+        )?;
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(
+            f,
+            "6  | {} |   | {} |   | {} |   | {} |   |",
+            self.squares[14], self.squares[15], self.squares[16], self.squares[17]
+        )?;
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(
+            f,
+            "7  |   | {} |   | {} |   | {} |   | {} |",
+            self.squares[10], self.squares[11], self.squares[12], self.squares[13]
+        )?;
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(
+            f,
+            "8  | {} |   | {} |   | {} |   | {} |   |",
+            self.squares[5], self.squares[6], self.squares[7], self.squares[8]
+        )?;
+        writeln!(f, "   ---------------------------------")?;
+        writeln!(f, "     A   B   C   D   E   F   G   H")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    use crate::human::MovementMap;
+
+    use super::*;
+
+    // Count the leaf nodes of the move tree `depth` plies deep.
+    fn perft(board: &mut Board, player: Player, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for movement in board.movements(player) {
+            board.do_movement(&movement);
+            nodes += perft(board, player.other(), depth - 1);
+            board.undo_movement(&movement);
+        }
+        nodes
+    }
+
+    #[test]
+    fn test_masks() {
+        let positions = [
+            Board::new(),
+            Board::from_fen("B:W18,K27:B9,14").unwrap().0,
+            Board::from_fen("B:W14,15,22,23:BK10").unwrap().0,
+            Board::empty(),
+        ];
+        for board in positions {
+            let (p1, p2) = board.piece_count();
+            assert_eq!(board.occupied_mask().count_ones(), (p1 + p2) as u32);
+            assert_eq!(board.occupied_mask() & board.empty_mask(), 0);
+            assert_eq!(board.occupied_mask() | board.empty_mask(), 0xffff_ffff);
+        }
+        // only squares 13-20 are empty at the start
+        assert_eq!(Board::new().empty_mask(), 0b1111_1111 << 12);
+    }
+
+    #[test]
+    fn test_move_summary() {
+        let summary = Board::new().move_summary(Player::Player1);
+        assert_eq!(
+            summary,
+            MoveSummary {
+                simple: 7,
+                single_jumps: 0,
+                multi_jumps: 0,
+            }
+        );
+
+        // 9x18x25 and 2x11 are the only movements
+        let (board, player) = Board::from_fen("B:W7,14,22:B2,9").unwrap();
+        let summary = board.move_summary(player);
+        assert_eq!(
+            summary,
+            MoveSummary {
+                simple: 0,
+                single_jumps: 1,
+                multi_jumps: 1,
+            }
+        );
+        let optional = board.with_rules(Rules {
+            captures_forced: false,
+            ..Rules::default()
+        });
+        assert!(optional.move_summary(player).simple > 0);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let board = Board::new();
+        let simple = board.parse_move(Player::Player1, "11-15").unwrap();
+        let (board, player) = Board::from_fen("B:W18:B14").unwrap();
+        let jump = board.parse_move(player, "14x23").unwrap();
+        let (board, player) = Board::from_fen("B:W14,15,22,23:BK10").unwrap();
+        let circle = board.parse_move(player, "10x19x26x17x10").unwrap();
+        for movement in [simple, jump, circle] {
+            let bytes = movement.encode();
+            assert_eq!(bytes.len(), 6 * movement.captures().max(1));
+            assert_eq!(Movement::decode(&bytes), Ok(movement));
+        }
+
+        assert_eq!(Movement::decode(&[]), Err(DecodeError::Length(0)));
+        assert_eq!(
+            Movement::decode(&[11, 1, 15, 0, 0]),
+            Err(DecodeError::Length(5))
+        );
+        assert_eq!(
+            Movement::decode(&[11, 1, 33, 0, 0, 0]),
+            Err(DecodeError::Square(33))
+        );
+        assert_eq!(
+            Movement::decode(&[11, 5, 15, 0, 0, 0]),
+            Err(DecodeError::Piece(5))
+        );
+        // a jump continued by a simple step, a simple step continued by a jump, and a jump
+        // continued from another square
+        assert_eq!(
+            Movement::decode(&[14, 1, 23, 0, 18, 3, 23, 1, 27, 0, 0, 0]),
+            Err(DecodeError::Step(1))
+        );
+        assert_eq!(
+            Movement::decode(&[11, 1, 15, 0, 0, 0, 15, 1, 24, 0, 19, 3]),
+            Err(DecodeError::Step(1))
+        );
+        assert_eq!(
+            Movement::decode(&[14, 1, 23, 0, 18, 3, 22, 1, 31, 0, 26, 3]),
+            Err(DecodeError::Step(1))
+        );
+    }
+
+    #[test]
+    fn test_circular_king_jump() {
+        // the king can go around the four pieces either way, ending where it started; both ways
+        // leave the same position, so only one is generated but either is legal
+        let (mut board, player) = Board::from_fen("B:W14,15,22,23:BK10").unwrap();
+        let movements: Vec<String> = board
+            .movements(player)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(movements, ["10x19x26x17x10"]);
+        assert_eq!(board.legal_moves(player).count(), 1);
+        let mut played = board.clone();
+        let mut other_way = board.jump_moves_at(
+            player,
+            Piece::player1_king(),
+            square_id(10).unwrap(),
+            square_id(10).unwrap(),
+            &mut Vec::new(),
+        );
+        other_way.retain(|m| m.to_string() == "10x17x26x19x10");
+        assert!(played.do_movement_checked(&other_way[0], player).is_ok());
+        assert_eq!(played.piece_count(), (1, 0));
+        // jumps capturing different pieces are all kept
+        let (two_ways, player) = Board::from_fen("B:W14,15:BK10").unwrap();
+        let mut movements: Vec<String> = two_ways
+            .movements(player)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        movements.sort();
+        assert_eq!(movements, ["10x17", "10x19"]);
+        for movement in board.movements(player) {
+            assert_eq!(movement.captures(), 4);
+            assert_eq!(movement.notation(None), movement.to_string());
+            assert_eq!(movement.notation(Some(4)), movement.to_string());
+            assert_eq!(movement.notation(Some(2)), "10x ... x10 (4 captures)");
+            let hash = board.hash();
+            board.do_movement(&movement);
+            assert_eq!(board.piece_count(), (1, 0));
+            assert_eq!(
+                board.get(square_id(10).unwrap()),
+                Square::Taken(Piece::player1_king())
+            );
+            board.undo_movement(&movement);
+            assert_eq!(board.hash(), hash);
+        }
+    }
+
+    #[test]
+    fn test_maximal_king_jump() {
+        // nine pieces off the edges, with every square around them empty: the king can take all
+        // of them in six different orders, or stop on 28 after 3 or 5 captures
+        let (mut board, player) = Board::from_fen("B:W6,7,8,14,15,16,22,23,24:BK1").unwrap();
+        let id = square_id(1).unwrap();
+        let sequences = board.jump_moves_at(player, Piece::player1_king(), id, id, &mut Vec::new());
+        assert_eq!(sequences.len(), 9);
+        // sequences capturing the same pieces are generated once
+        let movements = board.movements(player);
+        let mut captures: Vec<usize> = movements.iter().map(|m| m.captures()).collect();
+        captures.sort();
+        assert_eq!(captures, [3, 5, 5, 9]);
+        let maximal = movements.iter().find(|m| m.captures() == 9).unwrap();
+        assert_eq!(maximal.to_string(), "1x10x3x12x19x10x17x26x19x28");
+        board.do_movement(maximal);
+        assert_eq!(board.piece_count(), (1, 0));
+    }
+
+    #[test]
+    fn test_threat_map() {
+        let (board, _) = Board::from_fen("B:W18,K27:B9,14").unwrap();
+        let threatened = |by| {
+            let threats = board.threat_map(by);
+            let mut numbers: Vec<usize> = VALID_SQUARES
+                .into_iter()
+                .filter(|&id| threats[id])
+                .map(square_number)
+                .collect();
+            numbers.sort();
+            numbers
+        };
+        // 18 cannot jump 14 as 9 is taken, and the king on 27 has nothing beyond 31 and 32
+        assert_eq!(threatened(Player::Player2), [15, 24]);
+        // a jump from 9 over 13 would leave the board and one over 14 lands on 18
+        assert_eq!(threatened(Player::Player1), [17, 18]);
+    }
+
+    #[test]
+    fn test_promotion_squares() {
+        let numbers = |player| {
+            let mut numbers: Vec<usize> = promotion_squares(player).map(square_number).collect();
+            numbers.sort();
+            numbers
+        };
+        assert_eq!(numbers(Player::Player1), [29, 30, 31, 32]);
+        assert_eq!(numbers(Player::Player2), [1, 2, 3, 4]);
+
+        // each side crowns on the other side's back row of a 10x10 board as well
+        assert_eq!(promotion_row(Player::Player1, 10), 9);
+        assert_eq!(promotion_row(Player::Player2, 10), 0);
+    }
+
+    #[test]
+    fn test_promote_demote() {
+        for king in [Piece::player1_king(), Piece::player2_king()] {
+            assert_eq!(king.demote().promote(), king);
+            assert_eq!(king.promote(), king);
+        }
+        assert_eq!(Piece::player1_pawn().promote(), Piece::player1_king());
+        assert_eq!(Piece::player2_king().demote(), Piece::player2_pawn());
+        assert_eq!(Piece::player2_pawn().demote(), Piece::player2_pawn());
+    }
+
+    #[test]
+    fn test_is_legal() {
+        let board = Board::new();
+        let simple = board.parse_move(Player::Player1, "11-15").unwrap();
+        assert!(board.is_legal(&simple, Player::Player1));
+        assert!(!board.is_legal(&simple, Player::Player2));
+        let backwards = Movement::simple(
+            SquareState::piece(square_id(11).unwrap(), Piece::player1_pawn()),
+            SquareState::empty(square_id(7).unwrap()),
+        );
+        assert!(!board.is_legal(&backwards, Player::Player1));
+
+        // 9-13 is a simple movement, but 14 has to jump 18
+        let (board, player) = Board::from_fen("B:W18:B9,14").unwrap();
+        let simple = Movement::simple(
+            SquareState::piece(square_id(9).unwrap(), Piece::player1_pawn()),
+            SquareState::empty(square_id(13).unwrap()),
+        );
+        assert!(!board.is_legal(&simple, player));
+        let jump = board.parse_move(player, "14x23").unwrap();
+        assert!(board.is_legal(&jump, player));
+        assert!(board
+            .clone()
+            .with_rules(Rules {
+                captures_forced: false,
+                ..Rules::default()
+            })
+            .is_legal(&simple, player));
+
+        // a single jump is not legal when it can continue
+        let (board, player) = Board::from_fen("B:W7,14,22:B2,9").unwrap();
+        let multi = board.parse_move(player, "9x18x25").unwrap();
+        assert!(board.is_legal(&multi, player));
+        let mut single = multi.clone();
+        single.next = None;
+        assert!(!board.is_legal(&single, player));
+        for movement in board.movements(player) {
+            assert!(board.is_legal(&movement, player));
+        }
+    }
+
+    #[test]
+    fn test_king_count() {
+        // the kings of `player` found by scanning the board
+        let scan = |board: &Board, player| {
+            VALID_SQUARES
+                .iter()
+                .filter(|id| match board.squares[**id] {
+                    Square::Taken(piece) => piece.player == player && piece.king,
+                    _ => false,
+                })
+                .count() as u8
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let (mut board, mut player) = Board::from_fen("B:W21-28,K1:B5-12,K32").unwrap();
+            let mut undos = Vec::new();
+            for _ in 0..100 {
+                let movements = board.movements(player);
+                let Some(movement) = movements.choose(&mut rng) else {
+                    break;
+                };
+                undos.push(board.do_move(movement, player));
+                for p in [Player::Player1, Player::Player2] {
+                    assert_eq!(board.king_count(p), scan(&board, p));
+                }
+                player = player.other();
+            }
+            while let Some(undo) = undos.pop() {
+                board.undo(undo);
+                for p in [Player::Player1, Player::Player2] {
+                    assert_eq!(board.king_count(p), scan(&board, p));
+                }
+            }
+            assert_eq!(board.king_count(Player::Player1), 1);
+            assert_eq!(board.king_count(Player::Player2), 1);
+        }
+
+        let (board, _) = Board::from_fen("B:WK18,K30,31:B2,9,K14").unwrap();
+        assert_eq!(board.king_count(Player::Player2), 2);
+        let rotated = board.rotated();
+        assert_eq!(rotated.king_count(Player::Player1), 2);
+        assert_eq!(rotated.king_count(Player::Player2), 1);
+    }
+
+    #[test]
+    fn test_features() {
+        // each side has a pawn on its back row and a pawn two rows from promotion
+        let (board, _) = Board::from_fen("B:W10,K16,30:B3,K15,22").unwrap();
+        assert!(board.check_features());
+        assert_eq!(
+            board.features(Player::Player1),
+            PieceFeatures {
+                pawns: 2,
+                kings: 1,
+                center_kings: 1,
+                back_pawns: 1,
+                advanced_pawns: 1,
+            }
+        );
+        assert_eq!(
+            board.features(Player::Player2),
+            PieceFeatures {
+                pawns: 2,
+                kings: 1,
+                center_kings: 0,
+                back_pawns: 1,
+                advanced_pawns: 1,
+            }
+        );
+        assert!(board.rotated().check_features());
+
+        // the features always match a scan of the board, as pieces move, are captured and are
+        // crowned, and as the movements are taken back
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let mut board = Board::new();
+            let mut player = Player::Player1;
+            let mut undos = Vec::new();
+            for _ in 0..150 {
+                let movements = board.movements(player);
+                let Some(movement) = movements.choose(&mut rng) else {
+                    break;
+                };
+                undos.push(board.do_move(movement, player));
+                assert!(board.check_features());
+                player = player.other();
+            }
+            while let Some(undo) = undos.pop() {
+                board.undo(undo);
+                assert!(board.check_features());
+            }
+            assert_eq!(board.features(Player::Player1).back_pawns, 4);
+        }
+    }
+
+    #[test]
+    fn test_null_move() {
+        let mut board = Board::new();
+        let hash = board.hash();
+        board.make_null_move();
+        assert_ne!(board.hash(), hash);
+        assert!(board.check_hash());
+
+        // moves made after a pass keep the turn component
+        let movement = board.parse_move(Player::Player2, "22-18").unwrap();
+        board.do_movement(&movement);
+        board.undo_movement(&movement);
+
+        board.unmake_null_move();
+        assert_eq!(board.hash(), hash);
+        assert!(board.check_hash());
+    }
+
+    #[test]
+    fn test_from_squares() {
+        let (board, _) = Board::from_fen("B:W18,K30,31:B2,9,K14").unwrap();
+        let rebuilt = Board::try_from(*board.squares()).unwrap();
+        assert_eq!(rebuilt.squares(), board.squares());
+        assert_eq!(rebuilt.recompute_hash(), rebuilt.hash());
+
+        let mut squares = *Board::new().squares();
+        squares[9] = Square::Taken(Piece::player1_pawn());
+        assert_eq!(
+            Board::from_squares(squares).err(),
+            Some(SquaresError { id: 9 })
+        );
+        let mut squares = *Board::new().squares();
+        squares[5] = Square::Invalid;
+        assert_eq!(
+            Board::from_squares(squares).err(),
+            Some(SquaresError { id: 5 })
+        );
+    }
+
+    #[test]
+    fn test_new_with_handicap() {
+        let board = Board::new_with_handicap(Player::Player2, &[21, 23]).unwrap();
+        assert_eq!(board.piece_count(), (12, 10));
+        assert_eq!(board.get(square_id(21).unwrap()), Square::Empty);
+        assert_eq!(board.get(square_id(23).unwrap()), Square::Empty);
+        assert_eq!(board.recompute_hash(), board.hash());
+        assert_eq!(
+            Board::new_with_handicap(Player::Player1, &[])
+                .unwrap()
+                .to_string(),
+            Board::new().to_string()
+        );
+
+        assert_eq!(
+            Board::new_with_handicap(Player::Player1, &[21]).err(),
+            Some(HandicapError::NotStartingSquare(21))
+        );
+        assert_eq!(
+            Board::new_with_handicap(Player::Player1, &[9, 9]).err(),
+            Some(HandicapError::NotStartingSquare(9))
+        );
+        assert_eq!(
+            Board::new_with_handicap(Player::Player1, &[33]).err(),
+            Some(HandicapError::UnknownSquare(33))
+        );
+    }
+
+    #[test]
+    fn test_perft_detailed() {
+        let mut board = Board::new();
+        let hash = board.hash();
+        let counts: Vec<PerftStats> = (1..=5)
+            .map(|depth| board.perft_detailed(Player::Player1, depth))
+            .collect();
+        let expected = [
+            (7, 0, 0, 0),
+            (49, 0, 0, 0),
+            (302, 11, 0, 0),
+            (1469, 169, 0, 0),
+            (7361, 880, 0, 0),
+        ];
+        for (stats, (nodes, captures, promotions, multi_jumps)) in counts.iter().zip(expected) {
+            assert_eq!(
+                *stats,
+                PerftStats {
+                    nodes,
+                    captures,
+                    promotions,
+                    multi_jumps
+                }
+            );
+        }
+        assert_eq!(board.hash(), hash);
+
+        // promotions and multi-jumps only appear deeper in the game, so check them separately
+        let (mut board, player) = Board::from_fen("B:W7,14,22:B2,9").unwrap();
+        let stats = board.perft_detailed(player, 1);
+        assert_eq!((stats.nodes, stats.captures, stats.multi_jumps), (2, 2, 1));
+        let (mut board, player) = Board::from_fen("B:W5:B27").unwrap();
+        let stats = board.perft_detailed(player, 1);
+        assert_eq!((stats.nodes, stats.captures, stats.promotions), (2, 0, 2));
+    }
+
+    #[test]
+    #[allow(clippy::manual_contains)]
+    fn test_simple_movements() {
+        let board_new = Board::new();
+        let mut board = Board::new();
+        let hash = board.hash();
+        let movement = Movement::simple(
+            SquareState::piece(15, Piece::player1_pawn()),
+            SquareState::empty(19),
+        );
+        assert!(board
+            .simple_moves(Player::Player1)
+            .iter()
+            .any(|m| *m == movement));
+        board.do_movement(&movement);
+        assert_ne!(board_new.squares, board.squares);
+        board.undo_movement(&movement);
+        assert_eq!(board_new.squares, board.squares);
+        assert_eq!(hash, board.hash());
+    }
+
+    #[test]
+    #[allow(clippy::manual_contains)]
+    fn test_do_movement_jump() {
+        let mut board = Board::new();
+        let hash = board.hash();
+        let m1 = Movement::simple(
+            SquareState::piece(15, Piece::player1_pawn()),
+            SquareState::empty(20),
+        );
+        let m2 = Movement::simple(
+            SquareState::piece(30, Piece::player2_pawn()),
+            SquareState::empty(25),
+        );
+        board.do_movement(&m1);
+        board.do_movement(&m2);
+        let movement = Movement::jump(
+            SquareState::piece(20, Piece::player1_pawn()),
+            SquareState::empty(30),
+            SquareState::piece(25, Piece::player2_pawn()),
+        );
+        assert!(!board
+            .simple_moves(Player::Player1)
+            .iter()
+            .any(|m| *m == movement));
+        assert!(board
+            .jump_moves(Player::Player1)
+            .iter()
+            .any(|m| *m == movement));
+        board.do_movement(&movement);
+        assert_eq!(board.get(25), Square::Empty);
+        board.undo_movement(&movement);
+        board.undo_movement(&m2);
+        board.undo_movement(&m1);
+        assert_eq!(board.squares, Board::new().squares);
+        assert_eq!(hash, board.hash());
+    }
+
+    #[test]
+    #[allow(clippy::manual_contains)]
+    fn test_do_movement_multi_jump() {
+        let mut board = Board::new();
+        let hash = board.hash();
+        let m1 = Movement::simple(
+            SquareState::piece(15, Piece::player1_pawn()),
+            SquareState::empty(20),
+        );
+        let m2 = Movement::simple(
+            SquareState::piece(29, Piece::player2_pawn()),
+            SquareState::empty(24),
+        );
+        let m3 = Movement::simple(
+            SquareState::piece(11, Piece::player1_pawn()),
+            SquareState::empty(15),
+        );
+        let m4 = Movement::simple(
+            SquareState::piece(33, Piece::player2_pawn()),
+            SquareState::empty(29),
+        );
+        let m5 = Movement::simple(
+            SquareState::piece(17, Piece::player1_pawn()),
+            SquareState::empty(22),
+        );
+        let m6 = Movement::simple(
+            SquareState::piece(38, Piece::player2_pawn()),
+            SquareState::empty(33),
+        );
+        let m7 = Movement::simple(
+            SquareState::piece(6, Piece::player1_pawn()),
+            SquareState::empty(11),
+        );
+        let m8 = Movement::simple(
+            SquareState::piece(30, Piece::player2_pawn()),
+            SquareState::empty(25),
+        );
+        let movement = Movement::multi_jump(
+            SquareState::piece(20, Piece::player1_pawn()),
+            SquareState::empty(30),
+            SquareState::piece(25, Piece::player2_pawn()),
+            Box::new(Movement::jump(
+                SquareState::piece(30, Piece::player1_pawn()),
+                SquareState::empty(38),
+                SquareState::piece(34, Piece::player2_pawn()),
+            )),
+        );
+        board.do_movement(&m1);
+        board.do_movement(&m2);
+        board.do_movement(&m3);
+        board.do_movement(&m4);
+        board.do_movement(&m5);
+        board.do_movement(&m6);
+        board.do_movement(&m7);
+        board.do_movement(&m8);
+        assert!(!board
+            .simple_moves(Player::Player1)
+            .iter()
+            .any(|m| *m == movement));
+        assert!(board
+            .jump_moves(Player::Player1)
+            .iter()
+            .any(|m| *m == movement));
+        board.do_movement(&movement);
+        let (p1, p2) = board.piece_count();
+        assert_eq!(p1, 12);
+        assert_eq!(p2, 10);
+        board.undo_movement(&movement);
+        board.undo_movement(&m8);
+        board.undo_movement(&m7);
+        board.undo_movement(&m6);
+        board.undo_movement(&m5);
+        board.undo_movement(&m4);
+        board.undo_movement(&m3);
+        board.undo_movement(&m2);
+        board.undo_movement(&m1);
+        assert_eq!(board.squares, Board::new().squares);
+        assert_eq!(hash, board.hash());
+    }
+
+    #[test]
+    fn test_king_circle_jump() {
+        let mut board = Board::empty();
+        board.set(11, Square::Taken(Piece::player1_king()));
+        board.set(16, Square::Taken(Piece::player2_pawn()));
+        board.set(25, Square::Taken(Piece::player2_pawn()));
+        board.set(24, Square::Taken(Piece::player2_pawn()));
+        board.set(15, Square::Taken(Piece::player2_pawn()));
+        let hash = board.hash();
+        let jumps = board.jump_moves(Player::Player1);
+        let movement = Movement::multi_jump(
+            SquareState::piece(11, Piece::player1_king()),
+            SquareState::empty(21),
+            SquareState::piece(16, Piece::player2_pawn()),
+            Box::new(Movement::multi_jump(
+                SquareState::piece(21, Piece::player1_king()),
+                SquareState::empty(29),
+                SquareState::piece(25, Piece::player2_pawn()),
+                Box::new(Movement::multi_jump(
+                    SquareState::piece(29, Piece::player1_king()),
+                    SquareState::empty(19),
+                    SquareState::piece(24, Piece::player2_pawn()),
+                    Box::new(Movement::jump(
+                        SquareState::piece(19, Piece::player1_king()),
+                        SquareState::empty(11),
+                        SquareState::piece(15, Piece::player2_pawn()),
+                    )),
+                )),
+            )),
+        );
+        assert_eq!(jumps.len(), 1);
+        assert!(board.is_legal(&movement, Player::Player1));
+        board.do_movement(&movement);
+        assert_eq!(board.get(16), Square::Empty);
+        assert_eq!(board.get(25), Square::Empty);
+        assert_eq!(board.get(24), Square::Empty);
+        assert_eq!(board.get(15), Square::Empty);
+        board.undo_movement(&movement);
+        assert_eq!(board.get(16), Square::Taken(Piece::player2_pawn()));
+        assert_eq!(board.get(25), Square::Taken(Piece::player2_pawn()));
+        assert_eq!(board.get(24), Square::Taken(Piece::player2_pawn()));
+        assert_eq!(board.get(15), Square::Taken(Piece::player2_pawn()));
+        assert_eq!(hash, board.hash());
+    }
+
+    // A random position that could come up in a game: up to 12 pieces a side, and no pawn on the
+    // row where it would have been crowned.
+    fn random_position(rng: &mut StdRng) -> Board {
+        let mut board = Board::empty();
+        for player in [Player::Player1, Player::Player2] {
+            for _ in 0..rng.gen_range(1..=12) {
+                let id = VALID_SQUARES[rng.gen_range(0..VALID_SQUARES.len())];
+                let king = is_promotion_square(id, player) || rng.gen_bool(0.25);
+                if board.get(id) == Square::Empty {
+                    board.set(id, Square::Taken(Piece::new(player, king)));
+                }
             }
-            if let Square::Taken(jumped_piece) = self.squares[id_jumped] {
-                if jumped_piece.player != player && Square::Empty == self.squares[id_to]
-                    || id_to == start
-                {
-                    let from = SquareState::piece(id, piece);
-                    let to = SquareState::empty(id_to);
-                    let jumped = SquareState::piece(id_jumped, jumped_piece);
-                    prev_jumped.push(id_jumped);
-                    let multi_jumps = self.jump_moves_at(player, piece, id_to, start, prev_jumped);
-                    prev_jumped.pop();
-                    if multi_jumps.is_empty() {
-                        let movement = Movement::jump(from, to, jumped);
-                        movements.push(movement);
-                    } else {
-                        for mj in multi_jumps {
-                            let movement = Movement::multi_jump(from, to, jumped, Box::new(mj));
-                            movements.push(movement);
-                        }
-                    }
+        }
+        board
+    }
+
+    // The board is exactly as it was before, down to the incrementally kept state.
+    fn restored(board: &Board, before: &Board) -> bool {
+        board.squares == before.squares
+            && board.zobrist == before.zobrist
+            && board.kings == before.kings
+            && board.features == before.features
+    }
+
+    #[test]
+    fn test_do_undo_fuzz() {
+        // every case has its own seed, so a failure is reproduced from the seed alone
+        for seed in 0..500 {
+            let mut board = random_position(&mut StdRng::seed_from_u64(seed));
+            let before = board.clone();
+            for player in [Player::Player1, Player::Player2] {
+                for movement in board.movements(player) {
+                    let case = format!("seed {} movement {}", seed, movement);
+                    assert!(board.is_legal(&movement, player), "{}", case);
+                    board.do_movement(&movement);
+                    assert_eq!(board.hash(), board.recompute_hash(), "{}", case);
+                    board.undo_movement(&movement);
+                    assert!(restored(&board, &before), "{}", case);
+
+                    let undo = board.do_move(&movement, player);
+                    assert_eq!(board.hash(), board.recompute_hash(), "{}", case);
+                    assert!(board.check_features(), "{}", case);
+                    board.undo(undo);
+                    assert!(restored(&board, &before), "{}", case);
                 }
             }
         }
-        movements
     }
 
-    // Change the board state based on the given [Movement]. Updates the [ZobristHash].
-    pub fn do_movement(&mut self, movement: &Movement) {
-        self.squares[movement.to.id] = self.squares[movement.from.id];
-        self.zobrist
-            .flip(movement.to.id, movement.from.piece.unwrap().id());
-        self.squares[movement.from.id] = Square::Empty;
-        self.zobrist
-            .flip(movement.from.id, movement.from.piece.unwrap().id());
-        if let Some(jumped_state) = &movement.jumped {
-            self.squares[jumped_state.id] = Square::Empty;
-            self.zobrist
-                .flip(jumped_state.id, jumped_state.piece.unwrap().id());
-            if let Some(next_movement) = &movement.next {
-                self.do_movement(next_movement);
+    #[test]
+    fn test_legal_jump_exists() {
+        let squares = [
+            Square::Empty,
+            Square::Empty,
+            Square::Taken(Piece::player1_pawn()),
+            Square::Taken(Piece::player1_king()),
+            Square::Taken(Piece::player2_pawn()),
+            Square::Taken(Piece::player2_king()),
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let mut board = Board::empty();
+            for id in VALID_SQUARES {
+                board.set(id, squares[rng.gen_range(0..squares.len())]);
+            }
+            for player in [Player::Player1, Player::Player2] {
+                assert_eq!(
+                    board.legal_jump_exists(player),
+                    !board.jump_moves(player).is_empty()
+                );
             }
         }
     }
 
-    // Undo the board state based on the given [Movement]. Updates the [ZobristHash].
-    pub fn undo_movement(&mut self, movement: &Movement) {
-        if let Some(next_movement) = &movement.next {
-            self.undo_movement(next_movement);
+    #[test]
+    fn test_is_quiet() {
+        let mut board = Board::new();
+        assert!(board.is_quiet());
+        // the pieces on 15 and 19 face each other but neither can jump
+        for (player, notation) in [(Player::Player1, "11-15"), (Player::Player2, "23-19")] {
+            let movement = board.parse_move(player, notation).unwrap();
+            board.do_movement(&movement);
         }
-        self.squares[movement.from.id] = self.squares[movement.to.id];
-        self.zobrist
-            .flip(movement.from.id, movement.from.piece.unwrap().id());
-        self.squares[movement.to.id] = Square::Empty;
-        self.zobrist
-            .flip(movement.to.id, movement.from.piece.unwrap().id());
-        if let Some(jumped_state) = &movement.jumped {
-            self.squares[jumped_state.id] = Square::Taken(jumped_state.piece.unwrap());
-            self.zobrist
-                .flip(jumped_state.id, jumped_state.piece.unwrap().id());
+        assert!(board.is_quiet());
+        // only one side can capture
+        let (board, _) = Board::from_fen("B:W9,18:B14").unwrap();
+        assert!(board.legal_jump_exists(Player::Player1));
+        assert!(!board.legal_jump_exists(Player::Player2));
+        assert!(!board.is_quiet());
+        let (board, _) = Board::from_fen("W:W9,18:B14").unwrap();
+        assert!(!board.is_quiet());
+    }
+
+    #[test]
+    fn test_square_id_round_trip() {
+        for number in 1..=32 {
+            let square = SquareId::new(number).unwrap();
+            assert_eq!(square.number(), number);
+            assert_eq!(square.to_string(), number.to_string());
+            let id = square.board_id();
+            assert_eq!(Some(id), square_id(number));
+            assert!(VALID_SQUARES.contains(&id));
+            assert_eq!(SquareId::from_board_id(id), Some(square));
         }
+        assert_eq!(SquareId::all().count(), 32);
+        assert_eq!(SquareId::new(0), None);
+        assert_eq!(SquareId::new(33), None);
+        // the padding around the board is not a square
+        assert_eq!(SquareId::from_board_id(9), None);
+
+        let board = Board::new();
+        let eleven = SquareId::new(11).unwrap();
+        assert_eq!(board.piece_on(eleven), Some(Piece::player1_pawn()));
+        let mut moves: Vec<String> = board
+            .legal_moves_for_square(Player::Player1, eleven)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        moves.sort();
+        assert_eq!(moves, ["11-15", "11-16"]);
     }
 
-    #[allow(dead_code)]
-    pub fn piece_count(&self) -> (u8, u8) {
-        let mut p1 = 0;
-        let mut p2 = 0;
+    #[test]
+    fn test_square_coords() {
+        assert_eq!(square_coords(8), (0, 1));
+        assert_eq!(square_coords(5), (0, 7));
+        assert_eq!(square_coords(13), (1, 0));
+        assert_eq!(square_coords(37), (7, 6));
+        // diagonal neighbours are one row and one column apart
         for id in VALID_SQUARES {
-            if let Square::Taken(piece) = self.squares[id] {
-                if piece.player == Player::Player1 {
-                    p1 += 1;
-                } else {
-                    p2 += 1;
+            let (row, col) = square_coords(id);
+            for m in [-5, -4, 4, 5] {
+                let neighbour = (id as i32 + m) as usize;
+                if neighbour < 46 && VALID_SQUARES.contains(&neighbour) {
+                    let (n_row, n_col) = square_coords(neighbour);
+                    assert_eq!((n_row - row).abs(), 1);
+                    assert_eq!((n_col - col).abs(), 1);
                 }
             }
         }
-        (p1, p2)
     }
 
-    pub fn mark_kings(&mut self) -> u32 {
-        let mut kings = 0;
-        for id in PLAYER1_KINGS {
-            if let Square::Taken(piece) = self.squares[id] {
-                if piece.player == Player::Player1 && !piece.king {
-                    self.squares[id] = Square::Taken(Piece::player1_king());
-                    kings += 1;
-                }
-            }
-        }
-        for id in PLAYER2_KINGS {
-            if let Square::Taken(piece) = self.squares[id] {
-                if piece.player == Player::Player2 && !piece.king {
-                    self.squares[id] = Square::Taken(Piece::player2_king());
-                    kings += 1;
-                }
-            }
-        }
-        kings
+    #[test]
+    fn test_from_fen() {
+        let (board, player) =
+            Board::from_fen("B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12")
+                .unwrap();
+        assert_eq!(player, Player::Player1);
+        assert_eq!(board.squares, Board::new().squares);
+
+        let (board, player) = Board::from_fen("W:WK1:B5-6").unwrap();
+        assert_eq!(player, Player::Player2);
+        assert_eq!(board.get(8), Square::Taken(Piece::player2_king()));
+        assert_eq!(board.get(13), Square::Taken(Piece::player1_pawn()));
+        assert_eq!(board.get(12), Square::Taken(Piece::player1_pawn()));
+        assert_eq!(board.piece_count(), (2, 1));
+
+        assert!(Board::from_fen("B:W1:B1").is_none());
+        assert!(Board::from_fen("B:W33").is_none());
+        assert!(Board::from_fen("X:W1").is_none());
     }
-}
 
-impl Default for Board {
-    fn default() -> Self {
-        Board::new()
+    #[test]
+    fn test_diagram() {
+        assert_eq!(
+            Board::new().diagram(Player::Player1),
+            "bbbbbbbbbbbb........wwwwwwwwwwww B"
+        );
+        let (board, player) = Board::from_fen("W:WK1,18:B5,K32").unwrap();
+        assert_eq!(board.diagram(player), "W...b............w.............B W");
     }
-}
 
-impl fmt::Display for Board {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(
-            f,
-            "1  |   | {} |   | {} |   | {} |   | {} |",
-            self.squares[37], self.squares[38], self.squares[39], self.squares[40]
-        )?;
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(
-            f,
-            "2  | {} |   | {} |   | {} |   | {} |   |",
-            self.squares[32], self.squares[33], self.squares[34], self.squares[35]
-        )?;
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(
-            f,
-            "3  |   | {} |   | {} |   | {} |   | {} |",
-            self.squares[28], self.squares[29], self.squares[30], self.squares[31]
-        )?;
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(
-            f,
-            "4  | {} |   | {} |   | {} |   | {} |   |",
-            self.squares[23], self.squares[24], self.squares[25], self.squares[26]
-        )?;
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(
-            f,
-            "5  |   | {} |   | {} |   | {} |   | {} |",
-            self.squares[19], self.squares[20], self.squares[21], self.squares[22]
-        )?;
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(
-            f,
-            "6  | {} |   | {} |   | {} |   | {} |   |",
-            self.squares[14], self.squares[15], self.squares[16], self.squares[17]
-        )?;
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(
-            f,
-            "7  |   | {} |   | {} |   | {} |   | {} |",
-            self.squares[10], self.squares[11], self.squares[12], self.squares[13]
-        )?;
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(
-            f,
-            "8  | {} |   | {} |   | {} |   | {} |   |",
-            self.squares[5], self.squares[6], self.squares[7], self.squares[8]
-        )?;
-        writeln!(f, "   ---------------------------------")?;
-        writeln!(f, "     A   B   C   D   E   F   G   H")
+    #[test]
+    fn test_simulate() {
+        let (mut board, player) = Board::from_fen("B:W18:B9,14").unwrap();
+        let before = board.clone();
+        let jump = board.parse_move(player, "14x23").unwrap();
+        let (after, count) = board.simulate(&jump, player, |b| (b.hash(), b.piece_count()));
+        assert_ne!(after, before.hash());
+        assert_eq!(count, (2, 0));
+        assert_eq!(board.squares, before.squares);
+        assert_eq!(board.hash(), before.hash());
+
+        // the movement is undone when the closure panics
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            board.simulate(&jump, player, |_| panic!("closure panicked"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(board.squares, before.squares);
+        assert_eq!(board.hash(), before.hash());
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn test_replies_to() {
+        let (mut board, player) = Board::from_fen("B:W22,30:B14").unwrap();
+        let before = board.hash();
+
+        // 14-18 offers the pawn, and the capture is forced
+        let movement = board.parse_move(player, "14-18").unwrap();
+        let replies = board.replies_to(&movement, player);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].to_string(), "22x15");
+        assert_eq!(board.hash(), before);
+    }
 
     #[test]
-    fn test_simple_movements() {
-        let board_new = Board::new();
-        let mut board = Board::new();
-        let hash = board.hash();
+    fn test_board_builder() {
+        // a first position puzzle: Player 1 to move and win
+        let (board, player) = BoardBuilder::new()
+            .add(14, Piece::player1_pawn())
+            .add(9, Piece::player1_pawn())
+            .add(18, Piece::player2_pawn())
+            .add(1, Piece::player2_king())
+            .side_to_move(Player::Player1)
+            .build()
+            .unwrap();
+        let (expected, _) = Board::from_fen("B:WK1,18:B9,14").unwrap();
+        assert_eq!(player, Player::Player1);
+        assert_eq!(board.squares, expected.squares);
+        assert_eq!(board.king_count(Player::Player2), 1);
+        assert!(board.check_hash());
+
+        let build = |square, piece| BoardBuilder::new().add(square, piece).build().err();
+        assert_eq!(
+            build(33, Piece::player1_pawn()),
+            Some(BuildError::UnknownSquare(33))
+        );
+        assert_eq!(
+            build(0, Piece::player1_pawn()),
+            Some(BuildError::UnknownSquare(0))
+        );
+        // Player 1 pawns are crowned on 29-32 and Player 2 pawns on 1-4
+        assert_eq!(
+            build(30, Piece::player1_pawn()),
+            Some(BuildError::UncrownedPawn(30))
+        );
+        assert_eq!(
+            build(2, Piece::player2_pawn()),
+            Some(BuildError::UncrownedPawn(2))
+        );
+        assert_eq!(build(30, Piece::player1_king()), None);
+        assert_eq!(build(30, Piece::player2_pawn()), None);
+        assert_eq!(
+            BoardBuilder::new()
+                .add(14, Piece::player1_pawn())
+                .add(14, Piece::player2_pawn())
+                .build()
+                .err(),
+            Some(BuildError::Occupied(14))
+        );
+    }
+
+    #[test]
+    fn test_piece_at() {
+        let (board, _) = Board::from_fen("B:WK18:B14").unwrap();
+        let id = |number| square_id(number).unwrap();
+        assert_eq!(board.piece_at(id(14)), Some(Piece::player1_pawn()));
+        assert_eq!(board.piece_at(id(18)), Some(Piece::player2_king()));
+        assert_eq!(board.get(id(15)), Square::Empty);
+        assert_eq!(board.piece_at(id(15)), None);
+        assert_eq!(board.get(9), Square::Invalid);
+        assert_eq!(board.piece_at(9), None);
+    }
+
+    #[test]
+    fn test_movement_notation() {
         let movement = Movement::simple(
-            SquareState::piece(15, Piece::player1_pawn()),
-            SquareState::empty(19),
+            SquareState::piece(10, Piece::player1_pawn()),
+            SquareState::empty(14),
         );
-        assert!(board
-            .simple_moves(Player::Player1)
-            .iter()
-            .any(|m| *m == movement));
+        assert_eq!(movement.to_string(), "8-12");
+        let movement = Movement::multi_jump(
+            SquareState::piece(20, Piece::player1_pawn()),
+            SquareState::empty(30),
+            SquareState::piece(25, Piece::player2_pawn()),
+            Box::new(Movement::jump(
+                SquareState::piece(30, Piece::player1_pawn()),
+                SquareState::empty(38),
+                SquareState::piece(34, Piece::player2_pawn()),
+            )),
+        );
+        assert_eq!(movement.to_string(), "15x22x31");
+    }
+
+    #[test]
+    fn test_movement_classification() {
+        let simple = Board::new().parse_move(Player::Player1, "11-15").unwrap();
+        let (board, player) = Board::from_fen("B:W7,14,22:B2,9").unwrap();
+        let single = board.parse_move(player, "2x11").unwrap();
+        let multi = board.parse_move(player, "9x18x25").unwrap();
+        for (movement, expected) in [
+            (simple, [true, false, false]),
+            (single, [false, true, false]),
+            (multi, [false, false, true]),
+        ] {
+            let classes = [
+                movement.is_simple(),
+                movement.is_single_jump(),
+                movement.is_multi_jump(),
+            ];
+            assert_eq!(classes, expected, "movement {}", movement);
+            assert_eq!(movement.is_jump(), !movement.is_simple());
+        }
+    }
+
+    #[test]
+    fn test_describe_move() {
+        let board = Board::new();
+        let movement = board.parse_move(Player::Player1, "11-15").unwrap();
+        assert_eq!(
+            board.describe_move(&movement),
+            "Player1 pawn moves C6 to D5"
+        );
+
+        let (board, player) = Board::from_fen("W:WK18:B14,22").unwrap();
+        let movement = board.parse_move(player, "18x9").unwrap();
+        assert_eq!(
+            board.describe_move(&movement),
+            "Player2 king captures 1 piece from E4 ending on G6"
+        );
+
+        let (board, player) = Board::from_fen("B:W7,14,22:B2,9").unwrap();
+        let movement = board.parse_move(player, "9x18x25").unwrap();
+        assert_eq!(
+            board.describe_move(&movement),
+            "Player1 pawn captures 2 pieces from G6 ending on G2"
+        );
+
+        let (board, player) = Board::from_fen("B:W5:B27").unwrap();
+        let movement = board.parse_move(player, "27-32").unwrap();
+        assert_eq!(
+            board.describe_move(&movement),
+            "Player1 pawn moves C2 to B1 and is crowned"
+        );
+    }
+
+    #[test]
+    fn test_movement_order() {
+        let board = Board::new();
+        let (jumps, player) = Board::from_fen("B:W7,14,22:B2,9").unwrap();
+        let mut movements = [
+            board.parse_move(Player::Player1, "11-15").unwrap(),
+            jumps.parse_move(player, "2x11").unwrap(),
+            board.parse_move(Player::Player1, "9-13").unwrap(),
+            jumps.parse_move(player, "9x18x25").unwrap(),
+        ];
+        movements.sort();
+        let sorted: Vec<String> = movements.iter().map(|m| m.to_string()).collect();
+        // the jumps come first, then the simple movements by the id of their square
+        assert!(square_id(11) < square_id(9));
+        assert_eq!(sorted, ["9x18x25", "2x11", "11-15", "9-13"]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_stale_movement() {
+        let mut board = Board::new();
+        let movement = board.parse_move(Player::Player1, "11-15").unwrap();
+        board.do_movement(&movement);
+        // the pawn has already left 11
+        board.do_movement(&movement);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_stale_jump() {
+        let (mut board, player) = Board::from_fen("B:W18:B14").unwrap();
+        let movement = board.parse_move(player, "14x23").unwrap();
+        // the jumped pawn is no longer there
+        board.set(square_id(18).unwrap(), Square::Empty);
         board.do_movement(&movement);
-        assert_ne!(board_new.squares, board.squares);
-        board.undo_movement(&movement);
-        assert_eq!(board_new.squares, board.squares);
-        assert_eq!(hash, board.hash());
     }
 
     #[test]
-    fn test_do_movement_jump() {
-        let mut board = Board::new();
-        let hash = board.hash();
-        let m1 = Movement::simple(
-            SquareState::piece(15, Piece::player1_pawn()),
-            SquareState::empty(20),
+    fn test_undo_token() {
+        let (mut board, player) = Board::from_fen("B:W27,K9:B23").unwrap();
+        let before = (board.to_string(), board.hash());
+
+        let movement = board.parse_move(player, "23x32").unwrap();
+        let undo = board.do_move(&movement, player);
+        assert_eq!(
+            board.get(square_id(32).unwrap()),
+            Square::Taken(Piece::player1_king())
         );
-        let m2 = Movement::simple(
-            SquareState::piece(30, Piece::player2_pawn()),
-            SquareState::empty(25),
+        assert_eq!(undo.promotion().crowned, square_id(32));
+        assert_eq!(undo.player(), Player::Player1);
+
+        // the token alone reverts the jump and the promotion
+        drop(movement);
+        board.undo(undo);
+        assert_eq!((board.to_string(), board.hash()), before);
+        assert_eq!(
+            board.get(square_id(23).unwrap()),
+            Square::Taken(Piece::player1_pawn())
         );
-        board.do_movement(&m1);
-        board.do_movement(&m2);
-        let movement = Movement::jump(
-            SquareState::piece(20, Piece::player1_pawn()),
-            SquareState::empty(30),
-            SquareState::piece(25, Piece::player2_pawn()),
+    }
+
+    #[test]
+    fn test_apply_and_promote() {
+        let (mut board, player) = Board::from_fen("B:W5:B22,27").unwrap();
+        let movement = board.parse_move(player, "27-32").unwrap();
+        assert!(board.apply_and_promote(&movement, player));
+        assert_eq!(
+            board.get(square_id(32).unwrap()),
+            Square::Taken(Piece::player1_king())
         );
-        assert!(!board
-            .simple_moves(Player::Player1)
-            .iter()
-            .any(|m| *m == movement));
-        assert!(board
-            .jump_moves(Player::Player1)
-            .iter()
-            .any(|m| *m == movement));
-        board.do_movement(&movement);
-        assert_eq!(board.get(25), Square::Empty);
-        board.undo_movement(&movement);
-        board.undo_movement(&m2);
-        board.undo_movement(&m1);
-        assert_eq!(board.squares, Board::new().squares);
-        assert_eq!(hash, board.hash());
+        let (crowned, _) = Board::from_fen("W:W5:B22,K32").unwrap();
+        assert_eq!(board.hash(), crowned.hash());
+        assert_eq!(board.king_count(Player::Player1), 1);
+
+        let movement = board.parse_move(player, "22-26").unwrap();
+        assert!(!board.apply_and_promote(&movement, player));
+        assert_eq!(board.king_count(Player::Player1), 1);
     }
 
     #[test]
-    fn test_do_movement_multi_jump() {
-        let mut board = Board::new();
-        let hash = board.hash();
-        let m1 = Movement::simple(
-            SquareState::piece(15, Piece::player1_pawn()),
-            SquareState::empty(20),
+    fn test_setup_round_trip() {
+        let setup = " white: B1, d1 f3 \n\nBLACK: a8 c6\nKings: F3\nturn: B\n";
+        let (board, player) = Board::from_setup(setup).unwrap();
+        assert_eq!(player, Player::Player1);
+        assert_eq!(board.recompute_hash(), board.hash());
+        assert_eq!(board.piece_count(), (2, 3));
+        assert_eq!(board.king_count(Player::Player2), 1);
+        // the setup squares agree with the names used by the terminal interface
+        let map = MovementMap::new();
+        assert_eq!(
+            board.get(map.map["D1"]),
+            Square::Taken(Piece::player2_pawn())
         );
-        let m2 = Movement::simple(
-            SquareState::piece(29, Piece::player2_pawn()),
-            SquareState::empty(24),
+        assert_eq!(
+            board.get(map.map["F3"]),
+            Square::Taken(Piece::player2_king())
         );
-        let m3 = Movement::simple(
-            SquareState::piece(11, Piece::player1_pawn()),
-            SquareState::empty(15),
+        assert_eq!(
+            board.get(map.map["C6"]),
+            Square::Taken(Piece::player1_pawn())
         );
-        let m4 = Movement::simple(
-            SquareState::piece(33, Piece::player2_pawn()),
-            SquareState::empty(29),
+
+        let written = board.to_setup(player);
+        assert_eq!(
+            written,
+            "White: b1 d1 f3\nBlack: a8 c6\nKings: f3\nTurn: black\n"
         );
-        let m5 = Movement::simple(
-            SquareState::piece(17, Piece::player1_pawn()),
-            SquareState::empty(22),
+        let (reread, reread_player) = Board::from_setup(&written).unwrap();
+        assert_eq!(reread.to_string(), board.to_string());
+        assert_eq!(reread_player, player);
+
+        let (start, _) = Board::from_setup(&Board::new().to_setup(Player::Player2)).unwrap();
+        assert_eq!(start.to_string(), Board::new().to_string());
+    }
+
+    #[test]
+    fn test_setup_errors() {
+        assert_eq!(
+            Board::from_setup("White: a1\nBlack: c6\nTurn: white").err(),
+            Some(SetupError::LightSquare("a1".to_string()))
         );
-        let m6 = Movement::simple(
-            SquareState::piece(38, Piece::player2_pawn()),
-            SquareState::empty(33),
+        assert_eq!(
+            Board::from_setup("White: b1\nBlack: B1\nTurn: white").err(),
+            Some(SetupError::Occupied("B1".to_string()))
         );
-        let m7 = Movement::simple(
-            SquareState::piece(6, Piece::player1_pawn()),
-            SquareState::empty(11),
+        assert_eq!(
+            Board::from_setup("White: b1\nKings: d1\nTurn: white").err(),
+            Some(SetupError::Occupied("d1".to_string()))
         );
-        let m8 = Movement::simple(
-            SquareState::piece(30, Piece::player2_pawn()),
-            SquareState::empty(25),
+        assert_eq!(
+            Board::from_setup("White: b9\nTurn: white").err(),
+            Some(SetupError::Malformed("b9".to_string()))
         );
-        let movement = Movement::multi_jump(
-            SquareState::piece(20, Piece::player1_pawn()),
-            SquareState::empty(30),
-            SquareState::piece(25, Piece::player2_pawn()),
-            Box::new(Movement::jump(
-                SquareState::piece(30, Piece::player1_pawn()),
-                SquareState::empty(38),
-                SquareState::piece(34, Piece::player2_pawn()),
-            )),
+        assert_eq!(
+            Board::from_setup("White: b1").err(),
+            Some(SetupError::Malformed("missing turn".to_string()))
         );
-        board.do_movement(&m1);
-        board.do_movement(&m2);
-        board.do_movement(&m3);
-        board.do_movement(&m4);
-        board.do_movement(&m5);
-        board.do_movement(&m6);
-        board.do_movement(&m7);
-        board.do_movement(&m8);
-        assert!(!board
-            .simple_moves(Player::Player1)
-            .iter()
-            .any(|m| *m == movement));
-        assert!(board
-            .jump_moves(Player::Player1)
-            .iter()
-            .any(|m| *m == movement));
-        board.do_movement(&movement);
-        let (p1, p2) = board.piece_count();
-        assert_eq!(p1, 12);
-        assert_eq!(p2, 10);
-        board.undo_movement(&movement);
-        board.undo_movement(&m8);
-        board.undo_movement(&m7);
-        board.undo_movement(&m6);
-        board.undo_movement(&m5);
-        board.undo_movement(&m4);
-        board.undo_movement(&m3);
-        board.undo_movement(&m2);
-        board.undo_movement(&m1);
-        assert_eq!(board.squares, Board::new().squares);
-        assert_eq!(hash, board.hash());
     }
 
     #[test]
-    fn test_king_circle_jump() {
+    fn test_jump_targets_from() {
         let mut board = Board::empty();
-        let hash = board.hash();
         board.set(11, Square::Taken(Piece::player1_king()));
         board.set(16, Square::Taken(Piece::player2_pawn()));
         board.set(25, Square::Taken(Piece::player2_pawn()));
         board.set(24, Square::Taken(Piece::player2_pawn()));
         board.set(15, Square::Taken(Piece::player2_pawn()));
+        let hash = board.hash();
+
+        let square = |id| SquareId::from_board_id(id).unwrap();
+        // follow the king around the circle 11-21-29-19-11 one hop at a time
+        let hops = [(11, 16, 21), (21, 25, 29), (29, 24, 19), (19, 15, 11)];
+        let mut jumped = Vec::new();
+        let mut played = Vec::new();
+        for (from, over, to) in hops {
+            let targets = board.jump_targets_from(square(from), Player::Player1, &jumped);
+            assert!(targets.contains(&square(to)), "{} not in {:?}", to, targets);
+            let hop = Movement::jump(
+                SquareState::piece(from, Piece::player1_king()),
+                SquareState::empty(to),
+                SquareState::piece(over, Piece::player2_pawn()),
+            );
+            board.do_movement(&hop);
+            jumped.push(square(over));
+            played.push(hop);
+        }
+        assert!(board
+            .jump_targets_from(square(11), Player::Player1, &jumped)
+            .is_empty());
+
+        // captured pieces that are still on the board cannot be jumped twice
+        for hop in played.iter().rev() {
+            board.undo_movement(hop);
+        }
+        assert_eq!(board.hash(), hash);
+        assert!(board
+            .jump_targets_from(square(11), Player::Player1, &[square(16), square(15)])
+            .is_empty());
+        assert!(board
+            .jump_targets_from(square(11), Player::Player2, &[])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_must_continue_jump() {
+        let (mut board, player) = Board::from_fen("B:W18,27:B14").unwrap();
+        let id = |n| square_id(n).unwrap();
+        let square = |n| SquareId::new(n).unwrap();
+        let hops = [(14, 18, 23), (23, 27, 32)];
+        let mut jumped = Vec::new();
+        for (n, (from, over, to)) in hops.into_iter().enumerate() {
+            let hop = Movement::jump(
+                SquareState::piece(id(from), Piece::player1_pawn()),
+                SquareState::empty(id(to)),
+                SquareState::piece(id(over), Piece::player2_pawn()),
+            );
+            board.do_movement(&hop);
+            jumped.push(square(over));
+            let last = n == hops.len() - 1;
+            assert_eq!(board.must_continue_jump(square(to), player, &jumped), !last);
+        }
+        // the landing square must hold a piece of the player
+        assert!(!board.must_continue_jump(square(14), player, &[]));
+    }
+
+    #[test]
+    #[allow(clippy::manual_contains)]
+    fn test_king_circle_jump_over_friendly() {
+        let mut board = Board::empty();
+        board.set(11, Square::Taken(Piece::player1_king()));
+        board.set(16, Square::Taken(Piece::player2_pawn()));
+        board.set(25, Square::Taken(Piece::player2_pawn()));
+        board.set(24, Square::Taken(Piece::player2_pawn()));
+        board.set(15, Square::Taken(Piece::player1_pawn()));
         let jumps = board.jump_moves(Player::Player1);
         let movement = Movement::multi_jump(
             SquareState::piece(11, Piece::player1_king()),
@@ -702,33 +3516,144 @@ mod test {
                 SquareState::piece(21, Piece::player1_king()),
                 SquareState::empty(29),
                 SquareState::piece(25, Piece::player2_pawn()),
-                Box::new(Movement::multi_jump(
+                Box::new(Movement::jump(
                     SquareState::piece(29, Piece::player1_king()),
                     SquareState::empty(19),
                     SquareState::piece(24, Piece::player2_pawn()),
-                    Box::new(Movement::jump(
-                        SquareState::piece(19, Piece::player1_king()),
-                        SquareState::empty(11),
-                        SquareState::piece(15, Piece::player2_pawn()),
-                    )),
                 )),
             )),
         );
         assert!(jumps.iter().any(|m| *m == movement));
-        board.do_movement(&movement);
-        assert_eq!(board.get(16), Square::Empty);
-        assert_eq!(board.get(25), Square::Empty);
-        assert_eq!(board.get(24), Square::Empty);
-        assert_eq!(board.get(15), Square::Empty);
-        board.undo_movement(&movement);
-        assert_eq!(board.get(16), Square::Taken(Piece::player2_pawn()));
-        assert_eq!(board.get(25), Square::Taken(Piece::player2_pawn()));
-        assert_eq!(board.get(24), Square::Taken(Piece::player2_pawn()));
-        assert_eq!(board.get(15), Square::Taken(Piece::player2_pawn()));
+        for m in &jumps {
+            let mut jumped = Vec::new();
+            let mut next = Some(m);
+            while let Some(n) = next {
+                let state = n.jumped.unwrap();
+                assert_eq!(state.piece.unwrap().get_player(), Player::Player2);
+                assert!(!jumped.contains(&state.id));
+                jumped.push(state.id);
+                next = n.next.as_deref();
+            }
+        }
+    }
+
+    #[test]
+    fn test_do_movement_checked() {
+        let (mut board, _) = Board::from_fen("B:W12:B27").unwrap();
+        let hash = board.hash();
+        let movement = Movement::simple(
+            SquareState::piece(33, Piece::player1_pawn()),
+            SquareState::empty(37),
+        );
+        let info = board
+            .do_movement_checked(&movement, Player::Player1)
+            .unwrap();
+        assert_eq!(info.crowned, Some(37));
+        assert_eq!(board.get(37), Square::Taken(Piece::player1_king()));
+        board.undo_movement_checked(&movement, info);
+        assert_eq!(board.get(33), Square::Taken(Piece::player1_pawn()));
+        assert_eq!(board.get(37), Square::Empty);
+        assert_eq!(hash, board.hash());
+
+        // moving the opponent's piece
+        let movement = Movement::simple(
+            SquareState::piece(14, Piece::player2_pawn()),
+            SquareState::empty(10),
+        );
+        let squares = board.squares;
+        assert_eq!(
+            board.do_movement_checked(&movement, Player::Player1),
+            Err(IllegalMove)
+        );
+        assert_eq!(squares, board.squares);
         assert_eq!(hash, board.hash());
     }
 
     #[test]
+    fn test_crown_hash() {
+        // crowning a pawn swaps its key in the hash for the key of the king
+        let mut board = Board::empty();
+        board.set(37, Square::Taken(Piece::player1_pawn()));
+        board.set(5, Square::Taken(Piece::player2_pawn()));
+        assert_eq!(board.mark_kings(), 2);
+        assert!(board.check_hash());
+
+        let mut kings = Board::empty();
+        kings.set(37, Square::Taken(Piece::player1_king()));
+        kings.set(5, Square::Taken(Piece::player2_king()));
+        assert_eq!(board.hash(), kings.hash());
+        assert_eq!(board.mark_kings(), 0);
+        assert_eq!(board.hash(), kings.hash());
+    }
+
+    #[test]
+    fn test_optional_captures() {
+        let (board, player) = Board::from_fen("B:W18:B9,14").unwrap();
+        let jumps = board.jump_moves(player);
+        let simple = board.simple_moves(player);
+        assert_eq!(jumps.len(), 1);
+        assert!(!simple.is_empty());
+        assert_eq!(board.movements(player), jumps);
+
+        let board = board.with_rules(Rules {
+            captures_forced: false,
+            ..Rules::default()
+        });
+        let movements = board.movements(player);
+        assert_eq!(movements.len(), jumps.len() + simple.len());
+        assert!(jumps.iter().chain(&simple).all(|m| movements.contains(m)));
+    }
+
+    #[test]
+    fn test_check_hash() {
+        let mut board = Board::new();
+        assert!(board.check_hash());
+        board.set(19, Square::Taken(Piece::player1_king()));
+        board.set(5, Square::Empty);
+        assert!(board.check_hash());
+        assert!(board.mark_kings() == 0 && board.check_hash());
+
+        // changing a square without updating the hash
+        board.squares[20] = Square::Taken(Piece::player2_pawn());
+        assert!(!board.check_hash());
+    }
+
+    #[test]
+    fn test_zobrist_keys() {
+        let pieces = [
+            Piece::player1_pawn(),
+            Piece::player1_king(),
+            Piece::player2_pawn(),
+            Piece::player2_king(),
+        ];
+        let mut keys = vec![zobrist_keys().side];
+        for id in VALID_SQUARES {
+            for piece in pieces {
+                let mut zobrist = ZobristHash::new();
+                zobrist.flip(id, ZobristHash::piece_id(piece));
+                assert_ne!(zobrist.hash, 0);
+                keys.push(zobrist.hash);
+                zobrist.flip(id, ZobristHash::piece_id(piece));
+                assert_eq!(zobrist.hash, 0);
+            }
+        }
+        // no two (square, piece) pairs share a key
+        let count = keys.len();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), count);
+
+        // the keys are shared by every board
+        let hash = Board::new().hash();
+        assert_ne!(hash, 0);
+        assert_eq!(Board::new().hash(), hash);
+        assert_eq!(Board::new().recompute_hash(), hash);
+        let (board, _) = Board::from_fen("B:W21-32:B1-12").unwrap();
+        assert_eq!(board.hash(), hash);
+    }
+
+    #[test]
+    #[allow(clippy::manual_contains)]
     fn test_king_jump() {
         let mut board = Board::empty();
         board.set(11, Square::Taken(Piece::player2_king()));
@@ -749,4 +3674,276 @@ mod test {
         assert_eq!(board.get(16), Square::Taken(Piece::player1_pawn()));
         assert_eq!(board.get(21), Square::Empty);
     }
+
+    #[test]
+    fn test_player_try_from() {
+        for s in ["player1", "1", "black", "Player1", "BLACK"] {
+            assert_eq!(Player::try_from(s), Ok(Player::Player1));
+        }
+        for s in ["player2", "2", "white", "Player2", "White"] {
+            assert_eq!(Player::try_from(s), Ok(Player::Player2));
+        }
+        for s in ["", "3", "red", "player"] {
+            assert_eq!(Player::try_from(s), Err(ParsePlayerError));
+        }
+    }
+
+    #[test]
+    fn test_player_sign() {
+        assert_eq!(Player::Player1.sign(), 1);
+        assert_eq!(Player::Player2.sign(), -1);
+        assert_eq!(Player::Player1.forward_direction(), 1);
+        assert_eq!(Player::Player2.forward_direction(), -1);
+        for row in 0..ROWS {
+            assert_eq!(Player::Player1.relative_row(row), row);
+            assert_eq!(Player::Player2.relative_row(row), ROWS - 1 - row);
+        }
+    }
+
+    #[test]
+    fn test_play() {
+        let start = Board::new();
+        let (board, player) = start.play(Player::Player1, "11-15").unwrap();
+        let (board, player) = board.play(player, "23-19").unwrap();
+        let (board, player) = board.play(player, "8-11").unwrap();
+        assert_eq!(player, Player::Player2);
+
+        let mut expected = Board::new();
+        let mut side = Player::Player1;
+        for notation in ["11-15", "23-19", "8-11"] {
+            let movement = expected.parse_move(side, notation).unwrap();
+            expected.do_movement(&movement);
+            side = side.other();
+        }
+        assert_eq!(board.hash(), expected.hash());
+        assert_eq!(board.diagram(player), expected.diagram(side));
+        // the board played from is left as it was
+        assert_eq!(start.hash(), Board::new().hash());
+        assert_eq!(board.play(player, "8-11").unwrap_err(), MoveError::Illegal);
+    }
+
+    #[test]
+    fn test_parse_move() {
+        let board = Board::new();
+        let movement = board.parse_move(Player::Player1, "11-15").unwrap();
+        assert_eq!(movement.to_string(), "11-15");
+        assert_eq!(
+            board.parse_move(Player::Player1, "11-19"),
+            Err(MoveError::Illegal)
+        );
+        assert_eq!(
+            board.parse_move(Player::Player1, "11x15"),
+            Err(MoveError::Illegal)
+        );
+        for s in ["", "11", "11-", "11-15-19", "0-4", "11-33", "a-b"] {
+            assert_eq!(
+                board.parse_move(Player::Player1, s),
+                Err(MoveError::Malformed)
+            );
+        }
+
+        let (board, player) = Board::from_fen("B:W7,14,22:B2,9").unwrap();
+        let movement = board.parse_move(player, "9x18x25").unwrap();
+        assert_eq!(movement.to_string(), "9x18x25");
+        assert_eq!(board.parse_move(player, "9x25"), Ok(movement));
+        assert_eq!(board.parse_move(player, "9x18"), Err(MoveError::Illegal));
+
+        // a king going around a circle of pieces may be written either way
+        let (board, player) = Board::from_fen("B:W14,15,22,23:BK10").unwrap();
+        for route in ["10x19x26x17x10", "10x17x26x19x10"] {
+            let movement = board.parse_move(player, route).unwrap();
+            assert_eq!(movement.to_string(), route);
+            assert!(board.is_legal(&movement, player));
+        }
+        assert!(board.parse_move(player, "10x10").is_ok());
+    }
+
+    #[test]
+    fn test_reachable_hashes() {
+        let mut board = Board::new();
+        let hash = board.hash();
+        let perfts: Vec<u64> = (0..=4)
+            .map(|d| perft(&mut board, Player::Player1, d))
+            .collect();
+        assert_eq!(perfts, [1, 7, 49, 302, 1469]);
+
+        for depth in 0..=4 {
+            let reachable = board.reachable_hashes(Player::Player1, depth).len() as u64;
+            let nodes: u64 = perfts[..=depth as usize].iter().sum();
+            if depth < 3 {
+                assert_eq!(reachable, nodes);
+            } else {
+                // moving two pieces of the same side in either order transposes
+                assert!(reachable < nodes);
+            }
+        }
+        assert_eq!(hash, board.hash());
+    }
+
+    #[test]
+    fn test_no_hash_collisions() {
+        // every distinct arrangement of pieces reached has a hash of its own
+        let check = |board: &mut Board, player: Player, depth: u32| {
+            let mut seen: HashMap<u128, Board> = HashMap::new();
+            board.visit_reachable(player, depth, &mut |position| {
+                let first = seen
+                    .entry(position.hash())
+                    .or_insert_with(|| position.clone());
+                assert!(
+                    first.position_eq(position),
+                    "{} and {} share a hash",
+                    first.diagram(player),
+                    position.diagram(player)
+                );
+            });
+            seen.len()
+        };
+
+        let mut board = Board::new();
+        let start = board.clone();
+        assert_eq!(
+            check(&mut board, Player::Player1, 4),
+            board.reachable_hashes(Player::Player1, 4).len()
+        );
+        check(&mut board, Player::Player1, 6);
+        assert!(board.position_eq(&start));
+        assert!(!board.position_eq(&board.play(Player::Player1, "11-15").unwrap().0));
+
+        // positions with kings and promotions close at hand
+        for seed in 0..20 {
+            let mut board = random_position(&mut StdRng::seed_from_u64(seed));
+            check(&mut board, Player::Player1, 3);
+        }
+    }
+
+    #[test]
+    fn test_attackers_of() {
+        let (board, _) = Board::from_fen("W:W18,K10,19:B14,15").unwrap();
+        let square = |n| SquareId::new(n).unwrap();
+        let mut attackers: Vec<usize> = board
+            .attackers_of(square(14), Player::Player2)
+            .into_iter()
+            .map(SquareId::number)
+            .collect();
+        attackers.sort();
+        // the king on 10 jumps backwards to 17, the pawn on 18 jumps to 9
+        assert_eq!(attackers, [10, 18]);
+        // 18 also jumps 15 to 11, while 19 is blocked by the king on 10
+        assert_eq!(
+            board.attackers_of(square(15), Player::Player2),
+            [square(18)]
+        );
+        assert!(board.attackers_of(square(14), Player::Player1).is_empty());
+        assert!(board.attackers_of(square(1), Player::Player2).is_empty());
+    }
+
+    #[test]
+    fn test_pieces_hanging_after() {
+        let (mut board, player) = Board::from_fen("B:W22,23:B9,14").unwrap();
+        let hash = board.hash();
+        // 14-18 walks into both white pawns
+        let blunder = board.parse_move(player, "14-18").unwrap();
+        assert_eq!(board.pieces_hanging_after(&blunder, player), 1);
+        // 9-13 keeps both pawns out of reach
+        let safe = board.parse_move(player, "9-13").unwrap();
+        assert_eq!(board.pieces_hanging_after(&safe, player), 0);
+        assert_eq!(hash, board.hash());
+    }
+
+    #[test]
+    fn test_promotion_potential() {
+        // the pawn on 22 is two steps from the king row, the one on 5 six
+        let (mut board, _) = Board::from_fen("B:W1:B5,22").unwrap();
+        let before = board.hash();
+        assert_eq!(board.promotion_potential(Player::Player1, 1), 0);
+        assert_eq!(board.promotion_potential(Player::Player1, 2), 1);
+        assert_eq!(board.promotion_potential(Player::Player1, 6), 2);
+        assert_eq!(board.promotion_potential(Player::Player1, 100), 2);
+        assert_eq!(board.hash(), before);
+
+        // a pawn walled in by the opponent can not get through, and kings do not count
+        let (mut board, _) = Board::from_fen("B:W25,26,29,30,31:B22,K1").unwrap();
+        assert_eq!(board.promotion_potential(Player::Player1, 8), 0);
+    }
+
+    #[test]
+    fn test_forced_loss_after_capture() {
+        // Black has to take 10x19 and White answers with a double jump, two for one
+        let (mut board, player) = Board::from_fen("B:W15,31:B10,27").unwrap();
+        let hash = board.hash();
+        assert_eq!(board.forced_loss_after_capture(player), Some(-1));
+        assert_eq!(board.hash(), hash);
+        // without the pawn on 31 the capture is free
+        let (mut board, player) = Board::from_fen("B:W15:B10,27").unwrap();
+        assert_eq!(board.forced_loss_after_capture(player), Some(1));
+        // no capture to make
+        assert_eq!(
+            Board::new().forced_loss_after_capture(Player::Player1),
+            None
+        );
+        let (board, player) = Board::from_fen("B:W15,31:B10,27").unwrap();
+        let rules = Rules {
+            captures_forced: false,
+            ..Rules::default()
+        };
+        assert_eq!(
+            board.with_rules(rules).forced_loss_after_capture(player),
+            None
+        );
+    }
+
+    #[test]
+    fn test_phase() {
+        assert_eq!(Board::new().phase(), 0.0);
+        let (board, _) = Board::from_fen("B:W21-32:B1-6").unwrap();
+        assert_eq!(board.phase(), 0.25);
+        let (board, _) = Board::from_fen("B:WK32:BK1").unwrap();
+        assert!(board.phase() > 0.9);
+        assert_eq!(Board::empty().phase(), 1.0);
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for captures_forced in [true, false] {
+            let mut board = Board::new().with_rules(Rules {
+                captures_forced,
+                ..Rules::default()
+            });
+            let mut player = Player::Player1;
+            loop {
+                let movements = board.movements(player);
+                let legal_moves: Vec<Movement> = board.legal_moves(player).collect();
+                assert_eq!(legal_moves, movements);
+                if movements.is_empty() {
+                    break;
+                }
+                board.apply_and_promote(&movements[rng.gen_range(0..movements.len())], player);
+                player = player.other();
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_hash() {
+        let (board, player) = Board::from_fen("B:W18,K30,31:B2,9,K14").unwrap();
+        let rotated = board.rotated();
+        let (expected, _) = Board::from_fen("W:W31,24,K19:B15,K3,2").unwrap();
+        assert_eq!(rotated.squares, expected.squares);
+        assert_eq!(rotated.rotated().squares, board.squares);
+        assert!(rotated.check_hash());
+
+        assert_eq!(
+            board.canonical_hash(player),
+            rotated.canonical_hash(player.other())
+        );
+        // the same pieces with the other side to move are a different position
+        assert_ne!(
+            board.canonical_hash(player),
+            board.canonical_hash(player.other())
+        );
+        // the opening is its own rotation
+        let board = Board::new();
+        assert_eq!(board.rotated().squares, board.squares);
+    }
 }
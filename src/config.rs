@@ -0,0 +1,188 @@
+// This module contains the engine settings loaded from a TOML file with `--config`, so that the
+// search depth, the search features and the weights of the v2 and v5 evaluations can be changed
+// without recompiling. Every key is optional and a missing key keeps the setting from the command
+// line:
+//
+//      depth = 8
+//      alpha_beta = true
+//      quiescence = true
+//      weights = [100, 150, 40, 10, 5, -20, 10, 2]
+//
+// The `weights` are those of a [WeightedEval], in the order of its features, and select the v5
+// evaluation. The `back_rank_*` keys override the fields of [EVAL2_BACK_RANK] and select the v2
+// evaluation, so they can not be combined with `weights`.
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    checkers::{Board, Player},
+    error::Error,
+    minimax::{
+        evaluation2, evaluation5, BackRankWeights, Evaluator, MinimaxContext, StyleBias, TieBreak,
+        WeightedEval, EVAL2_BACK_RANK, WEIGHTED_EVAL, WEIGHTED_FEATURES,
+    },
+};
+
+// The settings read from a configuration file. The keys are named after the `--p1-*` options.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub depth: Option<u32>,
+    pub node_limit: Option<u64>,
+    pub alpha_beta: Option<bool>,
+    pub transposition_table: Option<bool>,
+    pub tt_threshold: Option<usize>,
+    pub quiescence: Option<bool>,
+    pub capture_nudge: Option<bool>,
+    pub iterative: Option<bool>,
+    pub ponder: Option<bool>,
+    pub eval_cache: Option<bool>,
+    pub fail_hard: Option<bool>,
+    pub move_ordering: Option<bool>,
+    pub profile: Option<bool>,
+    pub score_epsilon: Option<i32>,
+    pub aggression: Option<i32>,
+    pub random_ties: Option<bool>,
+    pub weights: Option<[i32; WEIGHTED_FEATURES]>,
+    pub back_rank_guard: Option<i32>,
+    pub back_rank_penalty: Option<i32>,
+    pub back_rank_phase: Option<f32>,
+}
+
+impl Config {
+    // Parse a configuration written in TOML.
+    pub fn parse(text: &str) -> Result<Config, Error> {
+        let config: Config =
+            toml::from_str(text).map_err(|e| Error::Config(e.message().to_string()))?;
+        if config.weights.is_some() && config.back_rank().is_some() {
+            return Err(Error::Config(
+                "weights and back_rank_* select different evaluations".to_string(),
+            ));
+        }
+        Ok(config)
+    }
+
+    // Read and parse the configuration file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, Error> {
+        let text = std::fs::read_to_string(path).map_err(|e| Error::Config(e.to_string()))?;
+        Self::parse(&text)
+    }
+
+    // The evaluation with the configured weights, or [WEIGHTED_EVAL] when none are set.
+    pub fn eval(&self) -> WeightedEval {
+        match self.weights {
+            Some(weights) => WeightedEval { weights },
+            None => WEIGHTED_EVAL,
+        }
+    }
+
+    // The back row weights of [evaluation2] with the configured ones, or None when none are set.
+    pub fn back_rank(&self) -> Option<BackRankWeights> {
+        if self.back_rank_guard.is_none()
+            && self.back_rank_penalty.is_none()
+            && self.back_rank_phase.is_none()
+        {
+            return None;
+        }
+        Some(BackRankWeights {
+            guard: self.back_rank_guard.unwrap_or(EVAL2_BACK_RANK.guard),
+            late_penalty: self
+                .back_rank_penalty
+                .unwrap_or(EVAL2_BACK_RANK.late_penalty),
+            late_phase: self.back_rank_phase.unwrap_or(EVAL2_BACK_RANK.late_phase),
+        })
+    }
+
+    // Override the settings of `ctx` with the ones in the configuration. As on the command line, a
+    // Transposition Table turns on Alpha-Beta Pruning. The weights only apply to the search of
+    // `ctx`, not to the v5 evaluation of other players.
+    pub fn context(&self, ctx: MinimaxContext) -> MinimaxContext {
+        let table = self.transposition_table.unwrap_or(ctx.table);
+        let (heuristic, evaluator) = match (self.weights, self.back_rank()) {
+            (Some(_), _) => (
+                evaluation5 as fn(&Board, Player) -> i32,
+                Some(Evaluator::Weighted(self.eval())),
+            ),
+            (None, Some(back_rank)) => (
+                evaluation2 as fn(&Board, Player) -> i32,
+                Some(Evaluator::Evaluation2(back_rank)),
+            ),
+            (None, None) => (ctx.heuristic, ctx.evaluator),
+        };
+        MinimaxContext {
+            table,
+            table_threshold: self.tt_threshold.unwrap_or(ctx.table_threshold),
+            depth: self.depth.unwrap_or(ctx.depth),
+            node_limit: self.node_limit.or(ctx.node_limit),
+            alpha_beta: self.alpha_beta.unwrap_or(ctx.alpha_beta) || table,
+            quiescence: self.quiescence.unwrap_or(ctx.quiescence),
+            capture_nudge: self.capture_nudge.unwrap_or(ctx.capture_nudge),
+            iterative: self.iterative.unwrap_or(ctx.iterative),
+            ponder: self.ponder.unwrap_or(ctx.ponder),
+            eval_cache: self.eval_cache.unwrap_or(ctx.eval_cache),
+            fail_soft: self.fail_hard.map_or(ctx.fail_soft, |hard| !hard),
+            move_ordering: self.move_ordering.unwrap_or(ctx.move_ordering),
+            profile: self.profile.unwrap_or(ctx.profile),
+            score_epsilon: self.score_epsilon.unwrap_or(ctx.score_epsilon),
+            style: StyleBias {
+                aggression: self.aggression.unwrap_or(ctx.style.aggression),
+            },
+            tie_break: match self.random_ties {
+                Some(true) => TieBreak::Random(ctx.seed),
+                Some(false) => TieBreak::Ordered,
+                None => ctx.tie_break,
+            },
+            heuristic,
+            evaluator,
+            ..ctx
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_file() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "depth = 9\ntransposition_table = true\nweights = [100, 120, 40, 10, 5, -20, 10, 2]\n",
+        )
+        .unwrap();
+        let config = Config::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+
+        let ctx = config.context(MinimaxContext::default());
+        assert_eq!(ctx.depth, 9);
+        assert!(ctx.table && ctx.alpha_beta);
+        assert!(!ctx.quiescence);
+        assert_eq!(config.eval().weights[1], 120);
+        assert_eq!(ctx.evaluator, Some(Evaluator::Weighted(config.eval())));
+
+        // nothing set keeps the context and the default weights
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.context(MinimaxContext::default()).depth, 6);
+        assert_eq!(config.eval(), WEIGHTED_EVAL);
+        assert_eq!(config.context(MinimaxContext::default()).evaluator, None);
+
+        // the back row weights select the v2 evaluation, keeping the weights not set
+        let config = Config::parse("back_rank_penalty = 25\nback_rank_phase = 0.75").unwrap();
+        let back_rank = BackRankWeights {
+            guard: EVAL2_BACK_RANK.guard,
+            late_penalty: 25,
+            late_phase: 0.75,
+        };
+        assert_eq!(config.back_rank(), Some(back_rank));
+        let ctx = config.context(MinimaxContext::default());
+        assert_eq!(ctx.evaluator, Some(Evaluator::Evaluation2(back_rank)));
+        assert!(Config::parse("weights = [1, 2, 3, 4, 5, 6, 7, 8]\nback_rank_guard = 5").is_err());
+
+        assert!(Config::parse("depht = 9").is_err());
+        assert!(Config::parse("weights = [1, 2]").is_err());
+        assert!(Config::from_file("missing.toml").is_err());
+    }
+}
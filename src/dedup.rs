@@ -0,0 +1,104 @@
+// This module contains an offline tool for removing duplicate positions from a collection, e.g.
+// positions collected from self-play games. Two positions are the same when their canonical
+// hashes are (see [Board::canonical_hash]), so a position and its rotation with the other side
+// to move count as one. The first position seen of each kind is kept, along with the number of
+// times it occurred.
+//
+// The tool reads one position in FEN per line and writes each unique position with its count:
+//
+//      dedup.1.fen = B:W18:B14
+//      dedup.1.count = 2
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::checkers::{Board, Player};
+
+// A position standing for all of its duplicates.
+pub struct Unique<T> {
+    // The first of the duplicates.
+    pub position: T,
+    // How many times the position occurred.
+    pub count: u32,
+}
+
+// Remove the duplicates from `positions`, keeping the first of each in the order they were seen.
+pub fn dedup<T>(
+    positions: impl IntoIterator<Item = T>,
+    board: impl Fn(&T) -> (Board, Player),
+) -> Vec<Unique<T>> {
+    let mut index: HashMap<u128, usize> = HashMap::new();
+    let mut unique: Vec<Unique<T>> = Vec::new();
+    for position in positions {
+        let (b, player) = board(&position);
+        let hash = b.canonical_hash(player);
+        match index.get(&hash) {
+            Some(&i) => unique[i].count += 1,
+            None => {
+                index.insert(hash, unique.len());
+                unique.push(Unique { position, count: 1 });
+            }
+        }
+    }
+    unique
+}
+
+// Remove the duplicates from the positions in FEN read from `input`, one per line, and write the
+// ones left to `output`. Blank lines are skipped and the first invalid FEN stops the tool.
+pub fn run<R: BufRead, W: Write>(input: R, output: &mut W) -> std::io::Result<()> {
+    let mut positions = Vec::new();
+    for line in input.lines() {
+        let line = line?;
+        let fen = line.trim();
+        if fen.is_empty() {
+            continue;
+        }
+        match Board::from_fen(fen) {
+            Some((board, player)) => positions.push((fen.to_string(), board, player)),
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid fen: {}", fen),
+                ))
+            }
+        }
+    }
+    let unique = dedup(positions, |(_, board, player)| (board.clone(), *player));
+    for (n, u) in unique.iter().enumerate() {
+        writeln!(output, "dedup.{}.fen = {}", n + 1, u.position.0)?;
+        writeln!(output, "dedup.{}.count = {}", n + 1, u.count)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dedup() {
+        let fens = [
+            "B:W18:B14",
+            // the same position turned around, with the other side to move
+            "W:W19:B15",
+            "W:W18:B14",
+            "B:W18:B14",
+        ];
+        let unique = dedup(fens, |fen| Board::from_fen(fen).unwrap());
+        let counts: Vec<(&str, u32)> = unique.iter().map(|u| (u.position, u.count)).collect();
+        assert_eq!(counts, [("B:W18:B14", 3), ("W:W18:B14", 1)]);
+    }
+
+    #[test]
+    fn test_run() {
+        let input = "B:W18:B14\n\nW:W19:B15\n";
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "dedup.1.fen = B:W18:B14\ndedup.1.count = 2\n"
+        );
+        assert!(run("B:W18:X14\n".as_bytes(), &mut Vec::new()).is_err());
+    }
+}
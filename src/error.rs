@@ -0,0 +1,88 @@
+// This module contains the error type returned at the public boundaries of the engine, where
+// input from a user or another program is parsed and applied to a [Board](crate::checkers::Board).
+use std::fmt;
+
+use crate::checkers::{
+    BuildError, HandicapError, IllegalMove, MoveError, SetupError, SquaresError,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Error {
+    // Terminal input that is not written in the expected format.
+    MalformedInput(String),
+    // Terminal input that names a square that does not exist.
+    UnknownSquare(String),
+    // A movement that starts from or jumps over an empty square.
+    MissingPiece(String),
+    // A movement in standard notation that can not be resolved.
+    Move(MoveError),
+    // A movement that is not legal for the player making it.
+    IllegalMove,
+    // A position in the setup format that can not be read.
+    Setup(SetupError),
+    // A handicap that can not be applied to the starting position.
+    Handicap(HandicapError),
+    // A padded array of squares that does not fit the board layout.
+    Squares(SquaresError),
+    // A placement of pieces that is not legal.
+    Build(BuildError),
+    // A configuration file that can not be read or parsed.
+    #[cfg(feature = "serde")]
+    Config(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MalformedInput(input) => write!(f, "malformed input: {}", input),
+            Self::UnknownSquare(square) => write!(f, "unknown square: {}", square),
+            Self::MissingPiece(square) => write!(f, "no piece on square: {}", square),
+            Self::Move(e) => write!(f, "{}", e),
+            Self::IllegalMove => write!(f, "{}", IllegalMove),
+            Self::Setup(e) => write!(f, "{}", e),
+            Self::Handicap(e) => write!(f, "{}", e),
+            Self::Squares(e) => write!(f, "{}", e),
+            Self::Build(e) => write!(f, "{}", e),
+            #[cfg(feature = "serde")]
+            Self::Config(message) => write!(f, "invalid configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<MoveError> for Error {
+    fn from(e: MoveError) -> Self {
+        Self::Move(e)
+    }
+}
+
+impl From<IllegalMove> for Error {
+    fn from(_: IllegalMove) -> Self {
+        Self::IllegalMove
+    }
+}
+
+impl From<SetupError> for Error {
+    fn from(e: SetupError) -> Self {
+        Self::Setup(e)
+    }
+}
+
+impl From<HandicapError> for Error {
+    fn from(e: HandicapError) -> Self {
+        Self::Handicap(e)
+    }
+}
+
+impl From<SquaresError> for Error {
+    fn from(e: SquaresError) -> Self {
+        Self::Squares(e)
+    }
+}
+
+impl From<BuildError> for Error {
+    fn from(e: BuildError) -> Self {
+        Self::Build(e)
+    }
+}
@@ -0,0 +1,414 @@
+// This module contains a game between two runners that is played one ply at a time, so that a
+// frontend with its own event loop can advance the game without blocking until it ends. The
+// rules for ending a game live here: a player with no legal movement on their turn loses, and a
+// game is drawn after a run of movements without a capture or promotion, or after a maximum
+// number of plies.
+use std::io::Write;
+
+use crate::{
+    checkers::{Board, Movement, Player},
+    runner::Runner,
+};
+
+// The number of plies without a capture or promotion after which a game is drawn.
+const DRAW_LIMIT: u32 = 40;
+// The shorter draw limit of a king ending under [Rules::king_ending_draw]: 13 moves each.
+pub const KING_ENDING_DRAW_LIMIT: u32 = 26;
+
+// The number of plies without a capture or promotion after which the game on `board` is drawn.
+// Under [Rules::king_ending_draw], one or two kings against a lone king are drawn sooner.
+fn draw_limit(board: &Board) -> u32 {
+    let (p1, p2) = board.piece_count();
+    let k1 = board.king_count(Player::Player1);
+    let k2 = board.king_count(Player::Player2);
+    let kings_only = p1 == k1 && p2 == k2;
+    let reduced = (k1 == 1 && (1..=2).contains(&k2)) || (k2 == 1 && (1..=2).contains(&k1));
+    if board.rules().king_ending_draw && kings_only && reduced {
+        KING_ENDING_DRAW_LIMIT
+    } else {
+        DRAW_LIMIT
+    }
+}
+
+// The state of a game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    // The game has not ended yet.
+    Ongoing,
+    Win(Player),
+    Draw,
+}
+
+impl GameResult {
+    // The winner of a finished game, or `None` for a draw or a game still being played.
+    pub fn winner(&self) -> Option<Player> {
+        match self {
+            Self::Win(player) => Some(*player),
+            Self::Ongoing | Self::Draw => None,
+        }
+    }
+}
+
+// What a call to [Match::step] did.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    // The side to move played the movement and the game goes on, or ends on the next step.
+    Moved(Movement),
+    // The game is over; every later step returns the same result.
+    Finished(GameResult),
+}
+
+// A game between `player1` and `player2` on `board`, with Player 1 moving first. As a safeguard
+// against games that never end, the game is drawn once `max_plies` plies have been played. The
+// output of the players, and the board after every ply when `verbose` is set, is written to
+// `out`.
+pub struct Match<'a, 'p1, 'p2> {
+    player1: &'a mut Runner<'p1>,
+    player2: &'a mut Runner<'p2>,
+    board: &'a mut Board,
+    // The side to move.
+    player: Player,
+    // The movements played so far.
+    history: Vec<Movement>,
+    // The plies played since the last capture or promotion.
+    draw: u32,
+    // The value of `draw` before each movement of `history`, to restore it when the movement is
+    // taken back.
+    draws: Vec<u32>,
+    max_plies: u32,
+    verbose: bool,
+    out: &'a mut dyn Write,
+    result: GameResult,
+}
+
+impl<'a, 'p1, 'p2> Match<'a, 'p1, 'p2> {
+    pub fn new(
+        player1: &'a mut Runner<'p1>,
+        player2: &'a mut Runner<'p2>,
+        board: &'a mut Board,
+        max_plies: u32,
+        verbose: bool,
+        out: &'a mut dyn Write,
+    ) -> Self {
+        Self {
+            player1,
+            player2,
+            board,
+            player: Player::Player1,
+            history: Vec::new(),
+            draw: 0,
+            draws: Vec::new(),
+            max_plies,
+            verbose,
+            out,
+            result: GameResult::Ongoing,
+        }
+    }
+
+    // The state of the game.
+    #[allow(dead_code)]
+    pub fn result(&self) -> GameResult {
+        self.result
+    }
+
+    // The number of plies played so far.
+    pub fn plies(&self) -> u32 {
+        self.history.len() as u32
+    }
+
+    // The movements played so far.
+    #[allow(dead_code)]
+    pub fn history(&self) -> &[Movement] {
+        &self.history
+    }
+
+    // Play one ply: ask the side to move for its movement and play it. A player with no legal
+    // movement on their turn loses, whether their pieces are all captured or only blocked, and
+    // even when the opponent is blocked too; so does a player whose runner gives up. The draw
+    // limit is checked at the end of each full move, after Player 2 has played.
+    pub fn step(&mut self) -> StepOutcome {
+        if self.result != GameResult::Ongoing {
+            return StepOutcome::Finished(self.result);
+        }
+        let player = self.player;
+        let movement = if self.board.legal_moves(player).next().is_none() {
+            None
+        } else {
+            match player {
+                Player::Player1 => self.player1.get_move(self.board, player, self.out),
+                Player::Player2 => self.player2.get_move(self.board, player, self.out),
+            }
+        };
+        // a human may take moves back before moving, which the runner has already undone on the
+        // board
+        let taken_back = match player {
+            Player::Player1 => self.player1.taken_back(),
+            Player::Player2 => self.player2.taken_back(),
+        };
+        for _ in 0..taken_back {
+            self.history.pop();
+            self.draw = self.draws.pop().unwrap_or(0);
+        }
+        let Some(movement) = movement else {
+            self.result = GameResult::Win(player.other());
+            return StepOutcome::Finished(self.result);
+        };
+
+        let info = self
+            .board
+            .do_movement_checked(&movement, player)
+            .expect("runners only return legal movements");
+        self.draws.push(self.draw);
+        if movement.is_jump() {
+            self.draw = 0;
+        } else {
+            self.draw += 1;
+        }
+        if info.crowned.is_some() {
+            match player {
+                Player::Player1 => self.player1.record_promotions(1),
+                Player::Player2 => self.player2.record_promotions(1),
+            }
+            self.draw = 0;
+        }
+        self.player1.record_move(&movement, info);
+        self.player2.record_move(&movement, info);
+        self.history.push(movement.clone());
+        self.player = player.other();
+        // the player thinks on the opponent's time while the opponent chooses a reply
+        match player {
+            Player::Player1 => self.player1.ponder(self.board, player),
+            Player::Player2 => self.player2.ponder(self.board, player),
+        }
+
+        if self.verbose {
+            writeln!(self.out, "{}", &self.board).unwrap();
+        }
+
+        let full_move = player == Player::Player2;
+        if (full_move && self.draw >= draw_limit(self.board)) || self.plies() >= self.max_plies {
+            self.result = GameResult::Draw;
+        }
+        StepOutcome::Moved(movement)
+    }
+}
+
+// Play a game between `player1` and `player2` starting from `board` to the end (see [Match]).
+// Returns the winner (`None` for a draw) and the number of plies played.
+pub fn play_game(
+    player1: &mut Runner,
+    player2: &mut Runner,
+    board: &mut Board,
+    max_plies: u32,
+    verbose: bool,
+    out: &mut dyn Write,
+) -> (Option<Player>, u32) {
+    let mut game = Match::new(player1, player2, board, max_plies, verbose, out);
+    loop {
+        if let StepOutcome::Finished(result) = game.step() {
+            return (result.winner(), game.plies());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        checkers::{square_id, square_name, Rules},
+        human::MovementMap,
+        minimax::{MinimaxContext, SharedTable},
+    };
+
+    #[test]
+    fn test_step() {
+        let (mut board, _) = Board::from_fen("B:W22,27:B10").unwrap();
+        let mut player1 = Runner::scripted(["10-14", "14x23x32"]);
+        let mut player2 = Runner::scripted(["22-18"]);
+        let mut sink = std::io::sink();
+        let mut game = Match::new(
+            &mut player1,
+            &mut player2,
+            &mut board,
+            100,
+            false,
+            &mut sink,
+        );
+
+        let mut played = Vec::new();
+        while let StepOutcome::Moved(movement) = game.step() {
+            assert_eq!(game.result(), GameResult::Ongoing);
+            played.push(movement.to_string());
+        }
+        assert_eq!(played, ["10-14", "22-18", "14x23x32"]);
+        assert_eq!(game.plies(), 3);
+        assert_eq!(game.history().len(), 3);
+        assert_eq!(game.result(), GameResult::Win(Player::Player1));
+        assert_eq!(game.result().winner(), Some(Player::Player1));
+        // the game stays finished
+        assert_eq!(
+            game.step(),
+            StepOutcome::Finished(GameResult::Win(Player::Player1))
+        );
+        assert_eq!(board.piece_count(), (1, 0));
+    }
+
+    #[test]
+    fn test_take_back() {
+        let name = |n| square_name(square_id(n).unwrap()).to_ascii_uppercase();
+        let input = format!(
+            "S: {} {}\ntakeback\nS: {} {}\n",
+            name(11),
+            name(15),
+            name(9),
+            name(13)
+        );
+        let mut player1 = Runner::human_with_input(MovementMap::new(), input.as_bytes());
+        let mut player2 = Runner::scripted(["22-18", "22-18"]);
+        let mut board = Board::new();
+        let mut sink = std::io::sink();
+        let mut game = Match::new(
+            &mut player1,
+            &mut player2,
+            &mut board,
+            100,
+            false,
+            &mut sink,
+        );
+
+        game.step();
+        game.step();
+        assert_eq!(game.plies(), 2);
+        assert_eq!(game.draw, 2);
+        // the human takes back 11-15 and 22-18 and plays 9-13 instead
+        assert!(matches!(game.step(), StepOutcome::Moved(m) if m.to_string() == "9-13"));
+        assert_eq!(game.plies(), 1);
+        assert_eq!(game.history()[0].to_string(), "9-13");
+        assert_eq!(game.draw, 1);
+        game.step();
+        assert_eq!(game.plies(), 2);
+        assert_eq!(game.draw, 2);
+    }
+
+    #[test]
+    fn test_max_plies() {
+        let ctx = MinimaxContext {
+            depth: 4,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let mut player1 = Runner::ai(ctx, SharedTable::new());
+        let mut player2 = Runner::ai(ctx, SharedTable::new());
+
+        // two lone kings can keep away from each other forever
+        let (mut board, _) = Board::from_fen("B:WK32:BK1").unwrap();
+
+        let (winner, plies) = play_game(
+            &mut player1,
+            &mut player2,
+            &mut board,
+            25,
+            false,
+            &mut std::io::sink(),
+        );
+        assert_eq!(winner, None);
+        assert_eq!(plies, 25);
+    }
+
+    #[test]
+    fn test_blocked() {
+        let play = |fen: &str| {
+            let (mut board, _) = Board::from_fen(fen).unwrap();
+            play_game(
+                &mut Runner::random(),
+                &mut Runner::random(),
+                &mut board,
+                100,
+                false,
+                &mut std::io::sink(),
+            )
+        };
+        // Player 1 is blocked while Player 2 could still move
+        assert_eq!(play("B:W5,6,10:B1"), (Some(Player::Player2), 0));
+        // both sides are blocked, so Player 1 loses on its turn
+        assert_eq!(play("B:W5-12:B1-4"), (Some(Player::Player2), 0));
+        // Player 1 can only move its king, after which Player 2 is blocked
+        assert_eq!(play("B:W5-12:B1-4,K32"), (Some(Player::Player1), 1));
+    }
+
+    #[test]
+    fn test_king_ending_draw() {
+        // both sides shuffle their kings back and forth
+        let play = |rules: Rules, fen: &str| {
+            let (board, _) = Board::from_fen(fen).unwrap();
+            let moves1 = ["1-6", "6-1"].repeat(20);
+            let moves2 = ["32-28", "28-32"].repeat(20);
+            play_game(
+                &mut Runner::scripted(moves1),
+                &mut Runner::scripted(moves2),
+                &mut board.with_rules(rules),
+                30,
+                false,
+                &mut std::io::sink(),
+            )
+        };
+        let rules = Rules {
+            king_ending_draw: true,
+            ..Rules::default()
+        };
+        assert_eq!(play(rules, "B:WK32:BK1,K3"), (None, KING_ENDING_DRAW_LIMIT));
+        assert_eq!(play(Rules::default(), "B:WK32:BK1,K3"), (None, 30));
+        // three kings against one are not a reduced ending
+        assert_eq!(play(rules, "B:WK32:BK1,K3,K4"), (None, 30));
+    }
+
+    #[test]
+    fn test_promotions() {
+        let mut player1 = Runner::random();
+        let mut player2 = Runner::random();
+
+        let (mut board, _) = Board::from_fen("B:WK1:B28").unwrap();
+        assert_eq!(board.king_count(Player::Player1), 0);
+
+        play_game(
+            &mut player1,
+            &mut player2,
+            &mut board,
+            1,
+            false,
+            &mut std::io::sink(),
+        );
+        assert_eq!(board.king_count(Player::Player1), 1);
+        assert_eq!(player1.into_stats().promotions, 1);
+        assert_eq!(player2.into_stats().promotions, 0);
+    }
+
+    #[test]
+    fn test_scripted_game() {
+        let play = |board: &mut Board, moves1: &[&str], moves2: &[&str]| {
+            play_game(
+                &mut Runner::scripted(moves1.iter().copied()),
+                &mut Runner::scripted(moves2.iter().copied()),
+                board,
+                100,
+                false,
+                &mut std::io::sink(),
+            )
+        };
+
+        // Player 2 walks into a double jump that takes its last pieces
+        let (mut board, _) = Board::from_fen("B:W22,27:B10").unwrap();
+        let result = play(&mut board, &["10-14", "14x23x32"], &["22-18"]);
+        assert_eq!(result, (Some(Player::Player1), 3));
+        assert_eq!(board.piece_count(), (1, 0));
+
+        // Player 1 resigns when its script runs out
+        let mut board = Board::new();
+        let result = play(&mut board, &["11-15"], &["23-19"]);
+        assert_eq!(result, (Some(Player::Player2), 2));
+        let (expected, _) = Board::from_fen("B:W19,21,22,24-32:B1-10,12,15")
+            .map(|(b, p)| (b.to_setup(p), p))
+            .unwrap();
+        assert_eq!(board.to_setup(Player::Player1), expected);
+    }
+}
@@ -1,9 +1,12 @@
 // This module contains the data structures and functions used to facilitate a terminal
 // interaction with a human playing a game of Checkers against the engine.
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{BufRead, Write};
 
-use crate::checkers::{Board, Movement, Player, Square, SquareState};
+use crate::{
+    checkers::{square_name, Board, Movement, Player, Square, SquareState},
+    error::Error,
+};
 
 // Construct a mapping of user inpt into padded array [Board] id's.
 pub struct MovementMap {
@@ -67,41 +70,47 @@ impl Default for MovementMap {
     }
 }
 
+// Look up the [Board] id of the square named `key`.
+fn square(map: &MovementMap, key: &str) -> Result<usize, Error> {
+    map.get(key)
+        .copied()
+        .ok_or_else(|| Error::UnknownSquare(key.to_string()))
+}
+
 fn parse_jump(
     board: &Board,
     map: &MovementMap,
     steps: &[&str],
     idx: usize,
     moving: Option<&SquareState>,
-) -> Option<Movement> {
+) -> Result<Movement, Error> {
     if steps.len() <= idx + 2 {
-        return None;
-    }
-    let start = map.get(steps[idx])?;
-    let jumped = map.get(steps[idx + 1])?;
-    let end = map.get(steps[idx + 2])?;
-
-    // nested jump from a multi-jump
-    if let Some(m) = moving {
-        if let Square::Taken(jumped_piece) = board.get(*jumped) {
-            let square_start = SquareState::piece(*start, m.piece.unwrap());
-            let square_jumped = SquareState::piece(*jumped, jumped_piece);
-            let square_end = SquareState::empty(*end);
-            return Some(Movement::jump(square_start, square_end, square_jumped));
-        }
+        return Err(Error::MalformedInput(steps.join(" ")));
     }
+    let start = square(map, steps[idx])?;
+    let jumped = square(map, steps[idx + 1])?;
+    let end = square(map, steps[idx + 2])?;
 
-    // normal jump or start of multi-jump
-    if let Square::Taken(start_piece) = board.get(*start) {
-        if let Square::Taken(jumped_piece) = board.get(*jumped) {
-            let square_start = SquareState::piece(*start, start_piece);
-            let square_jumped = SquareState::piece(*jumped, jumped_piece);
-            let square_end = SquareState::empty(*end);
-            return Some(Movement::jump(square_start, square_end, square_jumped));
-        }
-    }
-
-    None
+    let jumped_piece = match board.get(jumped) {
+        Square::Taken(piece) => piece,
+        _ => return Err(Error::MissingPiece(steps[idx + 1].to_string())),
+    };
+    let start_piece = match (moving, board.get(start)) {
+        // nested jump from a multi-jump
+        (
+            Some(SquareState {
+                piece: Some(piece), ..
+            }),
+            _,
+        ) => *piece,
+        // normal jump or start of multi-jump
+        (None, Square::Taken(piece)) => piece,
+        _ => return Err(Error::MissingPiece(steps[idx].to_string())),
+    };
+    let square_start = SquareState::piece(start, start_piece);
+    let square_jumped = SquareState::piece(jumped, jumped_piece);
+    let square_end = SquareState::empty(end);
+    Ok(Movement::jump(square_start, square_end, square_jumped))
 }
 
 fn parse_multi_jump(
@@ -111,59 +120,105 @@ fn parse_multi_jump(
     idx: usize,
     parent: &mut Movement,
     moving: SquareState,
-) {
+) -> Result<(), Error> {
     if steps.len() <= idx {
-        return;
+        return Ok(());
     }
     if steps[idx] != "J:" {
-        panic!("expected jump 1");
-    }
-    match parse_jump(board, map, steps, idx + 1, Some(&moving)) {
-        None => panic!("expected jump 2"),
-        Some(mut m) => {
-            parse_multi_jump(board, map, steps, idx + 4, &mut m, moving);
-            parent.set_next(&m);
-        }
+        return Err(Error::MalformedInput(steps.join(" ")));
     }
+    let mut m = parse_jump(board, map, steps, idx + 1, Some(&moving))?;
+    parse_multi_jump(board, map, steps, idx + 4, &mut m, moving)?;
+    parent.set_next(&m);
+    Ok(())
 }
 
-pub fn parse_input(line: &mut str, board: &Board, map: &MovementMap) -> Option<Movement> {
+pub fn parse_input(line: &mut str, board: &Board, map: &MovementMap) -> Result<Movement, Error> {
     let steps: Vec<&str> = line.trim().split(' ').collect();
 
     if steps.len() < 3 {
         if !steps.is_empty() && steps[0] == "?" {
             dbg!(board.movements(Player::Player1));
         }
-        return None;
+        return Err(Error::MalformedInput(line.trim().to_string()));
     }
 
     match steps[0] {
         "S:" => {
-            let start = map.get(steps[1])?;
-            let end = map.get(steps[2])?;
-            if let Square::Taken(piece) = board.get(*start) {
-                let square_start = SquareState::piece(*start, piece);
-                let square_end = SquareState::empty(*end);
-                return Some(Movement::simple(square_start, square_end));
+            let start = square(map, steps[1])?;
+            let end = square(map, steps[2])?;
+            if let Square::Taken(piece) = board.get(start) {
+                let square_start = SquareState::piece(start, piece);
+                let square_end = SquareState::empty(end);
+                return Ok(Movement::simple(square_start, square_end));
             }
-            None
+            Err(Error::MissingPiece(steps[1].to_string()))
         }
         "J:" => parse_jump(board, map, &steps, 1, None),
         "M:" => {
             let mut jump = parse_jump(board, map, &steps, 2, None)?;
             let moving = jump.from();
-            parse_multi_jump(board, map, &steps, 5, &mut jump, moving);
-            Some(jump)
+            parse_multi_jump(board, map, &steps, 5, &mut jump, moving)?;
+            Ok(jump)
         }
-        _ => None,
+        _ => Err(Error::MalformedInput(line.trim().to_string())),
     }
 }
 
-pub fn get_user_input(board: &Board, map: &MovementMap) -> Option<Movement> {
+// A line of input from the human player: a movement or a command.
+pub enum Input {
+    Move(Movement),
+    // Take back the last move of the human and the reply of the engine.
+    Takeback,
+    // The input has ended, so the human can not move any more.
+    Resign,
+}
+
+pub fn get_user_input(board: &Board, map: &MovementMap) -> Result<Input, Error> {
     std::io::stdout().flush().unwrap();
+    read_input(board, map, &mut std::io::stdin().lock())
+}
+
+// Read a line of input from `reader`, e.g. to play from a script instead of the terminal.
+pub fn read_input(
+    board: &Board,
+    map: &MovementMap,
+    reader: &mut dyn BufRead,
+) -> Result<Input, Error> {
     let mut line = String::new();
-    std::io::stdin().read_line(&mut line).unwrap();
-    parse_input(&mut line, board, map)
+    if reader.read_line(&mut line).unwrap() == 0 {
+        return Ok(Input::Resign);
+    }
+    match line.trim() {
+        "takeback" | "undo" => Ok(Input::Takeback),
+        _ => parse_input(&mut line, board, map).map(Input::Move),
+    }
+}
+
+// Explain to a human why `movement` is not legal for `player`: a capture is forced, a jump
+// stops before it has captured all it can, or else the movement is simply illegal.
+pub fn explain_illegal(board: &Board, movement: &Movement, player: Player) -> String {
+    let name = |id: usize| square_name(id).to_ascii_uppercase();
+    let jumps: Vec<Movement> = board.legal_moves(player).filter(|m| m.is_jump()).collect();
+    if !movement.is_jump() && board.rules().captures_forced && !jumps.is_empty() {
+        let mut from: Vec<usize> = jumps.iter().map(|m| m.from().id).collect();
+        from.sort();
+        from.dedup();
+        let from: Vec<String> = from.into_iter().map(name).collect();
+        return format!(
+            "you must capture: a jump is available from {}",
+            from.join(", ")
+        );
+    }
+    let continues = jumps.iter().any(|m| {
+        m.from().id == movement.from().id
+            && m.to().id == movement.to().id
+            && m.captures() > movement.captures()
+    });
+    if movement.is_jump() && continues {
+        return format!("you must keep jumping from {}", name(movement.end().id));
+    }
+    Error::IllegalMove.to_string()
 }
 
 #[cfg(test)]
@@ -181,7 +236,7 @@ mod test {
         let map = MovementMap::new();
         let mut input = "M: J: B7 C6 D5 J: D5 E4 F3".to_string();
         let movement = parse_input(&mut input, &board, &map);
-        assert!(movement.is_some());
+        assert!(movement.is_ok());
         let expected = Movement::multi_jump(
             SquareState::piece(10, Piece::player1_pawn()),
             SquareState::empty(20),
@@ -195,6 +250,22 @@ mod test {
         assert_eq!(expected, movement.unwrap());
     }
 
+    #[test]
+    fn test_explain_illegal() {
+        let (board, player) = Board::from_fen("B:W18,27:B9,14").unwrap();
+        let map = MovementMap::new();
+        let explain = |input: &str| {
+            let movement = parse_input(&mut input.to_string(), &board, &map).unwrap();
+            explain_illegal(&board, &movement, player)
+        };
+        assert_eq!(
+            explain("S: G6 H5"),
+            "you must capture: a jump is available from F5"
+        );
+        assert_eq!(explain("J: F5 E4 D3"), "you must keep jumping from D3");
+        assert_eq!(explain("J: G6 F5 E4"), "illegal move");
+    }
+
     #[test]
     fn test_parse_jump() {
         let mut board = Board::empty();
@@ -203,7 +274,7 @@ mod test {
         let map = MovementMap::new();
         let mut input = "J: G6 F5 E4".to_string();
         let movement = parse_input(&mut input, &board, &map);
-        assert!(movement.is_some());
+        assert!(movement.is_ok());
         let expected = Movement::jump(
             SquareState::piece(17, Piece::player1_pawn()),
             SquareState::empty(25),
@@ -211,4 +282,34 @@ mod test {
         );
         assert_eq!(expected, movement.unwrap());
     }
+
+    #[test]
+    fn test_parse_malformed_multi_jump() {
+        let mut board = Board::empty();
+        board.set(10, Square::Taken(Piece::player1_pawn()));
+        board.set(15, Square::Taken(Piece::player2_pawn()));
+        board.set(25, Square::Taken(Piece::player2_pawn()));
+        let map = MovementMap::new();
+        for (input, expected) in [
+            (
+                "M: J: B7 C6 D5 X: D5 E4 F3",
+                Error::MalformedInput("M: J: B7 C6 D5 X: D5 E4 F3".to_string()),
+            ),
+            (
+                "M: J: B7 C6 D5 J: D5 E4",
+                Error::MalformedInput("M: J: B7 C6 D5 J: D5 E4".to_string()),
+            ),
+            (
+                "M: J: B7 C6 D5 J: D5 Z9 F3",
+                Error::UnknownSquare("Z9".to_string()),
+            ),
+            (
+                "M: J: B7 C6 D5 J: D5 G4 H3",
+                Error::MissingPiece("G4".to_string()),
+            ),
+        ] {
+            let mut input = input.to_string();
+            assert_eq!(parse_input(&mut input, &board, &map), Err(expected));
+        }
+    }
 }
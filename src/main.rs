@@ -1,78 +1,61 @@
-use clap::{Parser, ValueEnum};
-use minimax::{evaluation1, evaluation2, evaluation3, MinimaxContext};
-use runner::Runner;
-use std::collections::HashMap;
+use clap::{Parser, Subcommand, ValueEnum};
+use minimax::{Evaluator, StatsAccumulator, StyleBias, TieBreak, Verbosity};
+use std::io::Write;
 use uuid::Uuid;
 
 mod checkers;
+#[cfg(feature = "serde")]
+mod config;
+mod dedup;
+mod error;
+mod game;
 mod human;
 mod minimax;
+mod prelude;
+mod protocol;
 mod runner;
+#[cfg(test)]
+mod tactics;
+mod tournament;
+#[cfg(feature = "tuning")]
+mod tuning;
 
-use checkers::{Board, Player};
+use checkers::Rules;
+use game::play_game;
 use human::MovementMap;
-
-const DRAW_LIMIT: u32 = 40;
-
-// The main game loop of a game against `player1` and `player2`.
-fn game_loop(mut player1: Runner, mut player2: Runner, gameid: &Uuid, verbose: bool) {
-    let mut board = Board::new();
-    let mut draw = 0;
-    let mut winner: Option<Player> = None;
-    loop {
-        // PLAYER 1
-        if let Some(movement) = player1.get_move(&mut board, Player::Player1) {
-            board.do_movement(&movement);
-            if movement.is_jump() {
-                draw = 0;
-            } else {
-                draw += 1;
-            }
-            if board.mark_kings() > 0 {
-                draw = 0;
-            }
-        } else {
-            winner = Some(Player::Player2);
-            break;
-        }
-
-        if verbose {
-            println!("{}", &board);
-        }
-
-        // PLAYER 2
-        if let Some(movement) = player2.get_move(&mut board, Player::Player2) {
-            board.do_movement(&movement);
-            if movement.is_jump() {
-                draw = 0;
-            } else {
-                draw += 1;
-            }
-            if board.mark_kings() > 0 {
-                draw = 0;
-            }
-        } else {
-            winner = Some(Player::Player1);
-            break;
-        }
-
-        if verbose {
-            println!("{}", &board);
-        }
-
-        if draw >= DRAW_LIMIT {
-            break;
-        }
-    }
+use prelude::*;
+
+// The main game loop of a game against `player1` and `player2`, writing the game and its
+// results to `out`. Returns the [Stats] of both players.
+fn game_loop(
+    mut player1: Runner,
+    mut player2: Runner,
+    gameid: &Uuid,
+    rules: Rules,
+    max_plies: u32,
+    verbose: bool,
+    out: &mut dyn Write,
+) -> std::io::Result<(Stats, Stats)> {
+    let mut board = Board::new().with_rules(rules);
+    let (winner, _) = play_game(
+        &mut player1,
+        &mut player2,
+        &mut board,
+        max_plies,
+        verbose,
+        out,
+    );
 
     match winner {
-        None => println!("game.{}.winner = draw", &gameid),
-        Some(Player::Player1) => println!("game.{}.winner = player1", &gameid),
-        Some(Player::Player2) => println!("game.{}.winner = player2", &gameid),
+        None => writeln!(out, "game.{}.winner = draw", &gameid)?,
+        Some(Player::Player1) => writeln!(out, "game.{}.winner = player1", &gameid)?,
+        Some(Player::Player2) => writeln!(out, "game.{}.winner = player2", &gameid)?,
     }
 
-    player1.display_stats("player1", gameid);
-    player2.display_stats("player2", gameid);
+    player1.display_stats(out, "player1", gameid)?;
+    player2.display_stats(out, "player2", gameid)?;
+
+    Ok((player1.into_stats(), player2.into_stats()))
 }
 
 // The possible engines to use.
@@ -97,6 +80,8 @@ enum Eval {
     V1,
     V2,
     V3,
+    V4,
+    V5,
 }
 
 impl std::fmt::Display for Eval {
@@ -105,6 +90,8 @@ impl std::fmt::Display for Eval {
             Eval::V1 => write!(f, "v1"),
             Eval::V2 => write!(f, "v2"),
             Eval::V3 => write!(f, "v3"),
+            Eval::V4 => write!(f, "v4"),
+            Eval::V5 => write!(f, "v5"),
         }
     }
 }
@@ -115,13 +102,36 @@ impl Eval {
             Eval::V1 => evaluation1,
             Eval::V2 => evaluation2,
             Eval::V3 => evaluation3,
+            Eval::V4 => evaluation4,
+            Eval::V5 => evaluation5,
         }
     }
 }
 
+// The subcommands that run instead of a game.
+#[derive(Subcommand)]
+enum Command {
+    /// Print the best moves for the side to move in a position (with the Player 1 settings)
+    Analyze {
+        /// The position in FEN, e.g. "B:W21-32:B1-12"
+        #[arg(long)]
+        fen: String,
+        /// Search depth
+        #[arg(long, default_value_t = 8)]
+        depth: u32,
+        /// How many moves to print
+        #[arg(long, default_value_t = 3)]
+        top: usize,
+    },
+    /// Remove duplicate positions, read in FEN one per line from stdin, counting each
+    Dedup,
+}
+
 // The command line options.
 #[derive(Parser)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Player 1 engine
     #[arg(long, default_value = "ai")]
     p1_engine: Engine,
@@ -131,18 +141,59 @@ struct Cli {
     /// Enable the use of a Transposition Table with Alpha-Beta Pruning for Player 1
     #[arg(long)]
     p1_transposition_table: bool,
+    /// Only use the Transposition Table for Player 1 when both sides have at least this many moves
+    /// between them (0: always)
+    #[arg(long, default_value_t = 0, value_name = "MOVES")]
+    p1_tt_threshold: usize,
     /// Enable quiescence search for Player 1
     #[arg(long)]
     p1_quiescence: bool,
+    /// Favour leaves where a winning capture is pending, a lighter alternative to quiescence
+    /// search, for Player 1
+    #[arg(long)]
+    p1_capture_nudge: bool,
     /// Enable iterative deepening search for Player 1
     #[arg(long)]
     p1_iterative: bool,
+    /// Enable pondering on the opponent's time for Player 1
+    #[arg(long)]
+    p1_ponder: bool,
+    /// Enable caching of leaf evaluations within a search for Player 1
+    #[arg(long)]
+    p1_eval_cache: bool,
+    /// Clamp search scores to the alpha-beta window (fail-hard) for Player 1
+    #[arg(long)]
+    p1_fail_hard: bool,
+    /// Search moves that leave fewer pieces hanging first for Player 1
+    #[arg(long)]
+    p1_move_ordering: bool,
+    /// Report the time spent in each part of the search for Player 1
+    #[arg(long)]
+    p1_profile: bool,
+    /// Play a random move among those scoring within this many centipawns of the best for Player 1
+    #[arg(long, default_value_t = 0, value_name = "CP")]
+    p1_score_epsilon: i32,
+    /// Bonus in centipawns for each capture and attack of a move, favouring aggressive play for
+    /// Player 1
+    #[arg(long, default_value_t = 0, value_name = "CP")]
+    p1_aggression: i32,
+    /// Break ties between equally scored moves at random (seeded with --seed) for Player 1
+    #[arg(long)]
+    p1_random_ties: bool,
     /// AI search depth limit for Player 1
     #[arg(long, default_value_t = 6)]
     p1_depth: u32,
+    /// Stop each search of Player 1 after exploring this many nodes, playing the best move found so
+    /// far (0: no limit)
+    #[arg(long, default_value_t = 0, value_name = "NODES")]
+    p1_node_limit: u64,
     /// Player 1 evaluation function
     #[arg(long, default_value = "v1")]
     p1_eval: Eval,
+    /// Override the Player 1 settings with a TOML file
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "PATH")]
+    config: Option<std::path::PathBuf>,
     /// Player 2 engine
     #[arg(long, default_value = "random")]
     p2_engine: Engine,
@@ -152,32 +203,140 @@ struct Cli {
     /// Enable the use of a Transposition Table with Alpha-Beta Pruning for Player 2
     #[arg(long)]
     p2_transposition_table: bool,
+    /// Only use the Transposition Table for Player 2 when both sides have at least this many moves
+    /// between them (0: always)
+    #[arg(long, default_value_t = 0, value_name = "MOVES")]
+    p2_tt_threshold: usize,
     /// Enable quiescence search for Player 2
     #[arg(long)]
     p2_quiescence: bool,
+    /// Favour leaves where a winning capture is pending, a lighter alternative to quiescence
+    /// search, for Player 2
+    #[arg(long)]
+    p2_capture_nudge: bool,
     /// Enable iterative deepening search for Player 2
     #[arg(long)]
     p2_iterative: bool,
+    /// Enable pondering on the opponent's time for Player 2
+    #[arg(long)]
+    p2_ponder: bool,
+    /// Enable caching of leaf evaluations within a search for Player 2
+    #[arg(long)]
+    p2_eval_cache: bool,
+    /// Clamp search scores to the alpha-beta window (fail-hard) for Player 2
+    #[arg(long)]
+    p2_fail_hard: bool,
+    /// Search moves that leave fewer pieces hanging first for Player 2
+    #[arg(long)]
+    p2_move_ordering: bool,
+    /// Report the time spent in each part of the search for Player 2
+    #[arg(long)]
+    p2_profile: bool,
+    /// Play a random move among those scoring within this many centipawns of the best for Player 2
+    #[arg(long, default_value_t = 0, value_name = "CP")]
+    p2_score_epsilon: i32,
+    /// Bonus in centipawns for each capture and attack of a move, favouring aggressive play for
+    /// Player 2
+    #[arg(long, default_value_t = 0, value_name = "CP")]
+    p2_aggression: i32,
+    /// Break ties between equally scored moves at random (seeded with --seed) for Player 2
+    #[arg(long)]
+    p2_random_ties: bool,
     /// AI search depth limit for Player 2
     #[arg(long, default_value_t = 6)]
     p2_depth: u32,
+    /// Stop each search of Player 2 after exploring this many nodes, playing the best move found so
+    /// far (0: no limit)
+    #[arg(long, default_value_t = 0, value_name = "NODES")]
+    p2_node_limit: u64,
     /// Player 2 evaluation function
     #[arg(long, default_value = "v1")]
     p2_eval: Eval,
     /// You (Player 1) against the engine (Player 2)
     #[arg(long)]
     play: bool,
+    /// Drive the engine (with the Player 1 settings) through a text protocol on stdin/stdout
+    #[arg(long)]
+    protocol: bool,
+    /// Fit the weights of the v5 evaluation to this many self-play games (with the Player 1
+    /// settings)
+    #[cfg(feature = "tuning")]
+    #[arg(long)]
+    tune: Option<u32>,
+    /// Play a round-robin tournament between these evaluation functions (with the Player 1
+    /// settings)
+    #[arg(long, value_delimiter = ',', value_name = "EVALS")]
+    tournament: Vec<Eval>,
+    /// Seed for the random openings of a tournament and the random moves and ties of the engines
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Allow captures to be declined (a casual variant)
+    #[arg(long)]
+    optional_captures: bool,
+    /// Draw one or two kings against a lone king after 13 moves each without a capture or promotion
+    #[arg(long)]
+    king_ending_draw: bool,
+    /// Score a game as a draw after this many plies
+    #[arg(long, default_value_t = 400)]
+    max_plies: u32,
     /// How many games to simulate
     #[arg(short, long, default_value_t = 1)]
     games: u32,
-    /// Show moves made by engines during simulation
-    #[arg(short, long)]
-    verbose: bool,
+    /// Show moves made by engines during simulation (-v), with every search iteration (-vv) and
+    /// every movement searched (-vvv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+// Print the best `top` moves in the position described by `fen`.
+fn analyze(ctx: MinimaxContext, fen: &str, depth: u32, top: usize) {
+    let ctx = MinimaxContext {
+        alpha_beta: true,
+        table: true,
+        table_threshold: 0,
+        ..ctx
+    };
+    let Some((mut board, player)) = Board::from_fen(fen) else {
+        eprintln!("invalid fen: {}", fen);
+        std::process::exit(1);
+    };
+    let results = minimax::analyze(
+        &mut Stats::new(),
+        &ctx,
+        &mut board,
+        player,
+        &mut TranspositionTable::new(),
+        depth,
+        top,
+    );
+    for (rank, result) in results.iter().enumerate() {
+        let movement = result.movement.as_ref().unwrap();
+        let score = match result.mate_in {
+            Some(n) => format!("mate {}", n),
+            None => result.score.to_string(),
+        };
+        let pv: Vec<String> = result.pv.iter().map(|m| m.to_string()).collect();
+        println!("analyze.{}.move = {}", rank + 1, movement);
+        println!("analyze.{}.score = {}", rank + 1, score);
+        println!("analyze.{}.pv = {}", rank + 1, pv.join(" "));
+        println!(
+            "analyze.{}.pdn = {}",
+            rank + 1,
+            result.pv_to_pdn(&board, player)
+        );
+    }
 }
 
 fn display_cli_config(cli: &Cli) {
     println!("config.games = {}", cli.games);
-    println!("config.verbose = {}", cli.verbose);
+    println!("config.verbose = {}", Verbosity::from_count(cli.verbose));
+    println!("config.optional_captures = {}", cli.optional_captures);
+    println!("config.king_ending_draw = {}", cli.king_ending_draw);
+    println!("config.max_plies = {}", cli.max_plies);
+    #[cfg(feature = "serde")]
+    if let Some(path) = &cli.config {
+        println!("config.file = {}", path.display());
+    }
 
     println!("config.player1.engine = {}", cli.p1_engine);
     println!("config.player1.alpha_beta = {}", cli.p1_alpha_beta);
@@ -185,9 +344,20 @@ fn display_cli_config(cli: &Cli) {
         "config.player1.transposition_table = {}",
         cli.p1_transposition_table
     );
+    println!("config.player1.tt_threshold = {}", cli.p1_tt_threshold);
     println!("config.player1.quiescence = {}", cli.p1_quiescence);
+    println!("config.player1.capture_nudge = {}", cli.p1_capture_nudge);
     println!("config.player1.depth = {}", cli.p1_depth);
+    println!("config.player1.node_limit = {}", cli.p1_node_limit);
     println!("config.player1.iterative = {}", cli.p1_iterative);
+    println!("config.player1.ponder = {}", cli.p1_ponder);
+    println!("config.player1.eval_cache = {}", cli.p1_eval_cache);
+    println!("config.player1.fail_hard = {}", cli.p1_fail_hard);
+    println!("config.player1.move_ordering = {}", cli.p1_move_ordering);
+    println!("config.player1.profile = {}", cli.p1_profile);
+    println!("config.player1.score_epsilon = {}", cli.p1_score_epsilon);
+    println!("config.player1.aggression = {}", cli.p1_aggression);
+    println!("config.player1.random_ties = {}", cli.p1_random_ties);
     println!("config.player1.eval = {}", cli.p1_eval);
 
     println!("config.player2.engine = {}", cli.p2_engine);
@@ -196,67 +366,213 @@ fn display_cli_config(cli: &Cli) {
         "config.player2.transposition_table = {}",
         cli.p2_transposition_table
     );
+    println!("config.player2.tt_threshold = {}", cli.p2_tt_threshold);
     println!("config.player2.quiescence = {}", cli.p2_quiescence);
+    println!("config.player2.capture_nudge = {}", cli.p2_capture_nudge);
     println!("config.player2.depth = {}", cli.p2_depth);
+    println!("config.player2.node_limit = {}", cli.p2_node_limit);
     println!("config.player2.iterative = {}", cli.p2_iterative);
+    println!("config.player2.ponder = {}", cli.p2_ponder);
+    println!("config.player2.eval_cache = {}", cli.p2_eval_cache);
+    println!("config.player2.fail_hard = {}", cli.p2_fail_hard);
+    println!("config.player2.move_ordering = {}", cli.p2_move_ordering);
+    println!("config.player2.profile = {}", cli.p2_profile);
+    println!("config.player2.score_epsilon = {}", cli.p2_score_epsilon);
+    println!("config.player2.aggression = {}", cli.p2_aggression);
+    println!("config.player2.random_ties = {}", cli.p2_random_ties);
     println!("config.player2.eval = {}", cli.p2_eval);
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    display_cli_config(&cli);
-
-    let ctx_p1 = MinimaxContext {
+    #[allow(unused_mut)]
+    let mut ctx_p1 = MinimaxContext {
         table: cli.p1_transposition_table,
+        table_threshold: cli.p1_tt_threshold,
         depth: cli.p1_depth,
+        node_limit: (cli.p1_node_limit > 0).then_some(cli.p1_node_limit),
         alpha_beta: cli.p1_alpha_beta || cli.p1_transposition_table,
         quiescence: cli.p1_quiescence,
+        capture_nudge: cli.p1_capture_nudge,
         iterative: cli.p1_iterative,
-        verbose: cli.verbose,
+        ponder: cli.p1_ponder,
+        eval_cache: cli.p1_eval_cache,
+        fail_soft: !cli.p1_fail_hard,
+        move_ordering: cli.p1_move_ordering,
+        order_moves: None,
+        tie_break: if cli.p1_random_ties {
+            TieBreak::Random(cli.seed)
+        } else {
+            TieBreak::Ordered
+        },
+        profile: cli.p1_profile,
+        score_epsilon: cli.p1_score_epsilon,
+        seed: cli.seed,
+        absolute_scores: false,
+        style: StyleBias {
+            aggression: cli.p1_aggression,
+        },
+        verbosity: Verbosity::from_count(cli.verbose),
         heuristic: cli.p1_eval.as_fn(),
+        evaluator: None,
     };
 
     let ctx_p2 = MinimaxContext {
         table: cli.p2_transposition_table,
+        table_threshold: cli.p2_tt_threshold,
         depth: cli.p2_depth,
+        node_limit: (cli.p2_node_limit > 0).then_some(cli.p2_node_limit),
         alpha_beta: cli.p2_alpha_beta || cli.p2_transposition_table,
         quiescence: cli.p2_quiescence,
+        capture_nudge: cli.p2_capture_nudge,
         iterative: cli.p2_iterative,
-        verbose: cli.verbose,
+        ponder: cli.p2_ponder,
+        eval_cache: cli.p2_eval_cache,
+        fail_soft: !cli.p2_fail_hard,
+        move_ordering: cli.p2_move_ordering,
+        order_moves: None,
+        tie_break: if cli.p2_random_ties {
+            TieBreak::Random(cli.seed)
+        } else {
+            TieBreak::Ordered
+        },
+        profile: cli.p2_profile,
+        score_epsilon: cli.p2_score_epsilon,
+        seed: cli.seed,
+        absolute_scores: false,
+        style: StyleBias {
+            aggression: cli.p2_aggression,
+        },
+        verbosity: Verbosity::from_count(cli.verbose),
         heuristic: cli.p2_eval.as_fn(),
+        evaluator: None,
     };
 
-    if cli.play {
-        let mut table = HashMap::with_capacity(100_000);
+    #[cfg(feature = "serde")]
+    if let Some(path) = &cli.config {
+        let config = match config::Config::from_file(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        };
+        ctx_p1 = config.context(ctx_p1);
+    }
+
+    match &cli.command {
+        Some(Command::Analyze { fen, depth, top }) => {
+            analyze(ctx_p1, fen, *depth, *top);
+            return;
+        }
+        Some(Command::Dedup) => {
+            let stdin = std::io::stdin();
+            if let Err(e) = dedup::run(stdin.lock(), &mut std::io::stdout()) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+
+    if cli.protocol {
+        let stdin = std::io::stdin();
+        protocol::run(stdin.lock(), &mut std::io::stdout(), ctx_p1).unwrap();
+        return;
+    }
+
+    #[cfg(feature = "tuning")]
+    if let Some(games) = cli.tune {
+        tuning::run(&ctx_p1, games);
+        return;
+    }
+
+    display_cli_config(&cli);
 
+    let rules = Rules {
+        captures_forced: !cli.optional_captures,
+        king_ending_draw: cli.king_ending_draw,
+    };
+
+    if !cli.tournament.is_empty() {
+        let engines: Vec<MinimaxContext> = cli
+            .tournament
+            .iter()
+            .map(|eval| MinimaxContext {
+                heuristic: eval.as_fn(),
+                // the configured weights only replace the evaluation they belong to
+                evaluator: ctx_p1.evaluator.filter(|evaluator| {
+                    matches!(
+                        (evaluator, eval),
+                        (Evaluator::Evaluation2(_), Eval::V2) | (Evaluator::Weighted(_), Eval::V5)
+                    )
+                }),
+                ..ctx_p1
+            })
+            .collect();
+        let names: Vec<String> = cli.tournament.iter().map(|e| e.to_string()).collect();
+        let results = tournament::run(&engines, cli.games, cli.seed, rules, cli.max_plies);
+        results.display(&names, &mut std::io::stdout()).unwrap();
+    } else if cli.play {
         let gameid = Uuid::new_v4();
 
         let player1 = Runner::human(MovementMap::new());
         let player2 = match cli.p2_engine {
-            Engine::AI => Runner::ai(ctx_p2, &mut table),
+            Engine::AI => Runner::ai(ctx_p2, SharedTable::new()),
             Engine::Random => Runner::random(),
         };
 
-        game_loop(player1, player2, &gameid, false);
+        game_loop(
+            player1,
+            player2,
+            &gameid,
+            rules,
+            cli.max_plies,
+            false,
+            &mut std::io::stdout(),
+        )
+        .unwrap();
     } else {
-        let mut table1 = HashMap::with_capacity(100_000);
-        let mut table2 = HashMap::with_capacity(100_000);
-
-        for _ in 0..cli.games {
+        // Each engine keeps its own Transposition Table for the whole run, shared between its
+        // searches and the worker thread that ponders on the opponent's time. The engines do not
+        // share a table with each other, as they may use different evaluations.
+        let table1 = SharedTable::new();
+        let table2 = SharedTable::new();
+        let mut summary1 = StatsAccumulator::new();
+        let mut summary2 = StatsAccumulator::new();
+
+        for game in 0..cli.games {
             let gameid = Uuid::new_v4();
 
+            // each game gets its own seed, so that the games differ from each other
+            let seed = cli.seed.wrapping_add(game as u64);
             let player1 = match cli.p1_engine {
-                Engine::AI => Runner::ai(ctx_p1, &mut table1),
+                Engine::AI => Runner::ai(MinimaxContext { seed, ..ctx_p1 }, table1.clone()),
                 Engine::Random => Runner::random(),
             };
             let player2 = match cli.p2_engine {
-                Engine::AI => Runner::ai(ctx_p2, &mut table2),
+                Engine::AI => Runner::ai(MinimaxContext { seed, ..ctx_p2 }, table2.clone()),
                 Engine::Random => Runner::random(),
             };
 
-            game_loop(player1, player2, &gameid, cli.verbose);
+            let (stats1, stats2) = game_loop(
+                player1,
+                player2,
+                &gameid,
+                rules,
+                cli.max_plies,
+                cli.verbose > 0,
+                &mut std::io::stdout(),
+            )
+            .unwrap();
+            summary1.add(&stats1);
+            summary2.add(&stats2);
         }
+
+        summary1.display(&mut std::io::stdout(), "player1").unwrap();
+        summary2.display(&mut std::io::stdout(), "player2").unwrap();
     }
 }
 
@@ -271,17 +587,13 @@ mod test {
     use super::*;
 
     #[test]
+    #[allow(clippy::manual_contains)]
     fn test_bugfix_1() {
         let ctx = MinimaxContext {
-            table: false,
-            depth: 6,
             alpha_beta: true,
-            quiescence: false,
-            verbose: false,
-            iterative: false,
-            heuristic: evaluation1,
+            ..Default::default()
         };
-        let mut table = HashMap::new();
+        let mut table = TranspositionTable::new();
 
         let mut board = Board::empty();
 
@@ -300,7 +612,7 @@ mod test {
         let map = MovementMap::new();
         let movement = parse_input(&mut input, &board, &map);
 
-        assert!(movement.is_some());
+        assert!(movement.is_ok());
 
         let movement = movement.unwrap();
         let movements = board.movements(Player::Player1);
@@ -315,6 +627,7 @@ mod test {
             &mut board,
             Player::Player2,
             &mut table,
+            None,
         );
 
         assert!(ai_movement.is_some());
@@ -1,116 +1,167 @@
 // This module contains the data structures and functions used to implement Minimax and the
 // various features and optimizations that the engine supports.
 
-use std::{collections::HashMap, time::Instant};
+use std::{
+    collections::HashMap,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
 
-use crate::checkers::{Board, Movement, Player, Square, VALID_SQUARES};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::checkers::{
+    promotion_row, square_coords, Board, Movement, Player, Square, CENTER, ROWS, STARTING_PIECES,
+    VALID_SQUARES,
+};
+
+// The score of a position where the side to move has already lost. A loss `n` plies from the
+// root of the search scores `-(MATE_SCORE - n)`, so quicker wins score higher.
+pub const MATE_SCORE: i32 = 1_000_000_000;
+// Scores with a magnitude of at least [MATE_BAND] are reserved for won and lost positions. The
+// evaluation functions stay well below it, as 24 kings are worth less than 10,000 centipawns.
+pub const MATE_BAND: i32 = MATE_SCORE - 1_000;
+
+// Returns true if the score is a won or lost position.
+pub fn is_mate(score: i32) -> bool {
+    score.abs() >= MATE_BAND
+}
+
+// Returns the number of moves until the game is decided for a mate score: positive when the
+// player the score belongs to wins, negative when it loses.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if !is_mate(score) {
+        return None;
+    }
+    if score > 0 {
+        Some((MATE_SCORE - score + 1) / 2)
+    } else {
+        Some(-(MATE_SCORE + score) / 2)
+    }
+}
+
+// Mate scores count the plies from the root of the search, but a position may be reached at any
+// ply, so the Transposition Table stores them counted from the position itself. Convert a score
+// of a position `ply` plies from the root to the score stored in the table.
+fn score_to_table(score: i32, ply: u32) -> i32 {
+    match score {
+        score if score >= MATE_BAND => score + ply as i32,
+        score if score <= -MATE_BAND => score - ply as i32,
+        score => score,
+    }
+}
+
+// Convert a score read from the Transposition Table back to a score of a position `ply` plies
+// from the root, see [score_to_table].
+fn score_from_table(score: i32, ply: u32) -> i32 {
+    match score {
+        score if score >= MATE_BAND => score - ply as i32,
+        score if score <= -MATE_BAND => score + ply as i32,
+        score => score,
+    }
+}
+
+// The sign conventions of scores: every evaluation function returns the score of the board from
+// the perspective of the player it is given, positive when that player is ahead, and the search
+// negates the score of a reply to get the score for the player making the movement (negamax).
+// So a search for Player 2 returns a score that is positive when Player 2 is ahead. An absolute
+// score is always from Player 1's perspective: positive when Player 1 is ahead, whoever is to
+// move.
+
+// The absolute score of the board under `heuristic`, from Player 1's perspective.
+#[allow(dead_code)]
+pub fn evaluate_absolute(heuristic: fn(&Board, Player) -> i32, board: &Board) -> i32 {
+    heuristic(board, Player::Player1)
+}
+
+// Convert the score of a search for `player` to an absolute score, from Player 1's perspective.
+pub fn absolute_score(score: i32, player: Player) -> i32 {
+    score * player.sign()
+}
 
-const CENTER: [usize; 6] = [15, 16, 20, 21, 24, 25];
 const BACKP1: [usize; 4] = [5, 6, 7, 8];
 const BACKP2: [usize; 4] = [37, 38, 39, 40];
 
+// Material, with a king worth three pawns, in centipawns.
 pub fn evaluation1(board: &Board, player: Player) -> i32 {
     let mut pawn = 0;
     let mut king = 0;
-    for id in VALID_SQUARES {
-        if let Square::Taken(piece) = board.get(id) {
-            if piece.get_player() == player {
-                if piece.is_king() {
-                    king += 1;
-                } else {
-                    pawn += 1;
-                }
-            } else if piece.is_king() {
-                king -= 1;
-            } else {
-                pawn -= 1;
-            }
+    for piece in VALID_SQUARES
+        .into_iter()
+        .filter_map(|id| board.piece_at(id))
+    {
+        let count = if piece.get_player() == player { 1 } else { -1 };
+        if piece.is_king() {
+            king += count;
+        } else {
+            pawn += count;
         }
     }
-    pawn + (3 * king)
+    100 * (pawn + (3 * king))
 }
 
-pub fn evaluation2(board: &Board, player: Player) -> i32 {
-    let mut me = 0;
-    let mut you = 0;
-    let mut tempo = 0;
-    let mut defense = 0;
-    let mut pawns = 0;
-    let mut kings = 0;
-    let mut total = 0;
-    let mut kcent = 0;
-    let mut cramp = 0;
-    for id in VALID_SQUARES {
-        if let Square::Taken(piece) = board.get(id) {
-            total += 1;
-            if piece.get_player() == player {
-                me += 1;
-                if piece.is_king() {
-                    kings += 1;
-                    if CENTER.contains(&id) {
-                        kcent += 1;
-                    }
-                } else {
-                    pawns += 1;
-                    match player {
-                        Player::Player1 => {
-                            if BACKP1.contains(&id) {
-                                defense += 1;
-                            }
-                        }
-                        Player::Player2 => {
-                            if BACKP2.contains(&id) {
-                                defense += 1;
-                            }
-                        }
-                    };
-                    if player == Player::Player1 && id >= 28 {
-                        tempo += 1;
-                    }
-                    if player == Player::Player2 && id <= 17 {
-                        tempo += 1;
-                    }
-                }
-            } else if piece.is_king() {
-                you += 1;
-                kings -= 1;
-                if CENTER.contains(&id) {
-                    kcent -= 1;
-                }
-            } else {
-                you += 1;
-                pawns -= 1;
-                if player == Player::Player1 && id <= 17 {
-                    tempo -= 1;
-                }
-                if player == Player::Player2 && id >= 28 {
-                    tempo -= 1;
-                }
-                match player.other() {
-                    Player::Player1 => {
-                        if BACKP1.contains(&id) {
-                            defense -= 1;
-                        }
-                    }
-                    Player::Player2 => {
-                        if BACKP2.contains(&id) {
-                            defense -= 1;
-                        }
-                    }
-                };
-            }
+// The weights of the defense term of [evaluation2]. Pawns on their own back row guard it against
+// the opponent's promotions and earn `guard` each early in the game, but once the phase of the
+// game (see [Board::phase]) reaches `late_phase` they cost `late_penalty` each, since holding the
+// back row into the ending wastes the tempo needed to crown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackRankWeights {
+    pub guard: i32,
+    pub late_penalty: i32,
+    pub late_phase: f32,
+}
+
+impl BackRankWeights {
+    // The weight of a back-row pawn at `phase`.
+    pub fn weight(&self, phase: f32) -> i32 {
+        if phase >= self.late_phase {
+            -self.late_penalty
+        } else {
+            self.guard
         }
     }
+}
+
+pub const EVAL2_BACK_RANK: BackRankWeights = BackRankWeights {
+    guard: 15,
+    late_penalty: 10,
+    // 12 or fewer pieces left
+    late_phase: 0.5,
+};
+
+// The defense term of [evaluation2] with the back row weights `back_rank`, from the perspective
+// of `player`.
+fn defense(board: &Board, player: Player, back_rank: &BackRankWeights) -> i32 {
+    let guards = board.features(player).back_pawns - board.features(player.other()).back_pawns;
+    back_rank.weight(board.phase()) * guards
+}
+
+// The per-piece terms are read from the piece features the board keeps up to date (see
+// [PieceFeatures]), so only the cramp, which depends on pairs of squares, is scanned.
+pub fn evaluation2(board: &Board, player: Player) -> i32 {
+    evaluation2_with(board, player, &EVAL2_BACK_RANK)
+}
+
+// [evaluation2] with the back row weights `back_rank` in place of [EVAL2_BACK_RANK].
+pub fn evaluation2_with(board: &Board, player: Player, back_rank: &BackRankWeights) -> i32 {
+    let mine = board.features(player);
+    let theirs = board.features(player.other());
+    let me = mine.pawns + mine.kings;
+    let you = theirs.pawns + theirs.kings;
+    let pawns = mine.pawns - theirs.pawns;
+    let kings = mine.kings - theirs.kings;
+    let kcent = mine.center_kings - theirs.center_kings;
+    let tempo = mine.advanced_pawns - theirs.advanced_pawns;
+    let mut cramp = 0;
 
     if let Square::Taken(piece1) = board.get(23) {
         if piece1.get_player() == Player::Player1 {
             if let Square::Taken(piece2) = board.get(28) {
                 if piece1.get_player() != piece2.get_player() {
-                    if player == Player::Player1 {
-                        cramp += 1;
-                    } else {
-                        cramp -= 1;
-                    }
+                    cramp += player.sign();
                 }
             }
         }
@@ -120,34 +171,47 @@ pub fn evaluation2(board: &Board, player: Player) -> i32 {
         if piece1.get_player() == Player::Player2 {
             if let Square::Taken(piece2) = board.get(17) {
                 if piece1.get_player() != piece2.get_player() {
-                    if player == Player::Player1 {
-                        cramp -= 1;
-                    } else {
-                        cramp += 1;
-                    }
+                    cramp -= player.sign();
                 }
             }
         }
     }
 
-    let d = if total <= 12 { -10 } else { 15 };
-    let t = if total <= 16 { 10 } else { 40 };
+    // 16 or fewer pieces left
+    let t = if board.phase() >= 1.0 / 3.0 { 10 } else { 40 };
 
-    // dbg!(pawns, kings, defense, tempo, me, you, kcent, cramp);
+    // dbg!(pawns, kings, tempo, me, you, kcent, cramp);
 
-    (105 * pawns)
+    // the weights value a pawn at 105, so the sum is converted to centipawns
+    let score = (105 * pawns)
         + (125 * kings)
-        + (d * defense)
+        + defense(board, player, back_rank)
         + (t * tempo)
         + ((250 * (me - you)) / (me + you))
         + (me - you)
         + (30 * kcent)
-        + (10 * cramp)
+        + (10 * cramp);
+    score * 100 / 105
 }
 
-// Advancement
-const ADV_5_6: [usize; 8] = [23, 24, 25, 26, 28, 29, 30, 31];
-const ADV_3_4: [usize; 8] = [14, 15, 16, 17, 19, 20, 21, 22];
+// Advancement: each piece counts 1 for its side on the 5th and 6th rows from its own back row,
+// and -1 on the 3rd and 4th rows. Returns the difference between the sides from the perspective
+// of `player`.
+fn advancement(board: &Board, player: Player) -> i32 {
+    let mut adv = 0;
+    for id in VALID_SQUARES {
+        if let Some(piece) = board.piece_at(id) {
+            let owner = piece.get_player();
+            let value = match owner.relative_row(square_coords(id).0) {
+                2 | 3 => -1,
+                4 | 5 => 1,
+                _ => 0,
+            };
+            adv += value * owner.sign() * player.sign();
+        }
+    }
+    adv
+}
 
 // Move
 const MOVE_SYSTEM: [usize; 16] = [5, 6, 7, 8, 14, 15, 16, 17, 23, 24, 25, 26, 32, 33, 34, 35];
@@ -159,7 +223,6 @@ pub fn evaluation3(board: &Board, player: Player) -> i32 {
     let mut center = 0;
     let mut king_center = 0;
     let mut mov = 0;
-    let mut adv = 0;
     let mut back = 0;
     let mut thret = 0;
     let mut me_kings = 0;
@@ -167,6 +230,9 @@ pub fn evaluation3(board: &Board, player: Player) -> i32 {
     let mut you_kings = 0;
     let mut you_pawns = 0;
 
+    // the squares where a piece of `player` could be jumped on the next move
+    let threats = board.threat_map(player.other());
+
     for id in VALID_SQUARES {
         if let Square::Taken(piece) = board.get(id) {
             // basic piece counts
@@ -181,51 +247,28 @@ pub fn evaluation3(board: &Board, player: Player) -> i32 {
             } else {
                 you_pawns += 1;
             }
-            // total mobility (can the piece move somewhere?)
+            if piece.get_player() != player {
+                continue;
+            }
             for m in piece.movements() {
                 let id_to = (id as i32 + m) as usize;
                 if let Square::Empty = board.get(id_to) {
-                    if piece.get_player() == player {
-                        mob += 1;
-                    }
+                    // total mobility (can the piece move somewhere?)
+                    mob += 1;
 
                     // denial of occupancy (will this movement allow capture for other player?)
-                    for s in &[-5, -4, 4, 5] {
-                        let id_surround = (id_to as i32 + s) as usize;
-                        if let Square::Taken(surround_piece) = board.get(id_surround) {
-                            if surround_piece.get_player() != player {
-                                // where opponent will land on their jump
-                                let id_jump_land = (id_to as i32 - s) as usize;
-                                if let Square::Empty = board.get(id_jump_land) {
-                                    // do i have any pieces that can jump back?
-                                    // NAIVE: TODO: FIX
-                                    for j in &[-5, -4, 4, 5] {
-                                        let id_defend = (id_jump_land as i32 + j) as usize;
-                                        if let Square::Taken(defender) = board.get(id_defend) {
-                                            if defender.get_player() == player {
-                                                deny += 1;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                    if threats[id_to] {
+                        deny += 1;
                     }
 
                     // threat (does this movement threaten a capture?)
-                    for j in piece.movements() {
-                        let id_jump = (id_to as i32 + j) as usize;
-                        if let Square::Taken(jumped_piece) = board.get(id_jump) {
-                            if jumped_piece.get_player() != piece.get_player() {
-                                let id_land = (id_jump as i32 + j) as usize;
-                                if let Square::Empty = board.get(id_land) {
-                                    if piece.get_player() == player {
-                                        thret += 1;
-                                    }
-                                }
-                            }
-                        }
-                    }
+                    thret += board
+                        .attacks_from(piece, id_to)
+                        .filter(|&attacked| match board.get(attacked) {
+                            Square::Taken(other) => other.get_player() != player,
+                            _ => false,
+                        })
+                        .count() as i32;
                 }
             }
         }
@@ -248,36 +291,7 @@ pub fn evaluation3(board: &Board, player: Player) -> i32 {
     }
 
     // Advancement
-    for id in ADV_3_4 {
-        if let Square::Taken(piece) = board.get(id) {
-            if piece.get_player() == Player::Player2 {
-                if player == Player::Player2 {
-                    adv += 1;
-                } else {
-                    adv -= 1;
-                }
-            } else if player == Player::Player1 {
-                adv -= 1;
-            } else {
-                adv += 1;
-            }
-        }
-    }
-    for id in ADV_5_6 {
-        if let Square::Taken(piece) = board.get(id) {
-            if piece.get_player() == Player::Player1 {
-                if player == Player::Player1 {
-                    adv += 1;
-                } else {
-                    adv -= 1;
-                }
-            } else if player == Player::Player2 {
-                adv -= 1;
-            } else {
-                adv += 1;
-            }
-        }
-    }
+    let adv = advancement(board, player);
 
     // Back Row Bridge
     if me_kings == 0 {
@@ -330,7 +344,9 @@ pub fn evaluation3(board: &Board, player: Player) -> i32 {
 
     let b: i32 = 2;
 
-    (-moc_2 * b.pow(18))
+    // Samuel's weights value a pawn at 2 material units of 2^20, so the sum is converted to
+    // centipawns. The terms worth less than a centipawn then only count together.
+    let score = (-moc_2 * b.pow(18))
         + (king_center * b.pow(16))
         + (-moc_4 * b.pow(14))
         + (-mode_3 * b.pow(13))
@@ -342,7 +358,276 @@ pub fn evaluation3(board: &Board, player: Player) -> i32 {
         + (center * b.pow(5))
         + (thret * b.pow(5))
         + (moc_3 * b.pow(4))
-        + ((me - you) * b.pow(20))
+        + ((me - you) * b.pow(20));
+    (score as i64 * 100 / (2 << 20)) as i32
+}
+
+// An endgame evaluation: material, with kings worth more than pawns, a bonus for runaway pawns,
+// and a bonus for the side that is ahead in material when its kings close in on the opponent's
+// pieces.
+pub fn evaluation_endgame(board: &Board, player: Player) -> i32 {
+    let mut material = 0;
+    for id in VALID_SQUARES {
+        if let Square::Taken(piece) = board.get(id) {
+            let value = if piece.is_king() { 150 } else { 100 };
+            if piece.get_player() == player {
+                material += value;
+            } else {
+                material -= value;
+            }
+        }
+    }
+    let approach = if material > 0 {
+        -king_distance(board, player)
+    } else if material < 0 {
+        king_distance(board, player.other())
+    } else {
+        0
+    };
+    material + (RUNAWAY_BONUS * runaway_pawns(board, player)) + (5 * approach)
+}
+
+// The bonus given to a runaway pawn, bringing it close to the value of a king.
+pub const RUNAWAY_BONUS: i32 = 40;
+
+// The number of runaway pawns of `player` minus those of the opponent. A pawn is a runaway when
+// no opposing piece can intercept it before it is crowned. This is a simplified version of the
+// "square of the pawn" rule: an opposing king intercepts when it is no more than a row behind
+// the pawn and within the pawn's distance to the king row (plus a move) of its column, and an
+// opposing pawn intercepts when it is ahead of the pawn within the cone the pawn can reach.
+pub fn runaway_pawns(board: &Board, player: Player) -> i32 {
+    let mut total = 0;
+    for id in VALID_SQUARES {
+        if let Square::Taken(piece) = board.get(id) {
+            if !piece.is_king() && is_runaway(board, id, piece.get_player()) {
+                total += if piece.get_player() == player { 1 } else { -1 };
+            }
+        }
+    }
+    total
+}
+
+// Checks if the pawn of `player` on square `id` can not be intercepted by the opponent.
+fn is_runaway(board: &Board, id: usize, player: Player) -> bool {
+    let (row, col) = square_coords(id);
+    let forward = player.forward_direction();
+    let king_row = promotion_row(player, ROWS);
+    let distance = (king_row - row).abs();
+    VALID_SQUARES.iter().all(|other| match board.get(*other) {
+        Square::Taken(piece) if piece.get_player() != player => {
+            let (o_row, o_col) = square_coords(*other);
+            let ahead = (o_row - row) * forward;
+            let across = (o_col - col).abs();
+            if piece.is_king() {
+                ahead < -1 || across > distance + 1
+            } else {
+                ahead <= 0 || across > ahead + 1
+            }
+        }
+        _ => true,
+    })
+}
+
+// The sum over the kings of `player` of the distance to the closest opposing piece.
+fn king_distance(board: &Board, player: Player) -> i32 {
+    let mut total = 0;
+    for id in VALID_SQUARES {
+        if board
+            .piece_at(id)
+            .is_some_and(|piece| piece.get_player() == player && piece.is_king())
+        {
+            let (row, col) = square_coords(id);
+            let closest = VALID_SQUARES
+                .into_iter()
+                .filter(|other| {
+                    board
+                        .piece_at(*other)
+                        .is_some_and(|p| p.get_player() != player)
+                })
+                .map(|other| {
+                    let (o_row, o_col) = square_coords(other);
+                    (o_row - row).abs().max((o_col - col).abs())
+                })
+                .min();
+            total += closest.unwrap_or(0);
+        }
+    }
+    total
+}
+
+const PHASE_MAX: i32 = 256;
+
+// Define an evaluation that blends a midgame and an endgame evaluation by the phase of the game
+// (see [Board::phase]). Up to `opening_phase` only the midgame evaluation counts, from
+// `endgame_phase` on only the endgame evaluation counts, and in between the two are interpolated
+// linearly.
+pub struct PhasedEval {
+    pub midgame: fn(&Board, Player) -> i32,
+    pub endgame: fn(&Board, Player) -> i32,
+    pub opening_phase: f32,
+    pub endgame_phase: f32,
+}
+
+impl PhasedEval {
+    // The weight of the midgame evaluation, from 0 (endgame) to [PHASE_MAX] (opening).
+    pub fn phase(&self, board: &Board) -> i32 {
+        let progress =
+            (board.phase() - self.opening_phase) / (self.endgame_phase - self.opening_phase);
+        ((1.0 - progress.clamp(0.0, 1.0)) * PHASE_MAX as f32).round() as i32
+    }
+
+    pub fn evaluate(&self, board: &Board, player: Player) -> i32 {
+        let phase = self.phase(board);
+        let mut score = 0;
+        if phase > 0 {
+            score += (self.midgame)(board, player) * phase;
+        }
+        if phase < PHASE_MAX {
+            score += (self.endgame)(board, player) * (PHASE_MAX - phase);
+        }
+        score / PHASE_MAX
+    }
+}
+
+pub const PHASED_EVAL: PhasedEval = PhasedEval {
+    midgame: evaluation2,
+    endgame: evaluation_endgame,
+    // 20 pieces left
+    opening_phase: 4.0 / 24.0,
+    // 8 pieces left
+    endgame_phase: 16.0 / 24.0,
+};
+
+// Blends [evaluation2] and [evaluation_endgame] by the phase of the game.
+pub fn evaluation4(board: &Board, player: Player) -> i32 {
+    PHASED_EVAL.evaluate(board, player)
+}
+
+// The number of pawns of `player` stuck in the dog hole minus those of the opponent. A pawn is
+// in the dog hole on the edge square next to the opponent's double corner (28 for Player 1, 5
+// for Player 2), where its only forward square is taken by an opposing piece in the corner.
+pub fn dog_holes(board: &Board, player: Player) -> i32 {
+    let mut total = 0;
+    for id in VALID_SQUARES {
+        if let Square::Taken(piece) = board.get(id) {
+            if !piece.is_king() && in_dog_hole(board, id, piece.get_player()) {
+                total += if piece.get_player() == player { 1 } else { -1 };
+            }
+        }
+    }
+    total
+}
+
+// Checks if the pawn of `player` on square `id` is in the dog hole.
+fn in_dog_hole(board: &Board, id: usize, player: Player) -> bool {
+    let (row, col) = square_coords(id);
+    let forward = player.forward_direction();
+    let hole_row = promotion_row(player, ROWS) - forward;
+    if row != hole_row || (col != 0 && col != 7) {
+        return false;
+    }
+    let ahead = (row + forward, if col == 0 { 1 } else { 6 });
+    VALID_SQUARES
+        .iter()
+        .find(|other| square_coords(**other) == ahead)
+        .is_some_and(|other| {
+            matches!(board.get(*other), Square::Taken(piece) if piece.get_player() != player)
+        })
+}
+
+// The number of pieces of `player` guarding its own double corner (squares 1 and 5 for Player 1,
+// 28 and 32 for Player 2) minus those of the opponent guarding theirs. Pieces there are hard to
+// attack, and it is where a defending king retreats to in the endgame.
+pub fn double_corners(board: &Board, player: Player) -> i32 {
+    let mut total = 0;
+    for id in VALID_SQUARES {
+        if let Square::Taken(piece) = board.get(id) {
+            let corner = match piece.get_player() {
+                Player::Player1 => [(0, 1), (1, 0)],
+                Player::Player2 => [(7, 6), (6, 7)],
+            };
+            if corner.contains(&square_coords(id)) {
+                total += if piece.get_player() == player { 1 } else { -1 };
+            }
+        }
+    }
+    total
+}
+
+// The number of pieces of `player` minus those of the opponent, kings counting as pawns.
+pub fn material_balance(board: &Board, player: Player) -> i32 {
+    let (p1, p2) = board.piece_count();
+    (p1 as i32 - p2 as i32) * player.sign()
+}
+
+// The desirability of trading pieces for `player`: the number of pieces traded off since the
+// start of the game, counted for the player when it is ahead in material and against it when it
+// is behind, so that the side ahead simplifies and the side behind avoids trades.
+pub fn trades(board: &Board, player: Player) -> i32 {
+    let (p1, p2) = board.piece_count();
+    let traded = (STARTING_PIECES - (p1 + p2) as i32).max(0);
+    material_balance(board, player).signum() * traded
+}
+
+// The number of features scored by a [WeightedEval].
+pub const WEIGHTED_FEATURES: usize = 8;
+
+// Define an evaluation as a weighted sum of features, each counted for the player minus the
+// opponent: pawns, kings, runaway pawns, pieces on the player's own back row, pieces in the
+// center, pawns in the dog hole, pieces in the player's own double corner and [trades]. The
+// weights are in centipawns and can be fitted with the `tuning` feature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedEval {
+    pub weights: [i32; WEIGHTED_FEATURES],
+}
+
+impl WeightedEval {
+    pub fn features(board: &Board, player: Player) -> [i32; WEIGHTED_FEATURES] {
+        let mut features = [0; WEIGHTED_FEATURES];
+        for id in VALID_SQUARES {
+            if let Square::Taken(piece) = board.get(id) {
+                let sign = if piece.get_player() == player { 1 } else { -1 };
+                if piece.is_king() {
+                    features[1] += sign;
+                } else {
+                    features[0] += sign;
+                }
+                let back = match piece.get_player() {
+                    Player::Player1 => BACKP1,
+                    Player::Player2 => BACKP2,
+                };
+                if back.contains(&id) {
+                    features[3] += sign;
+                }
+                if CENTER.contains(&id) {
+                    features[4] += sign;
+                }
+            }
+        }
+        features[2] = runaway_pawns(board, player);
+        features[5] = dog_holes(board, player);
+        features[6] = double_corners(board, player);
+        features[7] = trades(board, player);
+        features
+    }
+
+    pub fn evaluate(&self, board: &Board, player: Player) -> i32 {
+        Self::features(board, player)
+            .iter()
+            .zip(self.weights)
+            .map(|(feature, weight)| feature * weight)
+            .sum()
+    }
+}
+
+pub const WEIGHTED_EVAL: WeightedEval = WeightedEval {
+    weights: [100, 150, 40, 10, 5, -20, 10, 2],
+};
+
+// Scores the position with the weights of [WEIGHTED_EVAL]. Other weights are searched with the
+// `evaluator` of the [MinimaxContext].
+pub fn evaluation5(board: &Board, player: Player) -> i32 {
+    WEIGHTED_EVAL.evaluate(board, player)
 }
 
 // Define the data structure used to collect stats about the performance of the Minimax algorithm.
@@ -350,9 +635,27 @@ pub struct Stats {
     pub moves: u32,
     pub explored: u32,
     pub beta_cuts: u32,
+    pub tt_probes: u32,
     pub tt_exact: u32,
     pub tt_cuts: u32,
+    // Transposition Table entries dropped for being from an earlier generation.
+    pub tt_aged: u32,
     pub max_depth: u32,
+    pub ponder_hit: u32,
+    pub ponder_miss: u32,
+    pub eval_hits: u32,
+    pub eval_misses: u32,
+    pub promotions: u32,
+    pub profile: Profile,
+    // The value of `explored` at which the running search stops, from the node limit of the
+    // [MinimaxContext], and whether it has stopped there.
+    node_stop: Option<u32>,
+    stopped: bool,
+    // Set from another thread to stop the running search, e.g. a ponder search once the
+    // opponent has moved.
+    stop_signal: Option<Arc<AtomicBool>>,
+    // The time at which the running search stops, e.g. from the `movetime` of the protocol.
+    deadline: Option<Instant>,
 }
 
 impl Stats {
@@ -361,11 +664,164 @@ impl Stats {
             moves: 0,
             explored: 0,
             beta_cuts: 0,
+            tt_probes: 0,
             tt_exact: 0,
             tt_cuts: 0,
+            tt_aged: 0,
+            max_depth: 0,
+            ponder_hit: 0,
+            ponder_miss: 0,
+            eval_hits: 0,
+            eval_misses: 0,
+            promotions: 0,
+            profile: Profile::default(),
+            node_stop: None,
+            stopped: false,
+            stop_signal: None,
+            deadline: None,
+        }
+    }
+
+    // Stop the searches run with these stats once `deadline` has passed (never when None).
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    // If the last search was cut short by its node limit, its deadline or a stop signal.
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    // If the running search has to stop: it has reached its node limit or its deadline, or it has
+    // been told to stop from another thread. The clock is only read every [DEADLINE_NODES] nodes.
+    fn must_stop(&self) -> bool {
+        self.node_stop.is_some_and(|stop| self.explored >= stop)
+            || self
+                .stop_signal
+                .as_ref()
+                .is_some_and(|signal| signal.load(Ordering::Relaxed))
+            || self.deadline.is_some_and(|deadline| {
+                self.explored.is_multiple_of(DEADLINE_NODES) && Instant::now() >= deadline
+            })
+    }
+}
+
+// Define the time spent in each part of the search, collected when profiling is enabled in the
+// [MinimaxContext].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Profile {
+    // Generating movements.
+    pub movegen: Duration,
+    // Evaluating leaves, including the evaluation cache.
+    pub eval: Duration,
+    // Probing and inserting into the Transposition Table.
+    pub table: Duration,
+    // Applying and reverting movements.
+    pub do_undo: Duration,
+}
+
+impl Profile {
+    // Start timing a part of the search, if profiling is enabled.
+    fn start(ctx: &MinimaxContext) -> Option<Instant> {
+        ctx.profile.then(Instant::now)
+    }
+
+    // Add the time since `start` to `total`.
+    fn stop(start: Option<Instant>, total: &mut Duration) {
+        if let Some(start) = start {
+            *total += start.elapsed();
+        }
+    }
+
+    // Describe the timings on one line as `profile movegen <us> eval <us> table <us> do_undo <us>`.
+    pub fn info(&self) -> String {
+        format!(
+            "profile movegen {} eval {} table {} do_undo {}",
+            self.movegen.as_micros(),
+            self.eval.as_micros(),
+            self.table.as_micros(),
+            self.do_undo.as_micros()
+        )
+    }
+}
+
+// Define the data structure used to aggregate [Stats] across all the games of a run.
+pub struct StatsAccumulator {
+    pub games: u32,
+    pub moves: u64,
+    pub explored: u64,
+    pub max_depth: u64,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+}
+
+impl StatsAccumulator {
+    pub fn new() -> Self {
+        Self {
+            games: 0,
+            moves: 0,
+            explored: 0,
             max_depth: 0,
+            tt_probes: 0,
+            tt_hits: 0,
         }
     }
+
+    // Fold the [Stats] of a single game into the totals.
+    pub fn add(&mut self, stats: &Stats) {
+        self.games += 1;
+        self.moves += stats.moves as u64;
+        self.explored += stats.explored as u64;
+        self.max_depth += stats.max_depth as u64;
+        self.tt_probes += stats.tt_probes as u64;
+        self.tt_hits += (stats.tt_exact + stats.tt_cuts) as u64;
+    }
+
+    pub fn nodes_per_move(&self) -> f64 {
+        ratio(self.explored, self.moves)
+    }
+
+    // The average of the deepest ply reached in each game.
+    pub fn average_depth(&self) -> f64 {
+        ratio(self.max_depth, self.games as u64)
+    }
+
+    // The share of Transposition Table probes that ended the search of a node.
+    pub fn tt_hit_rate(&self) -> f64 {
+        ratio(self.tt_hits, self.tt_probes)
+    }
+
+    pub fn display(&self, out: &mut dyn Write, player: &str) -> std::io::Result<()> {
+        writeln!(out, "summary.{}.games = {}", player, self.games)?;
+        writeln!(out, "summary.{}.moves = {}", player, self.moves)?;
+        writeln!(out, "summary.{}.explored = {}", player, self.explored)?;
+        writeln!(
+            out,
+            "summary.{}.nodes_per_move = {:.2}",
+            player,
+            self.nodes_per_move()
+        )?;
+        writeln!(
+            out,
+            "summary.{}.average_depth = {:.2}",
+            player,
+            self.average_depth()
+        )?;
+        writeln!(
+            out,
+            "summary.{}.tt_hit_rate = {:.4}",
+            player,
+            self.tt_hit_rate()
+        )?;
+        Ok(())
+    }
+}
+
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 {
+        return 0.0;
+    }
+    numerator as f64 / denominator as f64
 }
 
 // Define the flag states used in a [TTEntry].
@@ -387,81 +843,491 @@ pub struct TTEntry {
     flag: Flag,
 }
 
-struct MinimaxResult {
-    score: i32,
-    movement: Option<Movement>,
-}
+// The number of entries a [TranspositionTable] holds by default.
+pub const TT_CAPACITY: usize = 100_000;
 
-#[derive(Clone, Copy)]
-pub struct MinimaxContext {
-    pub table: bool,
-    pub depth: u32,
-    pub alpha_beta: bool,
-    pub quiescence: bool,
-    pub iterative: bool,
-    pub verbose: bool,
-    pub heuristic: fn(&Board, Player) -> i32,
+// Define the Transposition Table: the searched scores of positions, keyed on
+// [Board::hash_with_turn]. A table can be kept for a whole game, so every search from the root
+// starts a new generation. Entries of earlier generations belong to positions the game has most
+// likely moved past, so once the table is full they are dropped to make room for the current
+// search. If only current entries are left, new ones are not stored.
+pub struct TranspositionTable {
+    // The entries with the generation they were stored in.
+    entries: HashMap<u128, (u32, TTEntry)>,
+    capacity: usize,
+    generation: u32,
+    // The number of entries of the current generation, so that the table is only swept for
+    // earlier entries when there are some.
+    current: usize,
 }
 
-#[allow(clippy::too_many_arguments)]
-fn minimax(
-    stats: &mut Stats,
-    ctx: &MinimaxContext,
-    mut max_depth: u32,
-    board: &mut Board,
-    player: Player,
-    table: &mut HashMap<u128, TTEntry>,
-    mut depth: u32,
-    mut alpha: i32,
+impl TranspositionTable {
+    pub fn new() -> Self {
+        Self::with_capacity(TT_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::with_capacity(capacity),
+            capacity,
+            generation: 0,
+            current: 0,
+        }
+    }
+
+    // Start the search of a new position from the root.
+    pub fn new_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.current = 0;
+    }
+
+    pub fn get(&self, hash: u128) -> Option<&TTEntry> {
+        self.entries.get(&hash).map(|(_, entry)| entry)
+    }
+
+    // Store `entry` for the position with hash `hash` in the current generation, replacing any
+    // entry of the same position. The entries dropped for being out of date are counted in the
+    // `tt_aged` of `stats`.
+    fn store(&mut self, stats: &mut Stats, hash: u128, entry: TTEntry) {
+        let generation = self.generation;
+        match self.entries.get(&hash) {
+            Some((g, _)) if *g == generation => {}
+            Some(_) => self.current += 1,
+            None => {
+                if self.entries.len() >= self.capacity {
+                    if self.current == self.entries.len() {
+                        return;
+                    }
+                    let before = self.entries.len();
+                    self.entries.retain(|_, (g, _)| *g == generation);
+                    stats.tt_aged += (before - self.entries.len()) as u32;
+                    if self.entries.len() >= self.capacity {
+                        return;
+                    }
+                }
+                self.current += 1;
+            }
+        }
+        self.entries.insert(hash, (generation, entry));
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+// A [TranspositionTable] shared between threads: an engine ponders on a worker thread, filling
+// the table, and its own search reads what the worker stored once the opponent has moved. Clones
+// share the same table.
+#[derive(Clone)]
+pub struct SharedTable(Arc<Mutex<TranspositionTable>>);
+
+impl SharedTable {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(TranspositionTable::new())))
+    }
+
+    // Take the table for the running thread, waiting for another thread to be done with it. A
+    // table left behind by a thread that panicked is still usable, as every entry is stored whole.
+    pub fn lock(&self) -> MutexGuard<'_, TranspositionTable> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+const EVAL_CACHE_CAPACITY: usize = 100_000;
+
+// Define a cache of static evaluation scores for the leaves of a single search. Unlike the
+// Transposition Table, which stores searched scores, this only saves calls to the heuristic.
+// Once the cache is full no new scores are added.
+pub struct EvalCache {
+    enabled: bool,
+    scores: HashMap<(u128, Player), i32>,
+}
+
+impl EvalCache {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            scores: HashMap::new(),
+        }
+    }
+
+    // Return the heuristic score of the board for `player`, computing it only if needed. Only
+    // quiet positions are cached.
+    pub fn evaluate(
+        &mut self,
+        stats: &mut Stats,
+        ctx: &MinimaxContext,
+        board: &Board,
+        player: Player,
+    ) -> i32 {
+        if !self.enabled || !board.is_quiet() {
+            return ctx.evaluate(board, player);
+        }
+        let key = (board.hash(), player);
+        if let Some(score) = self.scores.get(&key) {
+            stats.eval_hits += 1;
+            return *score;
+        }
+        stats.eval_misses += 1;
+        let score = ctx.evaluate(board, player);
+        if self.scores.len() < EVAL_CACHE_CAPACITY {
+            self.scores.insert(key, score);
+        }
+        score
+    }
+}
+
+struct MinimaxResult {
+    score: i32,
+    movement: Option<Movement>,
+}
+
+// How much the engine reports about its searches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    // Nothing.
+    Silent,
+    // The score of the chosen movement.
+    Summary,
+    // An `info` line for every completed iteration, then the summary.
+    Iterations,
+    // Every movement searched with its score, indented by ply, then the iterations.
+    Trace,
+}
+
+impl Verbosity {
+    // The verbosity for a count of `-v` flags.
+    pub fn from_count(count: u8) -> Self {
+        match count {
+            0 => Self::Silent,
+            1 => Self::Summary,
+            2 => Self::Iterations,
+            _ => Self::Trace,
+        }
+    }
+}
+
+impl std::fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Silent => write!(f, "silent"),
+            Self::Summary => write!(f, "summary"),
+            Self::Iterations => write!(f, "iterations"),
+            Self::Trace => write!(f, "trace"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MinimaxContext {
+    pub table: bool,
+    // In simple positions the transposition table costs more in hashing and insertions than it
+    // saves. When non-zero, the table is only used by searches of positions where both sides
+    // have at least this many movements between them.
+    pub table_threshold: usize,
+    pub depth: u32,
+    // When set, a search stops after exploring this many nodes and plays the best movement of
+    // the deepest search completed, so that its strength does not depend on the speed of the
+    // machine. It replaces the time limit of iterative deepening. A search stopped before a
+    // single movement is searched in full falls back to a search one ply deep.
+    pub node_limit: Option<u64>,
+    pub alpha_beta: bool,
+    pub quiescence: bool,
+    // Nudge the score of a leaf where the side to move has a winning capture pending, so that a
+    // search without quiescence does not stop right before a shot. See [capture_nudge].
+    pub capture_nudge: bool,
+    pub iterative: bool,
+    pub ponder: bool,
+    pub eval_cache: bool,
+    // How a search returns scores outside of its alpha-beta window. Fail-soft returns the best
+    // score found, which may lie outside the window and then bounds the true score more tightly.
+    // Fail-hard clamps the score to the window, so a fail-low returns alpha and a fail-high
+    // returns beta. Either way the transposition table stores the returned score with a flag
+    // saying if it is exact, a lower bound or an upper bound.
+    pub fail_soft: bool,
+    // Search the movements that leave the fewest pieces hanging first, so that blunders are
+    // searched last.
+    pub move_ordering: bool,
+    // A custom ordering of the movements, called at every node before the built-in ordering
+    // (which keeps the custom order between movements it ranks the same).
+    pub order_moves: Option<fn(&Board, Player, &mut [Movement])>,
+    pub tie_break: TieBreak,
+    // Time the parts of the search into the [Profile] of the [Stats]. Off by default, as reading
+    // the clock slows the search down.
+    pub profile: bool,
+    // When positive, the engine plays a random movement among those scoring within this many
+    // centipawns of the best, drawn from a generator seeded with `seed`, so that self-play
+    // games from the same start differ. The reported score is still that of the best movement.
+    pub score_epsilon: i32,
+    pub seed: u64,
+    // Bias the choice of movement towards a style of play.
+    pub style: StyleBias,
+    // Report scores from Player 1's perspective instead of that of the searching player, see
+    // [evaluate_absolute]. Only the reported scores change, the search itself is the same.
+    pub absolute_scores: bool,
+    pub verbosity: Verbosity,
+    // Scores positions in centipawns (a pawn is worth 100), the unit that scores are reported in.
+    pub heuristic: fn(&Board, Player) -> i32,
+    // When set, leaves are scored by this evaluator instead of `heuristic`, e.g. with weights read
+    // from a configuration file, so that each player can search with weights of its own.
+    pub evaluator: Option<Evaluator>,
+}
+
+// A search of depth 6 with the v1 evaluation and every optional feature off, the same as the
+// command line without any options.
+impl Default for MinimaxContext {
+    fn default() -> Self {
+        MinimaxContext {
+            table: false,
+            table_threshold: 0,
+            depth: 6,
+            node_limit: None,
+            alpha_beta: false,
+            quiescence: false,
+            capture_nudge: false,
+            iterative: false,
+            ponder: false,
+            eval_cache: false,
+            fail_soft: true,
+            move_ordering: false,
+            order_moves: None,
+            tie_break: TieBreak::Ordered,
+            profile: false,
+            score_epsilon: 0,
+            seed: 0,
+            style: StyleBias::default(),
+            absolute_scores: false,
+            verbosity: Verbosity::Silent,
+            heuristic: evaluation1,
+            evaluator: None,
+        }
+    }
+}
+
+impl MinimaxContext {
+    // The score of the board for `player` under the evaluation of the context.
+    fn evaluate(&self, board: &Board, player: Player) -> i32 {
+        match &self.evaluator {
+            Some(evaluator) => evaluator.evaluate(board, player),
+            None => (self.heuristic)(board, player),
+        }
+    }
+}
+
+// An evaluation function with weights of its own, which a `heuristic` can not carry. Only a
+// configuration file sets one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "serde"), allow(dead_code))]
+pub enum Evaluator {
+    // [evaluation2] with these back row weights.
+    Evaluation2(BackRankWeights),
+    // [evaluation5] with these weights.
+    Weighted(WeightedEval),
+}
+
+impl Evaluator {
+    pub fn evaluate(&self, board: &Board, player: Player) -> i32 {
+        match self {
+            Self::Evaluation2(back_rank) => evaluation2_with(board, player, back_rank),
+            Self::Weighted(eval) => eval.evaluate(board, player),
+        }
+    }
+}
+
+// The most, in centipawns, that a [StyleBias] adds to the score of a movement, so that the bias
+// only decides between movements of about the same score and never makes the engine blunder.
+const MAX_STYLE_BONUS: i32 = 50;
+
+// A bias of the engine's choice of movement towards a style of play. The bonus is added to the
+// scores of the root movements when choosing between them, but not to the reported score.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StyleBias {
+    // The bonus in centipawns for each piece a movement captures and each opposing piece that can
+    // be jumped after it.
+    pub aggression: i32,
+}
+
+impl StyleBias {
+    // The largest bonus in centipawns.
+    fn max_bonus(&self) -> i32 {
+        if self.aggression <= 0 {
+            return 0;
+        }
+        MAX_STYLE_BONUS
+    }
+
+    // The bonus in centipawns for `movement` of `player`, which has just been played on `board`.
+    fn bonus(&self, board: &Board, movement: &Movement, player: Player) -> i32 {
+        let threats = board.threat_map(player);
+        let attacked = VALID_SQUARES
+            .into_iter()
+            .filter(|&id| {
+                threats[id] && board.piece_at(id).is_some_and(|p| p.get_player() != player)
+            })
+            .count();
+        self.aggression
+            .saturating_mul((movement.captures() + attacked) as i32)
+            .min(MAX_STYLE_BONUS)
+    }
+}
+
+// The centipawns that [capture_nudge] adds for each piece won.
+const CAPTURE_NUDGE: i32 = 50;
+
+// The bonus for `player`, to move at a leaf, when its pending captures win material against the
+// best recaptures (see [Board::forced_loss_after_capture]): a fraction of a pawn for each piece
+// won, as the leaf evaluation does not see the capture. Losing captures are left to the search.
+pub fn capture_nudge(board: &mut Board, player: Player) -> i32 {
+    match board.forced_loss_after_capture(player) {
+        Some(won) if won > 0 => won * CAPTURE_NUDGE,
+        _ => 0,
+    }
+}
+
+// How the engine chooses between root movements with the same score. Either way the choice does
+// not depend on the order the movements are generated or searched in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TieBreak {
+    // Prefer the movement capturing the most pieces, then the one from the lowest square, then
+    // the one to the lowest square (the order of [Movement]).
+    Ordered,
+    // Prefer a random movement, drawn from a generator seeded with the given seed.
+    Random(u64),
+}
+
+impl TieBreak {
+    // Sort `movements` from the most to the least preferred.
+    fn sort(&self, movements: &mut [Movement]) {
+        match self {
+            Self::Ordered => movements.sort(),
+            Self::Random(seed) => {
+                movements.sort_by_key(|m| (m.from().id, m.to().id));
+                movements.shuffle(&mut StdRng::seed_from_u64(*seed));
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn minimax(
+    stats: &mut Stats,
+    ctx: &MinimaxContext,
+    mut max_depth: u32,
+    board: &mut Board,
+    player: Player,
+    table: &mut TranspositionTable,
+    cache: &mut EvalCache,
+    out: &mut dyn Write,
+    mut depth: u32,
+    mut alpha: i32,
     mut beta: i32,
 ) -> MinimaxResult {
     let alpha_orig = alpha;
+    let beta_orig = beta;
     let mut best_move: Option<Movement> = None;
-    let movements = board.movements(player);
+    let start = Profile::start(ctx);
+    let mut movements = board.movements(player);
+    Profile::stop(start, &mut stats.profile.movegen);
 
     if depth == 0 && ctx.quiescence && !movements.is_empty() && movements[0].is_jump() {
         depth = 1;
     }
 
-    if depth == 0 || movements.is_empty() {
-        let result = MinimaxResult {
-            score: (ctx.heuristic)(board, player),
+    if movements.is_empty() {
+        return MinimaxResult {
+            score: -(MATE_SCORE - max_depth as i32),
+            movement: best_move,
+        };
+    }
+
+    if depth == 0 {
+        let start = Profile::start(ctx);
+        let mut score = cache.evaluate(stats, ctx, board, player);
+        if ctx.capture_nudge && movements[0].is_jump() {
+            score += capture_nudge(board, player);
+        }
+        Profile::stop(start, &mut stats.profile.eval);
+        return MinimaxResult {
+            score,
             movement: best_move,
         };
-        return result;
     }
 
+    // At the root, equal scores are told apart by the preference order of the movements
+    let root = max_depth == 0;
+    let preference = if root {
+        ctx.tie_break.sort(&mut movements);
+        movements.clone()
+    } else {
+        Vec::new()
+    };
+    let rank = |m: &Movement| preference.iter().position(|p| p == m);
+    // At the root, the movements scoring within `epsilon` of the best are candidates to play,
+    // after the style bias adds up to `max_bonus` to their scores
+    let (epsilon, max_bonus) = if root {
+        (ctx.score_epsilon.max(0), ctx.style.max_bonus())
+    } else {
+        (0, 0)
+    };
+    let window = epsilon + max_bonus;
+    let mut candidates: Vec<(Movement, i32)> = Vec::new();
+
+    let ply = max_depth;
     max_depth += 1;
     if stats.max_depth < max_depth {
         stats.max_depth = max_depth;
     }
 
+    // the best movement of an earlier, shallower search of the position
+    let mut table_move = None;
     if ctx.table {
-        if let Some(entry) = table.get(&board.hash()) {
-            if entry.depth >= depth {
+        stats.tt_probes += 1;
+        let start = Profile::start(ctx);
+        let entry = table.get(board.hash_with_turn(player));
+        Profile::stop(start, &mut stats.profile.table);
+        if let Some(entry) = entry {
+            table_move = Some(entry.movement.clone());
+            let entry_score = score_from_table(entry.score, ply);
+            // the root movement is chosen by the tie-break and among the candidates, which are
+            // only known after searching every root movement
+            if entry.depth >= depth && !root {
                 match entry.flag {
                     Flag::Exact => {
                         stats.tt_exact += 1;
+                        let score = if ctx.fail_soft {
+                            entry_score
+                        } else {
+                            entry_score.clamp(alpha_orig, beta_orig)
+                        };
                         return MinimaxResult {
-                            score: entry.score,
+                            score,
                             movement: Some(entry.movement.clone()),
                         };
                     }
                     Flag::Lowerbound => {
-                        if alpha < entry.score {
-                            alpha = entry.score;
+                        if alpha < entry_score {
+                            alpha = entry_score;
                         }
                     }
                     Flag::Upperbound => {
-                        if beta > entry.score {
-                            beta = entry.score;
+                        if beta > entry_score {
+                            beta = entry_score;
                         }
                     }
                 }
                 if alpha >= beta {
                     stats.tt_cuts += 1;
+                    let score = if ctx.fail_soft {
+                        entry_score
+                    } else {
+                        entry_score.clamp(alpha_orig, beta_orig)
+                    };
                     return MinimaxResult {
-                        score: entry.score,
+                        score,
                         movement: Some(entry.movement.clone()),
                     };
                 }
@@ -469,11 +1335,48 @@ fn minimax(
         }
     }
 
+    if let Some(order_moves) = ctx.order_moves {
+        order_moves(board, player, &mut movements);
+    }
+
+    if ctx.move_ordering {
+        movements.sort_by_cached_key(|m| board.pieces_hanging_after(m, player));
+    }
+
+    // The movement from the table is searched first, as it is likely to still be the best and
+    // then narrows the window for the others.
+    if let Some(i) = table_move.and_then(|t| movements.iter().position(|m| *m == t)) {
+        movements[..=i].rotate_right(1);
+    }
+
     let mut value = i32::MIN + 1;
 
     for m in movements {
+        if stats.must_stop() {
+            stats.stopped = true;
+        }
+        if stats.stopped {
+            break;
+        }
         stats.explored += 1;
-        board.do_movement(&m);
+        let start = Profile::start(ctx);
+        let undo = board.do_move(&m, player);
+        Profile::stop(start, &mut stats.profile.do_undo);
+        let bonus = if max_bonus > 0 {
+            ctx.style.bonus(board, &m, player)
+        } else {
+            0
+        };
+        // at the root the window is widened by one (and by the candidate window) so that a
+        // score equal to alpha (or close enough to it to become a candidate) is exact
+        let floor = if root {
+            alpha
+                .saturating_sub(1)
+                .saturating_sub(window)
+                .max(i32::MIN + 1)
+        } else {
+            alpha
+        };
         let score = -minimax(
             stats,
             ctx,
@@ -481,13 +1384,32 @@ fn minimax(
             board,
             player.other(),
             table,
+            cache,
+            out,
             depth - 1,
             -beta,
-            -alpha,
+            -floor,
         )
         .score;
-        board.undo_movement(&m);
-        if value < score {
+        let start = Profile::start(ctx);
+        board.undo(undo);
+        Profile::stop(start, &mut stats.profile.do_undo);
+        // the score of a movement whose search was cut short is not known
+        if stats.stopped {
+            break;
+        }
+        if ctx.verbosity == Verbosity::Trace {
+            let indent = 2 * (max_depth as usize - 1);
+            let _ = writeln!(out, "{:indent$}{} {}", "", m, score, indent = indent);
+        }
+        if window > 0 {
+            candidates.push((m.clone(), score.saturating_add(bonus)));
+        }
+        let preferred = root
+            && score == value
+            && value == alpha
+            && best_move.as_ref().is_none_or(|b| rank(&m) < rank(b));
+        if value < score || preferred {
             value = score;
             best_move = Some(m);
             if value >= beta && ctx.alpha_beta {
@@ -500,7 +1422,11 @@ fn minimax(
         }
     }
 
-    if ctx.table {
+    if !ctx.fail_soft {
+        value = value.clamp(alpha_orig, beta_orig);
+    }
+
+    if ctx.table && !stats.stopped {
         if let Some(m) = &best_move {
             let flag = if value <= alpha_orig {
                 Flag::Upperbound
@@ -509,15 +1435,38 @@ fn minimax(
             } else {
                 Flag::Exact
             };
-            table.insert(
-                board.hash(),
+            let start = Profile::start(ctx);
+            table.store(
+                stats,
+                board.hash_with_turn(player),
                 TTEntry {
                     movement: m.clone(),
-                    score: value,
+                    score: score_to_table(value, ply),
                     depth,
                     flag,
                 },
             );
+            Profile::stop(start, &mut stats.profile.table);
+        }
+    }
+
+    if window > 0 {
+        // the search order depends on the table, so the candidates are put in preference order
+        candidates.sort_by_key(|(m, _)| rank(m));
+        let best = candidates.iter().map(|(_, score)| *score).max();
+        let best = best.unwrap_or(value);
+        let candidates: Vec<Movement> = candidates
+            .into_iter()
+            .filter(|(_, score)| *score >= best.saturating_sub(epsilon))
+            .map(|(m, _)| m)
+            .collect();
+        let chosen = if epsilon > 0 {
+            candidates.choose(&mut StdRng::seed_from_u64(ctx.seed))
+        } else {
+            candidates.first()
+        };
+        if let Some(m) = chosen {
+            best_move = Some(m.clone());
         }
     }
 
@@ -527,78 +1476,1817 @@ fn minimax(
     }
 }
 
-const MAX_DEPTH: u32 = 20;
+pub const MAX_DEPTH: u32 = 20;
+
+// A callback receiving the depth, score and best movement of a completed search iteration.
+pub type OnIteration<'a> = dyn FnMut(u32, i32, &Movement) + 'a;
 const MAX_TIME_MS: u128 = 50;
 
+// The number of nodes explored between two checks of the deadline of a search.
+const DEADLINE_NODES: u32 = 256;
+
 // The main entry point for asking the Checkers engine to select a move for a given [Player]
-// within the context of a given [Board] state.
+// within the context of a given [Board] state. `on_iteration` is called with the depth, score
+// and best movement after each completed iteration (a single one at the configured depth when
+// not searching iteratively). Output is written to stdout according to the verbosity of the
+// [MinimaxContext].
+#[allow(dead_code)]
 pub fn get_movement(
     stats: &mut Stats,
     ctx: &MinimaxContext,
     board: &mut Board,
     player: Player,
-    table: &mut HashMap<u128, TTEntry>,
+    table: &mut TranspositionTable,
+    on_iteration: Option<&mut OnIteration>,
+) -> Option<Movement> {
+    get_movement_to(
+        stats,
+        ctx,
+        board,
+        player,
+        table,
+        on_iteration,
+        &mut std::io::stdout(),
+    )
+}
+
+// Search the position for `player` from the root to `depth` plies with a full window. Every
+// entry point searches through here, so they agree on the root player and on the sign of the
+// score: it is always from the perspective of `player`, whichever side the board was last moved
+// by.
+#[allow(clippy::too_many_arguments)]
+fn search_root(
+    stats: &mut Stats,
+    ctx: &MinimaxContext,
+    board: &mut Board,
+    player: Player,
+    table: &mut TranspositionTable,
+    cache: &mut EvalCache,
+    out: &mut dyn Write,
+    depth: u32,
+) -> MinimaxResult {
+    minimax(
+        stats,
+        ctx,
+        0,
+        board,
+        player,
+        table,
+        cache,
+        out,
+        depth,
+        i32::MIN + 1,
+        i32::MAX - 1,
+    )
+}
+
+// If the transposition table is worth using to search the position, going by the number of
+// movements of both sides against the `table_threshold` of the [MinimaxContext].
+fn table_pays_off(ctx: &MinimaxContext, board: &Board, player: Player) -> bool {
+    ctx.table_threshold == 0
+        || board.legal_moves(player).count() + board.legal_moves(player.other()).count()
+            >= ctx.table_threshold
+}
+
+// Like [get_movement], but writes the output to `out`.
+pub fn get_movement_to(
+    stats: &mut Stats,
+    ctx: &MinimaxContext,
+    board: &mut Board,
+    player: Player,
+    table: &mut TranspositionTable,
+    on_iteration: Option<&mut OnIteration>,
+    out: &mut dyn Write,
 ) -> Option<Movement> {
+    search_to(stats, ctx, board, player, table, on_iteration, out).and_then(|r| r.movement)
+}
+
+// Like [get_movement_to], but returns the [SearchResult] of the deepest search completed, with
+// the score reported for the chosen movement. Returns None when `player` has no movements.
+pub fn search_to(
+    stats: &mut Stats,
+    ctx: &MinimaxContext,
+    board: &mut Board,
+    player: Player,
+    table: &mut TranspositionTable,
+    mut on_iteration: Option<&mut OnIteration>,
+    out: &mut dyn Write,
+) -> Option<SearchResult> {
     let movements = board.movements(player);
 
     if movements.is_empty() {
         return None;
     }
 
+    let ctx = &MinimaxContext {
+        table: ctx.table && table_pays_off(ctx, board, player),
+        ..*ctx
+    };
+    table.new_generation();
+
     let mut best_movement: Option<Movement> = None;
     let mut best_score = None;
+    let mut best_depth = 0;
+    let mut cache = EvalCache::new(ctx.eval_cache);
 
-    if ctx.iterative {
-        let timer = Instant::now();
-        for d in 1..=MAX_DEPTH {
-            if timer.elapsed().as_millis() > MAX_TIME_MS {
+    let depths = if ctx.iterative {
+        1..=MAX_DEPTH
+    } else {
+        ctx.depth..=ctx.depth
+    };
+    stats.node_stop = ctx.node_limit.map(|limit| {
+        stats
+            .explored
+            .saturating_add(limit.try_into().unwrap_or(u32::MAX))
+    });
+    stats.stopped = false;
+    let timer = Instant::now();
+    for mut d in depths {
+        if ctx.iterative && ctx.node_limit.is_none() && timer.elapsed().as_millis() > MAX_TIME_MS {
+            break;
+        }
+        let mut result = search_root(stats, ctx, board, player, table, &mut cache, out, d);
+        if stats.stopped {
+            // a search cut short by the node limit is only played when there is nothing better
+            if best_movement.is_some() {
                 break;
             }
-            let result = minimax(
-                stats,
-                ctx,
-                0,
-                board,
-                player,
-                table,
-                d,
-                i32::MIN + 1,
-                i32::MAX - 1,
-            );
-            if let Some(m) = result.movement {
-                best_movement = Some(m);
-                best_score = Some(result.score);
+            if result.movement.is_none() {
+                stats.node_stop = None;
+                stats.stopped = false;
+                d = 1;
+                result = search_root(stats, ctx, board, player, table, &mut cache, out, d);
+                stats.stopped = true;
             }
         }
-    } else {
-        let result = minimax(
-            stats,
-            ctx,
-            0,
-            board,
-            player,
-            table,
-            ctx.depth,
-            i32::MIN + 1,
-            i32::MAX - 1,
-        );
         if let Some(m) = result.movement {
+            let score = if ctx.absolute_scores {
+                absolute_score(result.score, player)
+            } else {
+                result.score
+            };
+            if let Some(callback) = on_iteration.as_mut() {
+                callback(d, score, &m);
+            }
+            if ctx.verbosity >= Verbosity::Iterations {
+                let iteration = SearchResult {
+                    pv: principal_variation(board, player, table, &m, d),
+                    movement: Some(m.clone()),
+                    score,
+                    depth: d,
+                    mate_in: mate_in(score),
+                };
+                let _ = writeln!(out, "{}", iteration.info(stats.explored));
+            }
             best_movement = Some(m);
-            best_score = Some(result.score);
+            best_score = Some(score);
+            best_depth = d;
+        }
+        if stats.stopped {
+            break;
         }
     }
+    stats.node_stop = None;
+    stats.stopped = false;
 
-    if ctx.verbose {
-        if best_score.is_some() {
-            println!("minimax engine score: {}", best_score.unwrap());
-        } else {
-            println!("no score found");
+    if ctx.verbosity >= Verbosity::Summary {
+        let _ = match best_score {
+            Some(score) => match mate_in(score) {
+                Some(n) => writeln!(out, "minimax engine score: mate in {}", n),
+                None => writeln!(out, "minimax engine score: {}", score),
+            },
+            None => writeln!(out, "no score found"),
+        };
+    }
+
+    if ctx.profile {
+        let _ = writeln!(out, "{}", stats.profile.info());
+    }
+
+    let movement = best_movement?;
+    let score = best_score?;
+    stats.moves += 1;
+    Some(SearchResult {
+        pv: principal_variation(board, player, table, &movement, best_depth),
+        movement: Some(movement),
+        score,
+        depth: best_depth,
+        mate_in: mate_in(score),
+    })
+}
+
+// The result of searching a position to a fixed depth.
+pub struct SearchResult {
+    // The best movement found (if any).
+    pub movement: Option<Movement>,
+    // The score of the position from the searching player's perspective.
+    pub score: i32,
+    // The depth the position was searched to.
+    pub depth: u32,
+    // The principal variation, starting with the best movement.
+    pub pv: Vec<Movement>,
+    // The number of moves until the game is decided, if the search found a forced result.
+    pub mate_in: Option<i32>,
+}
+
+impl SearchResult {
+    // Describe the result on one line as `info depth <d> score <cp | mate n> nodes <n> pv ...`.
+    pub fn info(&self, nodes: u32) -> String {
+        let score = match self.mate_in {
+            Some(n) => format!("mate {}", n),
+            None => self.score.to_string(),
+        };
+        let pv: Vec<String> = self.pv.iter().map(|m| m.to_string()).collect();
+        format!(
+            "info depth {} score {} nodes {} pv {}",
+            self.depth,
+            score,
+            nodes,
+            pv.join(" ")
+        )
+    }
+
+    // The principal variation as PDN movetext, e.g. `1. 11-15 23-19 2. 8-11`, played from
+    // `start` with `to_move` moving first. A variation starting with Player 2 opens with the move
+    // number followed by `...`. The movetext stops before the first movement that is not legal,
+    // e.g. when `start` is not the position that was searched.
+    pub fn pv_to_pdn(&self, start: &Board, to_move: Player) -> String {
+        let mut board = start.clone();
+        let mut player = to_move;
+        let mut number = 1;
+        let mut movetext = Vec::new();
+        for movement in &self.pv {
+            if !board.is_legal(movement, player) {
+                break;
+            }
+            match player {
+                Player::Player1 => movetext.push(format!("{}.", number)),
+                Player::Player2 if movetext.is_empty() => movetext.push(format!("{}...", number)),
+                Player::Player2 => {}
+            }
+            movetext.push(movement.to_string());
+            board.apply_and_promote(movement, player);
+            if player == Player::Player2 {
+                number += 1;
+            }
+            player = player.other();
         }
+        movetext.join(" ")
+    }
+}
+
+// Search the position for `player` to exactly `depth` plies, ignoring the depth and iterative
+// settings of the [MinimaxContext].
+pub fn search(
+    stats: &mut Stats,
+    ctx: &MinimaxContext,
+    board: &mut Board,
+    player: Player,
+    table: &mut TranspositionTable,
+    depth: u32,
+) -> SearchResult {
+    let result = search_root(
+        stats,
+        ctx,
+        board,
+        player,
+        table,
+        &mut EvalCache::new(ctx.eval_cache),
+        &mut std::io::sink(),
+        depth,
+    );
+    let pv = match &result.movement {
+        Some(m) => principal_variation(board, player, table, m, depth),
+        None => Vec::new(),
+    };
+    SearchResult {
+        movement: result.movement,
+        score: result.score,
+        depth,
+        pv,
+        mate_in: mate_in(result.score),
     }
+}
 
-    if best_movement.is_some() {
-        stats.moves += 1;
+// Search the position again to `depth` plies after `previous`, a shallower search of the same
+// position with the same `table`. The entries left in the table order the movements of the
+// deeper search, starting with the previous best movement, so deepening is incremental. The
+// table is only used when it is enabled in the context.
+#[allow(dead_code)]
+pub fn extend_search(
+    stats: &mut Stats,
+    ctx: &MinimaxContext,
+    board: &mut Board,
+    player: Player,
+    table: &mut TranspositionTable,
+    previous: &SearchResult,
+    depth: u32,
+) -> SearchResult {
+    if let Some(m) = &previous.movement {
+        // an entry of depth 0 never cuts the search, it only orders the movements
+        let hash = board.hash_with_turn(player);
+        if table.get(hash).is_none() {
+            table.store(
+                stats,
+                hash,
+                TTEntry {
+                    movement: m.clone(),
+                    score: previous.score,
+                    depth: 0,
+                    flag: Flag::Exact,
+                },
+            );
+        }
     }
+    search(stats, ctx, board, player, table, depth.max(previous.depth))
+}
+
+// Search every movement of `player` to `depth` plies and return the best `top` of them, best
+// first, each with its score and principal variation.
+pub fn analyze(
+    stats: &mut Stats,
+    ctx: &MinimaxContext,
+    board: &mut Board,
+    player: Player,
+    table: &mut TranspositionTable,
+    depth: u32,
+    top: usize,
+) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = board
+        .movements(player)
+        .into_iter()
+        .map(|m| {
+            let undo = board.do_move(&m, player);
+            let reply = search(
+                stats,
+                ctx,
+                board,
+                player.other(),
+                table,
+                depth.saturating_sub(1),
+            );
+            board.undo(undo);
+            // a forced result is one ply further away from this position
+            let score = match -reply.score {
+                score if score >= MATE_BAND => score - 1,
+                score if score <= -MATE_BAND => score + 1,
+                score => score,
+            };
+            let mut pv = vec![m.clone()];
+            pv.extend(reply.pv);
+            SearchResult {
+                movement: Some(m),
+                score,
+                depth,
+                pv,
+                mate_in: mate_in(score),
+            }
+        })
+        .collect();
+    results.sort_by_key(|r| -r.score);
+    results.truncate(top);
+    results
+}
 
-    best_movement
+// Movements scoring within this many centipawns of the best are candidates in a
+// [DifficultyReport].
+const DIFFICULTY_MARGIN: i32 = 25;
+
+// How hard a position is to play, measured by how the movements of a search compare.
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct DifficultyReport {
+    // The number of movements scoring within [DIFFICULTY_MARGIN] of the best.
+    pub candidate_count: usize,
+    // The difference in centipawns between the best and the worst movement.
+    pub score_spread: i32,
+    // True if a search at half the depth prefers a different movement.
+    pub best_changed_with_depth: bool,
+}
+
+// Estimate how difficult the position is for `player` by analyzing it at the depth of the
+// context and at half of it. A position with many good movements, or whose best movement is only
+// found by the deeper search, is more difficult than one with a single sensible movement.
+#[allow(dead_code)]
+pub fn estimate_difficulty(
+    ctx: &MinimaxContext,
+    board: &mut Board,
+    player: Player,
+) -> DifficultyReport {
+    let mut analyze_to = |depth| {
+        analyze(
+            &mut Stats::new(),
+            ctx,
+            board,
+            player,
+            &mut TranspositionTable::new(),
+            depth,
+            usize::MAX,
+        )
+    };
+    let shallow = analyze_to((ctx.depth / 2).max(1));
+    let deep = analyze_to(ctx.depth.max(1));
+
+    let scores: Vec<i32> = deep.iter().map(|r| r.score).collect();
+    let (best, worst) = match (scores.first(), scores.last()) {
+        (Some(&best), Some(&worst)) => (best, worst),
+        _ => (0, 0),
+    };
+    DifficultyReport {
+        candidate_count: scores
+            .iter()
+            .filter(|&&score| best.saturating_sub(score) <= DIFFICULTY_MARGIN)
+            .count(),
+        score_spread: best.saturating_sub(worst),
+        best_changed_with_depth: shallow.first().map(|r| &r.movement)
+            != deep.first().map(|r| &r.movement),
+    }
+}
+
+// Follow the best movements stored in the transposition table, starting with `movement`, for
+// at most `length` plies. The table only holds replies when it is enabled in the context, so
+// without it the variation is just `movement`.
+fn principal_variation(
+    board: &mut Board,
+    player: Player,
+    table: &TranspositionTable,
+    movement: &Movement,
+    length: u32,
+) -> Vec<Movement> {
+    let mut pv = vec![movement.clone()];
+    let mut undos = vec![board.do_move(movement, player)];
+    let mut side = player.other();
+    while (pv.len() as u32) < length {
+        match table.get(board.hash_with_turn(side)) {
+            Some(entry) if board.movements(side).contains(&entry.movement) => {
+                undos.push(board.do_move(&entry.movement, side));
+                pv.push(entry.movement.clone());
+                side = side.other();
+            }
+            _ => break,
+        }
+    }
+    while let Some(undo) = undos.pop() {
+        board.undo(undo);
+    }
+    pv
+}
+
+// Search on the opponent's time. `board` is the position after `player` has moved. The
+// opponent's reply is predicted (from the transposition table when possible, otherwise with a
+// shallower search) and the resulting position is searched for `player`, filling the table as a
+// side effect. The hash of the predicted position is passed to `on_prediction` before it is
+// searched. Returns that hash along with the movement found for it, or None when `stop` is set
+// before the search is done. The nodes searched while pondering are not counted in the caller's
+// [Stats].
+pub fn ponder(
+    ctx: &MinimaxContext,
+    board: &mut Board,
+    player: Player,
+    table: &mut TranspositionTable,
+    stop: &Arc<AtomicBool>,
+    on_prediction: &mut dyn FnMut(u128),
+) -> Option<(u128, Movement)> {
+    let opponent = player.other();
+    let movements = board.movements(opponent);
+    let mut stats = Stats::new();
+    stats.stop_signal = Some(Arc::clone(stop));
+    table.new_generation();
+
+    let predicted = match table.get(board.hash_with_turn(opponent)) {
+        Some(entry) if movements.contains(&entry.movement) => Some(entry.movement.clone()),
+        _ => {
+            search_root(
+                &mut stats,
+                ctx,
+                board,
+                opponent,
+                table,
+                &mut EvalCache::new(ctx.eval_cache),
+                &mut std::io::sink(),
+                ctx.depth.saturating_sub(1).max(1),
+            )
+            .movement
+        }
+    }?;
+    if stats.stopped {
+        return None;
+    }
+
+    let undo = board.do_move(&predicted, opponent);
+    let hash = board.hash_with_turn(player);
+    on_prediction(hash);
+    let result = search_root(
+        &mut stats,
+        ctx,
+        board,
+        player,
+        table,
+        &mut EvalCache::new(ctx.eval_cache),
+        &mut std::io::sink(),
+        ctx.depth,
+    );
+    board.undo(undo);
+
+    if stats.stopped {
+        return None;
+    }
+    result.movement.map(|m| (hash, m))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::checkers::{square_id, square_number, Piece};
+
+    use super::*;
+
+    #[test]
+    fn test_ponder_fills_table() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 4,
+            alpha_beta: true,
+            ponder: true,
+            ..Default::default()
+        };
+        let mut table = TranspositionTable::new();
+        let mut board = Board::new();
+
+        let movement = get_movement(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            Player::Player1,
+            &mut table,
+            None,
+        )
+        .unwrap();
+        board.do_movement(&movement);
+
+        let (hash, pondered) = ponder(
+            &ctx,
+            &mut board,
+            Player::Player1,
+            &mut table,
+            &Arc::default(),
+            &mut |_| {},
+        )
+        .unwrap();
+
+        // the opponent plays the predicted reply
+        let reply = table
+            .get(board.hash_with_turn(Player::Player2))
+            .unwrap()
+            .movement
+            .clone();
+        board.do_movement(&reply);
+        assert_eq!(hash, board.hash_with_turn(Player::Player1));
+
+        // the replies searched in the real search are answered from the table
+        let mut stats = Stats::new();
+        let movement = get_movement(
+            &mut stats,
+            &ctx,
+            &mut board,
+            Player::Player1,
+            &mut table,
+            None,
+        );
+        let mut cold = Stats::new();
+        get_movement(
+            &mut cold,
+            &ctx,
+            &mut board,
+            Player::Player1,
+            &mut TranspositionTable::new(),
+            None,
+        );
+        assert!(stats.tt_exact > 0);
+        assert!(stats.explored < cold.explored);
+        assert_eq!(movement, Some(pondered));
+    }
+
+    #[test]
+    fn test_ponder_crowns() {
+        let ctx = MinimaxContext {
+            depth: 2,
+            alpha_beta: true,
+            ponder: true,
+            ..Default::default()
+        };
+        // every reply of white crowns the pawn on 6, so the predicted position has a king
+        let (mut board, _) = Board::from_fen("W:W6:B20").unwrap();
+        let (hash, _) = ponder(
+            &ctx,
+            &mut board,
+            Player::Player1,
+            &mut TranspositionTable::new(),
+            &Arc::default(),
+            &mut |_| {},
+        )
+        .unwrap();
+        let predicted = board.movements(Player::Player2).into_iter().find(|m| {
+            let mut after = board.clone();
+            after.apply_and_promote(m, Player::Player2);
+            after.hash_with_turn(Player::Player1) == hash
+        });
+        assert!(predicted.is_some());
+        assert!(board.check_hash());
+    }
+
+    #[test]
+    fn test_on_iteration() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 0,
+            alpha_beta: true,
+            iterative: true,
+            ..Default::default()
+        };
+        let mut board = Board::new();
+        let mut iterations = Vec::new();
+        let mut on_iteration = |depth: u32, _score: i32, m: &Movement| {
+            iterations.push((depth, m.clone()));
+        };
+
+        let movement = get_movement(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            Player::Player1,
+            &mut TranspositionTable::new(),
+            Some(&mut on_iteration),
+        );
+
+        assert!(!iterations.is_empty());
+        for (i, (depth, _)) in iterations.iter().enumerate() {
+            assert_eq!(*depth, i as u32 + 1);
+        }
+        assert_eq!(iterations.last().map(|(_, m)| m.clone()), movement);
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 3,
+            alpha_beta: true,
+            move_ordering: true,
+            ..Default::default()
+        };
+        let choose = |ctx: &MinimaxContext| {
+            // each run starts from a fresh table
+            let (mut board, player) = Board::from_fen("B:WK32:BK1").unwrap();
+            get_movement(
+                &mut Stats::new(),
+                ctx,
+                &mut board,
+                player,
+                &mut TranspositionTable::new(),
+                None,
+            )
+            .unwrap()
+            .to_string()
+        };
+
+        // 1-5 and 1-6 both keep the material even; 6 is the lower board id
+        for _ in 0..5 {
+            assert_eq!(choose(&ctx), "1-6");
+        }
+
+        let random = MinimaxContext {
+            tie_break: TieBreak::Random(7),
+            ..ctx
+        };
+        let first = choose(&random);
+        for _ in 0..5 {
+            assert_eq!(choose(&random), first);
+        }
+
+        // a table entry for the root does not override the tie-break
+        let (mut board, player) = Board::from_fen("B:WK32:BK1").unwrap();
+        let mut table = TranspositionTable::new();
+        table.store(
+            &mut Stats::new(),
+            board.hash_with_turn(player),
+            TTEntry {
+                movement: board.parse_move(player, "1-5").unwrap(),
+                score: 0,
+                depth: MAX_DEPTH,
+                flag: Flag::Exact,
+            },
+        );
+        let movement = get_movement(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            player,
+            &mut table,
+            None,
+        );
+        assert_eq!(movement.unwrap().to_string(), "1-6");
+    }
+
+    #[test]
+    fn test_table_threshold() {
+        let ctx = MinimaxContext {
+            table: true,
+            table_threshold: 10,
+            depth: 4,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let probes = |mut board: Board, player| {
+            let mut stats = Stats::new();
+            let movement = get_movement(
+                &mut stats,
+                &ctx,
+                &mut board,
+                player,
+                &mut TranspositionTable::new(),
+                None,
+            );
+            assert!(movement.is_some());
+            stats.tt_probes
+        };
+
+        // two kings in the corners have 4 movements between them, the opening 14
+        let (board, player) = Board::from_fen("B:WK32:BK1").unwrap();
+        assert_eq!(probes(board, player), 0);
+        assert!(probes(Board::new(), Player::Player1) > 0);
+    }
+
+    #[test]
+    fn test_fail_soft_and_fail_hard() {
+        let pure = MinimaxContext {
+            depth: 0,
+            heuristic: evaluation2,
+            ..Default::default()
+        };
+        let mut board = Board::new();
+        let mut player = Player::Player1;
+        for notation in ["11-15", "23-19", "8-11", "22-17", "15-18"] {
+            let movement = board.parse_move(player, notation).unwrap();
+            board.do_movement(&movement);
+            player = player.other();
+        }
+
+        for fail_soft in [true, false] {
+            let ctx = MinimaxContext {
+                table: true,
+                table_threshold: 0,
+                alpha_beta: true,
+                fail_soft,
+                ..pure
+            };
+            // the table is filled by each depth and reused by the next
+            let mut table = TranspositionTable::new();
+            for depth in 1..=5 {
+                let expected = search(
+                    &mut Stats::new(),
+                    &pure,
+                    &mut board,
+                    player,
+                    &mut TranspositionTable::new(),
+                    depth,
+                );
+                for _ in 0..2 {
+                    let result = search(
+                        &mut Stats::new(),
+                        &ctx,
+                        &mut board,
+                        player,
+                        &mut table,
+                        depth,
+                    );
+                    assert_eq!(result.score, expected.score, "depth {}", depth);
+                }
+            }
+        }
+
+        // an exact score from the table outside the window is clamped to it when failing hard
+        let mut table = TranspositionTable::new();
+        table.store(
+            &mut Stats::new(),
+            board.hash_with_turn(player),
+            TTEntry {
+                movement: board.movements(player).remove(0),
+                score: 500,
+                depth: 3,
+                flag: Flag::Exact,
+            },
+        );
+        for (fail_soft, expected) in [(true, 500), (false, 10)] {
+            let ctx = MinimaxContext {
+                table: true,
+                alpha_beta: true,
+                fail_soft,
+                ..pure
+            };
+            let result = minimax(
+                &mut Stats::new(),
+                &ctx,
+                1,
+                &mut board,
+                player,
+                &mut table,
+                &mut EvalCache::new(false),
+                &mut std::io::sink(),
+                3,
+                -10,
+                10,
+            );
+            assert_eq!(result.score, expected);
+        }
+    }
+
+    #[test]
+    fn test_verbosity() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 3,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let output = |verbosity| {
+            let mut out = Vec::new();
+            get_movement_to(
+                &mut Stats::new(),
+                &MinimaxContext { verbosity, ..ctx },
+                &mut Board::new(),
+                Player::Player1,
+                &mut TranspositionTable::new(),
+                None,
+                &mut out,
+            );
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(output(Verbosity::Silent), "");
+        let summary = output(Verbosity::Summary);
+        assert_eq!(summary.lines().count(), 1);
+        assert!(summary.starts_with("minimax engine score: "));
+        let iterations = output(Verbosity::Iterations);
+        let lines: Vec<&str> = iterations.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("info depth 3 score "));
+        assert_eq!(lines[1], summary.trim_end());
+        let trace = output(Verbosity::Trace);
+        assert!(trace.lines().count() > 7);
+        assert!(trace.ends_with(&iterations));
+    }
+
+    #[test]
+    fn test_eval_below_mate_band() {
+        let squares = [
+            Square::Empty,
+            Square::Taken(Piece::player1_pawn()),
+            Square::Taken(Piece::player1_king()),
+            Square::Taken(Piece::player2_pawn()),
+            Square::Taken(Piece::player2_king()),
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let mut board = Board::empty();
+            for id in VALID_SQUARES {
+                board.set(id, squares[rng.gen_range(0..squares.len())]);
+            }
+            if board.piece_count() == (0, 0) {
+                continue;
+            }
+            for player in [Player::Player1, Player::Player2] {
+                for heuristic in [
+                    evaluation1,
+                    evaluation2,
+                    evaluation3,
+                    evaluation4,
+                    evaluation5,
+                ] {
+                    assert!(!is_mate(heuristic(&board, player)));
+                }
+            }
+        }
+
+        // the most lopsided position possible
+        let (board, _) = Board::from_fen("B:W:BK1-12,K21-32").unwrap();
+        for heuristic in [
+            evaluation1,
+            evaluation2,
+            evaluation3,
+            evaluation4,
+            evaluation5,
+        ] {
+            assert!(!is_mate(heuristic(&board, Player::Player1)));
+            assert!(!is_mate(heuristic(&board, Player::Player2)));
+        }
+    }
+
+    #[test]
+    fn test_estimate_difficulty() {
+        let ctx = MinimaxContext {
+            depth: 4,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        // 14x23 is forced
+        let (mut board, player) = Board::from_fen("B:W18,32:B14,1").unwrap();
+        let forced = estimate_difficulty(&ctx, &mut board, player);
+        assert_eq!(forced.candidate_count, 1);
+        assert_eq!(forced.score_spread, 0);
+        assert!(!forced.best_changed_with_depth);
+
+        // no movement of the opening loses material within 4 plies
+        let quiet = estimate_difficulty(&ctx, &mut Board::new(), Player::Player1);
+        assert_eq!(quiet.candidate_count, 7);
+        assert!(quiet.candidate_count > forced.candidate_count);
+    }
+
+    #[test]
+    fn test_get_movement_agrees_with_search() {
+        let ctx = MinimaxContext {
+            depth: 1,
+            alpha_beta: true,
+            heuristic: evaluation3,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..10 {
+            let mut board = Board::new();
+            let mut player = Player::Player1;
+            for _ in 0..rng.gen_range(0..30) {
+                let movements = board.movements(player);
+                if movements.is_empty() {
+                    break;
+                }
+                board.apply_and_promote(&movements[rng.gen_range(0..movements.len())], player);
+                player = player.other();
+            }
+            // either side can be searched on the same board, whoever moved last
+            for side in [Player::Player1, Player::Player2] {
+                for depth in 1..=4 {
+                    let ctx = MinimaxContext { depth, ..ctx };
+                    let mut scores = Vec::new();
+                    let movement = get_movement_to(
+                        &mut Stats::new(),
+                        &ctx,
+                        &mut board,
+                        side,
+                        &mut TranspositionTable::new(),
+                        Some(&mut |_, score, _| scores.push(score)),
+                        &mut std::io::sink(),
+                    );
+                    let result = search(
+                        &mut Stats::new(),
+                        &ctx,
+                        &mut board,
+                        side,
+                        &mut TranspositionTable::new(),
+                        depth,
+                    );
+                    assert_eq!(movement, result.movement);
+                    if movement.is_some() {
+                        assert_eq!(scores, [result.score]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_extend_search() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 8,
+            alpha_beta: true,
+            heuristic: evaluation2,
+            ..Default::default()
+        };
+        let board = Board::from_fen(
+            "W:W18,21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,5,6,7,8,9,10,11,12,15",
+        )
+        .unwrap()
+        .0;
+
+        let mut cold = Stats::new();
+        let expected = search(
+            &mut cold,
+            &ctx,
+            &mut board.clone(),
+            Player::Player2,
+            &mut TranspositionTable::new(),
+            8,
+        );
+
+        let mut table = TranspositionTable::new();
+        let previous = search(
+            &mut Stats::new(),
+            &ctx,
+            &mut board.clone(),
+            Player::Player2,
+            &mut table,
+            6,
+        );
+        let mut warm = Stats::new();
+        let result = extend_search(
+            &mut warm,
+            &ctx,
+            &mut board.clone(),
+            Player::Player2,
+            &mut table,
+            &previous,
+            8,
+        );
+        assert_eq!(result.score, expected.score);
+        assert_eq!(result.depth, 8);
+        assert!(warm.explored < cold.explored);
+    }
+
+    #[test]
+    fn test_profile() {
+        let ctx = MinimaxContext {
+            table: true,
+            alpha_beta: true,
+            profile: true,
+            heuristic: evaluation2,
+            ..Default::default()
+        };
+        let search_with = |ctx: &MinimaxContext| {
+            let mut stats = Stats::new();
+            let mut out = Vec::new();
+            get_movement_to(
+                &mut stats,
+                ctx,
+                &mut Board::new(),
+                Player::Player1,
+                &mut TranspositionTable::new(),
+                None,
+                &mut out,
+            );
+            (stats.profile, String::from_utf8(out).unwrap())
+        };
+
+        let (profile, out) = search_with(&ctx);
+        assert!(profile.movegen > Duration::ZERO);
+        assert!(profile.eval > Duration::ZERO);
+        assert!(profile.table > Duration::ZERO);
+        assert!(profile.do_undo > Duration::ZERO);
+        assert!(out.starts_with("profile movegen "));
+
+        let (profile, out) = search_with(&MinimaxContext {
+            profile: false,
+            ..ctx
+        });
+        assert_eq!(profile.movegen + profile.eval, Duration::ZERO);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_score_epsilon() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 4,
+            alpha_beta: true,
+            heuristic: evaluation2,
+            ..Default::default()
+        };
+        let choose = |ctx: &MinimaxContext| {
+            let mut stats = Stats::new();
+            let movement = get_movement_to(
+                &mut stats,
+                ctx,
+                &mut Board::new(),
+                Player::Player1,
+                &mut TranspositionTable::new(),
+                None,
+                &mut std::io::sink(),
+            );
+            movement.unwrap().to_string()
+        };
+        let best = choose(&ctx);
+
+        // several movements of the opening are within half a pawn of the best
+        let chosen: Vec<String> = (0..8)
+            .map(|seed| {
+                let random = MinimaxContext {
+                    score_epsilon: 50,
+                    seed,
+                    ..ctx
+                };
+                let movement = choose(&random);
+                assert_eq!(choose(&random), movement);
+                movement
+            })
+            .collect();
+        assert!(chosen.iter().any(|m| *m != best));
+    }
+
+    #[test]
+    fn test_order_moves() {
+        let ctx = MinimaxContext {
+            depth: 1,
+            alpha_beta: true,
+            verbosity: Verbosity::Trace,
+            ..Default::default()
+        };
+        let trace = |ctx: &MinimaxContext| {
+            let mut out = Vec::new();
+            get_movement_to(
+                &mut Stats::new(),
+                ctx,
+                &mut Board::new(),
+                Player::Player1,
+                &mut TranspositionTable::new(),
+                None,
+                &mut out,
+            );
+            let out = String::from_utf8(out).unwrap();
+            out.lines()
+                .map(|line| line.split(' ').next().unwrap().to_string())
+                .take(7)
+                .collect::<Vec<String>>()
+        };
+        // the root movements are searched in the preference order of the tie-break
+        assert_eq!(trace(&ctx)[0], "12-16");
+
+        fn by_to_square(_: &Board, _: Player, movements: &mut [Movement]) {
+            movements.sort_by_key(|m| square_number(m.to().id));
+        }
+        let explored = trace(&MinimaxContext {
+            order_moves: Some(by_to_square),
+            ..ctx
+        });
+        assert_eq!(
+            explored,
+            ["9-13", "10-14", "9-14", "11-15", "10-15", "12-16", "11-16"]
+        );
+    }
+
+    #[test]
+    fn test_absolute_scores() {
+        let (board, _) = Board::from_fen("B:W18,22,K30:B1,2,9,14").unwrap();
+        // evaluation2 and the evaluations built on CENTER are left out, as CENTER is not
+        // symmetric between the two sides
+        let heuristics: [fn(&Board, Player) -> i32; 2] = [evaluation1, evaluation_endgame];
+        for heuristic in heuristics {
+            let score = evaluate_absolute(heuristic, &board);
+            assert_ne!(score, 0);
+            assert_eq!(evaluate_absolute(heuristic, &board.rotated()), -score);
+        }
+
+        let ctx = MinimaxContext {
+            depth: 2,
+            alpha_beta: true,
+            verbosity: Verbosity::Summary,
+            ..Default::default()
+        };
+        // Player 2 is a pawn down
+        let (board, player) = Board::from_fen("W:W18,22:B1,2,3").unwrap();
+        let summary = |ctx: &MinimaxContext| {
+            let mut out = Vec::new();
+            get_movement_to(
+                &mut Stats::new(),
+                ctx,
+                &mut board.clone(),
+                player,
+                &mut TranspositionTable::new(),
+                None,
+                &mut out,
+            );
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(summary(&ctx), "minimax engine score: -100\n");
+        let absolute = MinimaxContext {
+            absolute_scores: true,
+            ..ctx
+        };
+        assert_eq!(summary(&absolute), "minimax engine score: 100\n");
+    }
+
+    #[test]
+    fn test_pv_to_pdn() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 4,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let pdn = |fen: &str| {
+            let (mut board, player) = Board::from_fen(fen).unwrap();
+            let result = search(
+                &mut Stats::new(),
+                &ctx,
+                &mut board,
+                player,
+                &mut TranspositionTable::new(),
+                4,
+            );
+            (result.pv_to_pdn(&board, player), result)
+        };
+        // every move of both sides is forced until Player 1 runs out of pieces
+        let (movetext, result) = pdn("B:W13,17:B5,9");
+        assert_eq!(movetext, "1. 9-14 17x10 2. 5-9 13x6");
+        assert_eq!(pdn("W:W13,17:B5,14").0, "1... 17x10 2. 5-9 13x6");
+        // the movetext stops at the first movement that is not legal from `start`
+        let (other, _) = Board::from_fen("B:W13,17:B5,10").unwrap();
+        assert_eq!(result.pv_to_pdn(&other, Player::Player1), "");
+    }
+
+    #[test]
+    fn test_root_table() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 5,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let mut table = TranspositionTable::new();
+        let (mut board, _) = Board::from_fen("B:W18,21,23,25,26:B6,9,10,11,14").unwrap();
+
+        let mut cold = Stats::new();
+        let first = search(&mut cold, &ctx, &mut board, Player::Player1, &mut table, 5);
+        // the root result is stored, so searching the position again starts with its movement
+        // and the replies are answered from the table
+        let mut warm = Stats::new();
+        let second = search(&mut warm, &ctx, &mut board, Player::Player1, &mut table, 5);
+        assert!(warm.explored < cold.explored);
+        assert!(warm.tt_exact > 0);
+        assert_eq!(
+            (second.movement, second.score),
+            (first.movement, first.score)
+        );
+
+        // the entry is keyed on the side to move, so the other side is searched in full
+        let mut other = Stats::new();
+        search(&mut other, &ctx, &mut board, Player::Player2, &mut table, 5);
+        assert!(other.explored > 0);
+    }
+
+    #[test]
+    fn test_table_aging() {
+        let movement = Board::new().movements(Player::Player1).remove(0);
+        let entry = |score| TTEntry {
+            movement: movement.clone(),
+            score,
+            depth: 1,
+            flag: Flag::Exact,
+        };
+        let mut stats = Stats::new();
+        let mut table = TranspositionTable::with_capacity(3);
+        table.store(&mut stats, 1, entry(1));
+        table.store(&mut stats, 2, entry(2));
+        table.new_generation();
+        table.store(&mut stats, 3, entry(3));
+        // the table is full, so the entries of the earlier generation make room
+        table.store(&mut stats, 4, entry(4));
+        assert_eq!(stats.tt_aged, 2);
+        assert_eq!(table.len(), 2);
+        assert!(table.get(1).is_none() && table.get(2).is_none());
+        assert_eq!(table.get(3).unwrap().score, 3);
+        assert_eq!(table.get(4).unwrap().score, 4);
+        table.store(&mut stats, 5, entry(5));
+        // with only current entries left, new positions are not stored but known ones are updated
+        table.store(&mut stats, 6, entry(6));
+        assert!(table.get(6).is_none());
+        table.store(&mut stats, 3, entry(30));
+        assert_eq!(table.get(3).unwrap().score, 30);
+        assert_eq!(table.len(), 3);
+        assert_eq!(stats.tt_aged, 2);
+    }
+
+    #[test]
+    fn test_table_consistency() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 4,
+            alpha_beta: true,
+            heuristic: evaluation2,
+            ..Default::default()
+        };
+        let (start, _) = Board::from_fen("B:WK1,K20:B14,24").unwrap();
+        let play = |moves: &[&str]| {
+            let mut board = start.clone();
+            let mut player = Player::Player1;
+            for m in moves {
+                let movement = board.parse_move(player, m).unwrap();
+                board.do_movement_checked(&movement, player).unwrap();
+                player = player.other();
+            }
+            (board, player)
+        };
+        let fresh = |board: &Board, player| {
+            search(
+                &mut Stats::new(),
+                &ctx,
+                &mut board.clone(),
+                player,
+                &mut TranspositionTable::new(),
+                ctx.depth,
+            )
+        };
+
+        // the same position, reached by crowning on 32 from either 27 or 28
+        let (mut a, player) = play(&["24-28", "1-6", "28-32", "6-1"]);
+        let (mut b, _) = play(&["24-27", "1-5", "27-32", "5-1"]);
+        assert_eq!(a.hash(), b.hash());
+        let mut table = TranspositionTable::new();
+        for board in [&mut a, &mut b] {
+            let expected = fresh(board, player);
+            let result = search(&mut Stats::new(), &ctx, board, player, &mut table, 4);
+            assert_eq!(result.movement, expected.movement);
+            assert_eq!(result.score, expected.score);
+        }
+
+        // the entries for the position with Player 1 to move are not used with Player 2 to move
+        let expected = fresh(&a, player.other());
+        let result = search(
+            &mut Stats::new(),
+            &ctx,
+            &mut a,
+            player.other(),
+            &mut table,
+            4,
+        );
+        assert_eq!(result.movement, expected.movement);
+        assert_eq!(result.score, expected.score);
+    }
+
+    #[test]
+    fn test_style_bias() {
+        // material, with a small edge for a Player 1 piece on square 7
+        fn heuristic(board: &Board, player: Player) -> i32 {
+            let edge = match board.get(square_id(7).unwrap()) {
+                Square::Taken(piece) if piece.get_player() == Player::Player1 => 10,
+                _ => 0,
+            };
+            let edge = if player == Player::Player1 {
+                edge
+            } else {
+                -edge
+            };
+            evaluation1(board, player) + edge
+        }
+        let ctx = MinimaxContext {
+            depth: 2,
+            alpha_beta: true,
+            heuristic,
+            ..Default::default()
+        };
+        // 10-14 attacks the pawn on 18 but scores 10 less than 3-7, while 10-15 attacks it and
+        // loses a pawn
+        let (board, player) = Board::from_fen("B:W18:B3,9,10").unwrap();
+        let choose = |ctx: &MinimaxContext| {
+            let mut stats = Stats::new();
+            let movement = get_movement(
+                &mut stats,
+                ctx,
+                &mut board.clone(),
+                player,
+                &mut TranspositionTable::new(),
+                None,
+            );
+            movement.unwrap().to_string()
+        };
+        assert_eq!(choose(&ctx), "3-7");
+        let aggressive = MinimaxContext {
+            style: StyleBias { aggression: 20 },
+            ..ctx
+        };
+        assert_eq!(choose(&aggressive), "10-14");
+        // the bonus is capped, so it never makes up for losing material
+        let reckless = MinimaxContext {
+            style: StyleBias { aggression: 1000 },
+            ..ctx
+        };
+        let reckless = choose(&reckless);
+        assert_ne!(reckless, "10-15");
+        assert_eq!(reckless, "10-14");
+    }
+
+    #[test]
+    fn test_crowning_in_search() {
+        let ctx = MinimaxContext {
+            alpha_beta: true,
+            ..Default::default()
+        };
+        // 7-3 crowns, so 2-7 does not trap a stuck pawn: the new king takes 3x10
+        let (mut board, player) = Board::from_fen("W:W7:B2,25").unwrap();
+        let result = search(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            player,
+            &mut TranspositionTable::new(),
+            4,
+        );
+        assert_eq!(result.movement.unwrap().to_string(), "7-3");
+        assert_eq!(result.mate_in, None);
+
+        // after 9x18x25 white still crowns on 3, so the capture wins material but no game
+        let (mut board, player) = Board::from_fen("B:W7,14,22:B2,9").unwrap();
+        let result = search(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            player,
+            &mut TranspositionTable::new(),
+            6,
+        );
+        assert_eq!(result.movement.unwrap().to_string(), "9x18x25");
+        assert_eq!(result.mate_in, None);
+    }
+
+    #[test]
+    fn test_mate_in() {
+        let ctx = MinimaxContext {
+            alpha_beta: true,
+            ..Default::default()
+        };
+        // 14x23 leaves white with only the pawn on 13, which is taken after 13-9 5x14
+        let (mut board, player) = Board::from_fen("B:W13,18:B1,2,5,14").unwrap();
+        let result = search(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            player,
+            &mut TranspositionTable::new(),
+            6,
+        );
+        assert_eq!(result.mate_in, Some(2));
+
+        let (mut board, player) = Board::from_fen("W:W13:B1,2,5").unwrap();
+        let result = search(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            player,
+            &mut TranspositionTable::new(),
+            6,
+        );
+        assert_eq!(result.mate_in, Some(-1));
+        assert_eq!(mate_in(100), None);
+
+        // a mate score stored at one ply is read back at another with the right distance
+        let ctx = MinimaxContext { table: true, ..ctx };
+        let mut table = TranspositionTable::new();
+        let mut at_ply = |ply, table: &mut TranspositionTable, stats: &mut Stats| {
+            minimax(
+                stats,
+                &ctx,
+                ply,
+                &mut board,
+                player,
+                table,
+                &mut EvalCache::new(false),
+                &mut std::io::sink(),
+                4,
+                -MATE_SCORE,
+                MATE_SCORE,
+            )
+            .score
+        };
+        at_ply(5, &mut table, &mut Stats::new());
+        let expected = at_ply(1, &mut TranspositionTable::new(), &mut Stats::new());
+        let mut stats = Stats::new();
+        assert_eq!(at_ply(1, &mut table, &mut stats), expected);
+        assert_eq!(stats.tt_exact, 1);
+    }
+
+    #[test]
+    fn test_trades() {
+        let without_trades = WeightedEval {
+            weights: {
+                let mut weights = WEIGHTED_EVAL.weights;
+                weights[7] = 0;
+                weights
+            },
+        };
+        // the score gained by Player 1 from an even trade, beyond the features other than trades
+        let gain = |before: &str, after: &str| {
+            let (before, _) = Board::from_fen(before).unwrap();
+            let (after, _) = Board::from_fen(after).unwrap();
+            let with = WEIGHTED_EVAL.evaluate(&after, Player::Player1)
+                - WEIGHTED_EVAL.evaluate(&before, Player::Player1);
+            let without = without_trades.evaluate(&after, Player::Player1)
+                - without_trades.evaluate(&before, Player::Player1);
+            with - without
+        };
+
+        // Player 1 is a piece up, and a trade of 9 for 20 helps it
+        assert_eq!(
+            material_balance(
+                &Board::from_fen("B:W20,21:B5,9,12").unwrap().0,
+                Player::Player1
+            ),
+            1
+        );
+        assert!(gain("B:W20,21:B5,9,12", "B:W21:B5,12") > 0);
+        // Player 1 is a piece down, and the same trade hurts it
+        assert!(gain("B:W20,21,22:B5,9", "B:W21,22:B5") < 0);
+        // with even material trades are neutral
+        assert_eq!(gain("B:W20,21:B5,9", "B:W21:B5"), 0);
+    }
+
+    #[test]
+    fn test_advancement() {
+        let advancement_of = |fen: &str| {
+            let (board, _) = Board::from_fen(fen).unwrap();
+            advancement(&board, Player::Player1)
+        };
+        assert_eq!(advancement_of("B:W32:B17"), 1);
+        assert_eq!(advancement_of("B:W32:B9"), -1);
+        assert_eq!(advancement_of("B:W13:B32"), -1);
+        assert_eq!(advancement_of("B:W13:B17"), 0);
+
+        // the same position seen from either side, or turned around, scores the same
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let mut board = Board::new();
+            let mut player = Player::Player1;
+            for _ in 0..rng.gen_range(0..40) {
+                let movements = board.movements(player);
+                if movements.is_empty() {
+                    break;
+                }
+                board.apply_and_promote(&movements[rng.gen_range(0..movements.len())], player);
+                player = player.other();
+            }
+            let p1 = advancement(&board, Player::Player1);
+            assert_eq!(p1, -advancement(&board, Player::Player2));
+            assert_eq!(p1, advancement(&board.rotated(), Player::Player2));
+        }
+    }
+
+    #[test]
+    fn test_defense() {
+        let defense_of = |fen: &str| {
+            let (board, player) = Board::from_fen(fen).unwrap();
+            defense(&board, player, &EVAL2_BACK_RANK)
+        };
+        // Player 1 guards its back row with four pawns, Player 2 with none
+        let midgame = "B:W13,14,15,16,17,18:B1,2,3,4,5,6,7";
+        let ending = "B:W13,14,15:B1,2,3,4";
+        assert_eq!(defense_of(midgame), 4 * EVAL2_BACK_RANK.guard);
+        assert_eq!(defense_of(ending), -4 * EVAL2_BACK_RANK.late_penalty);
+        assert!(defense_of(midgame) > 0);
+        assert!(defense_of(ending) < 0);
+        // the same pawns off the back row are neutral
+        assert_eq!(defense_of("B:W13,14,15:B5,6,7,8"), 0);
+    }
+
+    #[test]
+    fn test_dog_holes() {
+        let holes = |fen: &str| {
+            let (board, _) = Board::from_fen(fen).unwrap();
+            dog_holes(&board, Player::Player1)
+        };
+        // the pawn on 28 is stuck behind the piece on 32
+        assert_eq!(holes("B:W32:B28"), 1);
+        assert_eq!(holes("B:WK32:B28"), 1);
+        assert_eq!(holes("B:W31:B28"), 0);
+        assert_eq!(holes("B:W32:BK28"), 0);
+        // the pawn on 5 is stuck behind the piece on 1
+        assert_eq!(holes("B:W5:B1"), -1);
+        assert_eq!(holes("B:W5,32:B1,28"), 0);
+
+        let features = |fen: &str| {
+            let (board, _) = Board::from_fen(fen).unwrap();
+            WEIGHTED_EVAL.evaluate(&board, Player::Player1)
+        };
+        assert!(features("B:W32:B28") < features("B:W31:B28"));
+    }
+
+    #[test]
+    fn test_double_corners() {
+        let corners = |fen: &str| {
+            let (board, _) = Board::from_fen(fen).unwrap();
+            double_corners(&board, Player::Player1)
+        };
+        assert_eq!(corners("B:W20:B1,5"), 2);
+        assert_eq!(corners("B:W20:BK5"), 1);
+        // only the own double corner counts
+        assert_eq!(corners("B:W20:BK28,K32"), 0);
+        assert_eq!(corners("B:WK28,K32:B20"), -2);
+        assert_eq!(corners("B:W20:B4"), 0);
+    }
+
+    #[test]
+    fn test_runaway_pawns() {
+        let runaways = |fen: &str| {
+            let (board, _) = Board::from_fen(fen).unwrap();
+            runaway_pawns(&board, Player::Player1)
+        };
+        // the white king is too far behind to catch the pawn
+        assert_eq!(runaways("B:WK1:B22"), 1);
+        // the white king is in front of the pawn
+        assert_eq!(runaways("B:WK30:B22"), 0);
+        // the white pawn blocks the path of the black pawn and the other way around
+        assert_eq!(runaways("B:W26:B22"), 0);
+        // both pawns have passed each other
+        assert_eq!(runaways("B:W11:B22"), 0);
+        assert_eq!(runaways("B:W11,12:B22"), -1);
+
+        let evaluate = |fen: &str| {
+            let (board, player) = Board::from_fen(fen).unwrap();
+            evaluation_endgame(&board, player)
+        };
+        let king = evaluate("B:W:BK22");
+        let runaway = evaluate("B:W:B22");
+        let stoppable = evaluate("B:W26:B22,K1") - evaluate("B:W26:BK1");
+        assert_eq!(stoppable, 100);
+        assert_eq!(runaway, 100 + RUNAWAY_BONUS);
+        assert!(king - runaway < runaway - stoppable);
+    }
+
+    #[test]
+    fn test_phased_eval_continuous() {
+        // remove pieces one at a time, alternating sides so that material stays close
+        let (mut board, _) = Board::from_fen("B:W21-32:B1-12").unwrap();
+        let order = [
+            1, 32, 2, 31, 3, 30, 4, 29, 5, 28, 6, 27, 7, 26, 8, 25, 9, 24, 10, 23,
+        ];
+        let mut previous = evaluation4(&board, Player::Player1);
+        for number in order {
+            board.set(square_id(number).unwrap(), Square::Empty);
+            let score = evaluation4(&board, Player::Player1);
+            // never much more than the pawn that was removed
+            assert!((score - previous).abs() <= 150, "{} {}", previous, score);
+            previous = score;
+        }
+        assert_eq!(PHASED_EVAL.phase(&board), 0);
+    }
+
+    #[test]
+    fn test_stats_accumulator() {
+        let mut game1 = Stats::new();
+        game1.moves = 10;
+        game1.explored = 1000;
+        game1.max_depth = 6;
+        game1.tt_probes = 100;
+        game1.tt_exact = 10;
+        game1.tt_cuts = 15;
+        let mut game2 = Stats::new();
+        game2.moves = 30;
+        game2.explored = 3000;
+        game2.max_depth = 10;
+        game2.tt_probes = 300;
+        game2.tt_exact = 50;
+        game2.tt_cuts = 25;
+
+        let mut acc = StatsAccumulator::new();
+        acc.add(&game1);
+        acc.add(&game2);
+        assert_eq!(acc.games, 2);
+        assert_eq!(acc.explored, 4000);
+        assert_eq!(acc.nodes_per_move(), 100.0);
+        assert_eq!(acc.average_depth(), 8.0);
+        assert_eq!(acc.tt_hit_rate(), 0.25);
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        let mut ctx = MinimaxContext {
+            depth: 4,
+            alpha_beta: true,
+            quiescence: true,
+            eval_cache: true,
+            heuristic: evaluation3,
+            ..Default::default()
+        };
+        let mut board = Board::new();
+        let mut stats = Stats::new();
+        let mut cache = EvalCache::new(true);
+        let score = evaluation3(&board, Player::Player1);
+        assert_eq!(
+            cache.evaluate(&mut stats, &ctx, &board, Player::Player1),
+            score
+        );
+        assert_eq!(
+            cache.evaluate(&mut stats, &ctx, &board, Player::Player1),
+            score
+        );
+        assert_eq!(stats.eval_hits, 1);
+        assert_eq!(stats.eval_misses, 1);
+
+        // positions with a capture pending are not cached
+        let (pending, _) = Board::from_fen("B:W18:B14").unwrap();
+        let score = evaluation3(&pending, Player::Player1);
+        for _ in 0..2 {
+            assert_eq!(
+                cache.evaluate(&mut stats, &ctx, &pending, Player::Player1),
+                score
+            );
+        }
+        assert_eq!(stats.eval_hits, 1);
+        assert_eq!(stats.eval_misses, 1);
+
+        let mut player = Player::Player1;
+        let mut stats = Stats::new();
+        for _ in 0..6 {
+            let cached = get_movement(
+                &mut stats,
+                &ctx,
+                &mut board,
+                player,
+                &mut TranspositionTable::new(),
+                None,
+            );
+            ctx.eval_cache = false;
+            let fresh = get_movement(
+                &mut Stats::new(),
+                &ctx,
+                &mut board,
+                player,
+                &mut TranspositionTable::new(),
+                None,
+            );
+            ctx.eval_cache = true;
+            assert_eq!(cached, fresh);
+            board.do_movement(&fresh.unwrap());
+            player = player.other();
+        }
+        assert!(stats.eval_hits > 0);
+    }
+
+    #[test]
+    fn test_capture_nudge() {
+        let mut ctx = MinimaxContext {
+            depth: 0,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let leaf = |ctx: &MinimaxContext, fen: &str| {
+            let (mut board, player) = Board::from_fen(fen).unwrap();
+            minimax(
+                &mut Stats::new(),
+                ctx,
+                0,
+                &mut board,
+                player,
+                &mut TranspositionTable::new(),
+                &mut EvalCache::new(false),
+                &mut std::io::sink(),
+                0,
+                -MATE_SCORE,
+                MATE_SCORE,
+            )
+            .score
+        };
+
+        // 10x19 wins a piece that 15 can not win back
+        let winning = "B:W15:B10,27";
+        let plain = leaf(&ctx, winning);
+        ctx.capture_nudge = true;
+        assert_eq!(leaf(&ctx, winning), plain + CAPTURE_NUDGE);
+
+        // a capture that loses a piece back is not nudged, nor is a quiet position
+        for fen in ["B:W15,31:B10,27", "B:W31:B10"] {
+            ctx.capture_nudge = false;
+            let plain = leaf(&ctx, fen);
+            ctx.capture_nudge = true;
+            assert_eq!(leaf(&ctx, fen), plain, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let mut ctx = MinimaxContext {
+            table: true,
+            alpha_beta: true,
+            quiescence: true,
+            heuristic: evaluation3,
+            ..Default::default()
+        };
+        let run = |ctx: &MinimaxContext| {
+            let mut stats = Stats::new();
+            // nodes explored by an earlier search do not count against the limit
+            stats.explored = 1_000_000;
+            let movement = get_movement_to(
+                &mut stats,
+                ctx,
+                &mut Board::new(),
+                Player::Player1,
+                &mut TranspositionTable::new(),
+                None,
+                &mut std::io::sink(),
+            );
+            (stats.explored - 1_000_000, movement.unwrap())
+        };
+
+        let (unlimited, _) = run(&ctx);
+        for iterative in [false, true] {
+            ctx.iterative = iterative;
+            ctx.node_limit = Some(unlimited as u64 / 4);
+            let (explored, movement) = run(&ctx);
+            assert!(explored as u64 <= unlimited as u64 / 4);
+            assert_eq!(run(&ctx), (explored, movement));
+        }
+
+        // a limit too small to search a single movement in full still plays a movement
+        ctx.iterative = false;
+        ctx.node_limit = Some(1);
+        let (_, movement) = run(&ctx);
+        assert!(Board::new().is_legal(&movement, Player::Player1));
+    }
 }
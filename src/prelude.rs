@@ -0,0 +1,37 @@
+// This module re-exports the types and functions used by most code that plays or searches
+// games, so that they can be imported at once with `use crate::prelude::*` instead of from the
+// module defining each of them.
+#[allow(unused_imports)]
+pub use crate::{
+    checkers::{Board, Movement, Piece, Player, Square, SquareId, SquareState},
+    minimax::{
+        evaluation1, evaluation2, evaluation3, evaluation4, evaluation5, get_movement,
+        MinimaxContext, SharedTable, Stats, TranspositionTable,
+    },
+    runner::Runner,
+};
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+
+    #[test]
+    fn test_prelude() {
+        let ctx = MinimaxContext {
+            depth: 2,
+            alpha_beta: true,
+            heuristic: evaluation3,
+            ..Default::default()
+        };
+        let mut board = Board::new();
+        let movement: Option<Movement> = get_movement(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            Player::Player1,
+            &mut TranspositionTable::new(),
+            None,
+        );
+        assert!(board.is_legal(&movement.unwrap(), Player::Player1));
+    }
+}
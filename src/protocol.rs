@@ -0,0 +1,176 @@
+// This module contains a line-based text protocol, loosely modelled on UCI, that allows external
+// programs to drive the engine over stdin/stdout. The supported commands are:
+//
+//      isready
+//      position startpos [moves <move> ...]
+//      position fen <fen> [moves <move> ...]
+//      go [depth <n> | movetime <ms>]
+//      quit
+//
+// Moves are written in standard checkers notation, e.g. `11-15` or `11x18x25`.
+use std::{
+    io::{BufRead, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    checkers::{Board, Movement, Player},
+    minimax::{search, MinimaxContext, Stats, TranspositionTable, MAX_DEPTH},
+};
+
+// Build the position described by the arguments of a `position` command.
+fn parse_position(args: &[&str]) -> Result<(Board, Player), String> {
+    let (mut board, mut player, rest) = match args {
+        ["startpos", rest @ ..] => (Board::new(), Player::Player1, rest),
+        ["fen", rest @ ..] => {
+            let end = rest
+                .iter()
+                .position(|a| *a == "moves")
+                .unwrap_or(rest.len());
+            let fen = rest[..end].join(" ");
+            let (board, player) =
+                Board::from_fen(&fen).ok_or_else(|| format!("invalid fen {}", fen))?;
+            (board, player, &rest[end..])
+        }
+        _ => return Err("expected startpos or fen".to_string()),
+    };
+    if let Some(("moves", moves)) = rest.split_first().map(|(a, r)| (*a, r)) {
+        for notation in moves {
+            let movement = board
+                .parse_move(player, notation)
+                .map_err(|e| format!("{} {}", e, notation))?;
+            board
+                .do_movement_checked(&movement, player)
+                .map_err(|e| format!("{} {}", e, notation))?;
+            player = player.other();
+        }
+    }
+    Ok((board, player))
+}
+
+// Run the protocol loop until `quit` is received or `input` is exhausted.
+pub fn run<R: BufRead, W: Write>(
+    input: R,
+    output: &mut W,
+    ctx: MinimaxContext,
+) -> std::io::Result<()> {
+    let mut board = Board::new();
+    let mut player = Player::Player1;
+    let mut table = TranspositionTable::new();
+
+    for line in input.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["isready"] => writeln!(output, "readyok")?,
+            ["position", args @ ..] => match parse_position(args) {
+                Ok((b, p)) => {
+                    board = b;
+                    player = p;
+                }
+                Err(e) => writeln!(output, "info string {}", e)?,
+            },
+            ["go", args @ ..] => {
+                let (max_depth, movetime) = match args {
+                    ["depth", n] => (n.parse().unwrap_or(ctx.depth), None),
+                    ["movetime", ms] => (MAX_DEPTH, ms.parse::<u64>().ok()),
+                    _ => (ctx.depth, None),
+                };
+                let deadline =
+                    movetime.and_then(|ms| Instant::now().checked_add(Duration::from_millis(ms)));
+                let mut stats = Stats::new();
+                let mut best: Option<Movement> = None;
+                // the table is kept from one position to the next, aging the older entries
+                table.new_generation();
+                for depth in 1..=max_depth.clamp(1, MAX_DEPTH) {
+                    // the first depth is searched in full, so that there is a move to play
+                    stats.set_deadline(deadline.filter(|_| depth > 1));
+                    let result = search(&mut stats, &ctx, &mut board, player, &mut table, depth);
+                    // a depth cut short by the deadline did not compare all the movements
+                    if result.movement.is_none() || stats.stopped() {
+                        break;
+                    }
+                    writeln!(output, "{}", result.info(stats.explored))?;
+                    best = result.movement;
+                }
+                match best {
+                    Some(m) => writeln!(output, "bestmove {}", m)?,
+                    None => writeln!(output, "bestmove (none)")?,
+                }
+            }
+            ["quit"] => break,
+            _ => {}
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_go_depth() {
+        let ctx = MinimaxContext {
+            table: true,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let input = "position startpos\ngo depth 4\nquit\n".as_bytes();
+        let mut output = Vec::new();
+        run(input, &mut output, ctx).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(output.lines().filter(|l| l.starts_with("info")).count(), 4);
+        let bestmove = output
+            .lines()
+            .find_map(|l| l.strip_prefix("bestmove "))
+            .unwrap();
+        assert!(Board::new().parse_move(Player::Player1, bestmove).is_ok());
+    }
+
+    #[test]
+    fn test_go_depth_clamped() {
+        for go in ["go depth 0", "go depth x"] {
+            let ctx = MinimaxContext {
+                depth: 0,
+                ..Default::default()
+            };
+            let input = format!("position startpos\n{}\nquit\n", go);
+            let mut output = Vec::new();
+            run(input.as_bytes(), &mut output, ctx).unwrap();
+            let output = String::from_utf8(output).unwrap();
+
+            assert_eq!(output.lines().filter(|l| l.starts_with("info")).count(), 1);
+            let bestmove = output
+                .lines()
+                .find_map(|l| l.strip_prefix("bestmove "))
+                .unwrap();
+            assert!(
+                Board::new().parse_move(Player::Player1, bestmove).is_ok(),
+                "{}",
+                go
+            );
+        }
+    }
+
+    #[test]
+    fn test_go_movetime() {
+        // without alpha-beta pruning the deeper searches take far longer than the movetime, so
+        // they have to be stopped within a depth
+        let ctx = MinimaxContext::default();
+        let input = "position startpos\ngo movetime 100\nquit\n".as_bytes();
+        let mut output = Vec::new();
+        let timer = Instant::now();
+        run(input, &mut output, ctx).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(timer.elapsed() < Duration::from_secs(2));
+        let bestmove = output
+            .lines()
+            .find_map(|l| l.strip_prefix("bestmove "))
+            .unwrap();
+        assert!(Board::new().parse_move(Player::Player1, bestmove).is_ok());
+    }
+}
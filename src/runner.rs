@@ -1,28 +1,59 @@
 // This module contains the data structures and functions used to play a game for a given type of agent.
 
-use std::collections::HashMap;
+use std::{
+    collections::VecDeque,
+    io::{BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
 
-use rand::prelude::SliceRandom;
+use rand::seq::IteratorRandom;
 use uuid::Uuid;
 
 use crate::{
-    checkers::{Board, Movement, Player},
-    human::{get_user_input, MovementMap},
-    minimax::{get_movement, MinimaxContext, Stats, TTEntry},
+    checkers::{Board, Movement, Player, PromotionInfo},
+    human::{explain_illegal, get_user_input, read_input, Input, MovementMap},
+    minimax::{ponder, search_to, MinimaxContext, SearchResult, SharedTable, Stats},
 };
 
 enum RunnerKind {
     Random,
     AI,
     Human,
+    Scripted,
+}
+
+// A search running on the opponent's time on a worker thread.
+struct PonderWorker {
+    // The hash of the position the worker searches, sent once it has predicted the reply.
+    prediction: Receiver<u128>,
+    // Set to stop the search when the opponent did not play the predicted reply.
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Option<(u128, Movement)>>,
 }
 
 pub struct Runner<'a> {
     kind: RunnerKind,
     context: Option<MinimaxContext>,
-    table: Option<&'a mut HashMap<u128, TTEntry>>,
+    table: Option<SharedTable>,
     map: Option<MovementMap>,
     stats: Stats,
+    // The search running on the opponent's time, see [Runner::ponder].
+    pondering: Option<PonderWorker>,
+    // The result of the search behind the last move of an AI.
+    last: Option<SearchResult>,
+    // The movements played in the game so far, kept by humans to take moves back.
+    history: Vec<(Movement, PromotionInfo)>,
+    // The plies taken back since the game last asked, see [Runner::taken_back].
+    taken_back: u32,
+    // The movements left to play, in standard notation, for scripted runners.
+    script: VecDeque<String>,
+    // Where a human's input is read from, when not from the terminal.
+    input: Option<Box<dyn BufRead + Send + 'a>>,
 }
 
 impl<'a> Runner<'a> {
@@ -33,16 +64,28 @@ impl<'a> Runner<'a> {
             table: None,
             map: None,
             stats: Stats::new(),
+            pondering: None,
+            last: None,
+            history: Vec::new(),
+            taken_back: 0,
+            script: VecDeque::new(),
+            input: None,
         }
     }
 
-    pub fn ai(context: MinimaxContext, table: &'a mut HashMap<u128, TTEntry>) -> Self {
+    pub fn ai(context: MinimaxContext, table: SharedTable) -> Self {
         Self {
             kind: RunnerKind::AI,
             context: Some(context),
             table: Some(table),
             map: None,
             stats: Stats::new(),
+            pondering: None,
+            last: None,
+            history: Vec::new(),
+            taken_back: 0,
+            script: VecDeque::new(),
+            input: None,
         }
     }
 
@@ -53,66 +96,491 @@ impl<'a> Runner<'a> {
             table: None,
             map: Some(map),
             stats: Stats::new(),
+            pondering: None,
+            last: None,
+            history: Vec::new(),
+            taken_back: 0,
+            script: VecDeque::new(),
+            input: None,
+        }
+    }
+
+    // A human reading their moves from `input` instead of the terminal.
+    #[allow(dead_code)]
+    pub fn human_with_input(map: MovementMap, input: impl BufRead + Send + 'a) -> Self {
+        let mut runner = Self::human(map);
+        runner.input = Some(Box::new(input));
+        runner
+    }
+
+    // A runner playing `moves`, written in standard notation, in order. It resigns when it runs
+    // out of moves or its next move is not legal, e.g. to replay a recorded game.
+    #[allow(dead_code)]
+    pub fn scripted<S: Into<String>>(moves: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            kind: RunnerKind::Scripted,
+            context: None,
+            table: None,
+            map: None,
+            stats: Stats::new(),
+            pondering: None,
+            last: None,
+            history: Vec::new(),
+            taken_back: 0,
+            script: moves.into_iter().map(Into::into).collect(),
+            input: None,
         }
     }
 
-    pub fn display_stats(&self, player: &str, gameid: &Uuid) {
-        println!("game.{}.{}.moves = {}", &gameid, player, self.stats.moves);
-        println!(
+    // Write the stats of the runner for the game `gameid` to `out`.
+    pub fn display_stats(
+        &self,
+        out: &mut dyn Write,
+        player: &str,
+        gameid: &Uuid,
+    ) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "game.{}.{}.moves = {}",
+            &gameid, player, self.stats.moves
+        )?;
+        writeln!(
+            out,
             "game.{}.{}.explored = {}",
             &gameid, player, self.stats.explored
-        );
-        println!(
+        )?;
+        writeln!(
+            out,
             "game.{}.{}.beta_cuts = {}",
             &gameid, player, self.stats.beta_cuts
-        );
-        println!(
+        )?;
+        writeln!(
+            out,
+            "game.{}.{}.tt_probes = {}",
+            &gameid, player, self.stats.tt_probes
+        )?;
+        writeln!(
+            out,
             "game.{}.{}.tt_exact = {}",
             &gameid, player, self.stats.tt_exact
-        );
-        println!(
+        )?;
+        writeln!(
+            out,
             "game.{}.{}.tt_cuts = {}",
             &gameid, player, self.stats.tt_cuts
-        );
-        println!(
+        )?;
+        writeln!(
+            out,
+            "game.{}.{}.tt_aged = {}",
+            &gameid, player, self.stats.tt_aged
+        )?;
+        writeln!(
+            out,
             "game.{}.{}.max_depth = {}",
             &gameid, player, self.stats.max_depth
-        );
+        )?;
+        writeln!(
+            out,
+            "game.{}.{}.ponder_hit = {}",
+            &gameid, player, self.stats.ponder_hit
+        )?;
+        writeln!(
+            out,
+            "game.{}.{}.ponder_miss = {}",
+            &gameid, player, self.stats.ponder_miss
+        )?;
+        writeln!(
+            out,
+            "game.{}.{}.eval_hits = {}",
+            &gameid, player, self.stats.eval_hits
+        )?;
+        writeln!(
+            out,
+            "game.{}.{}.eval_misses = {}",
+            &gameid, player, self.stats.eval_misses
+        )?;
+        writeln!(
+            out,
+            "game.{}.{}.promotions = {}",
+            &gameid, player, self.stats.promotions
+        )?;
+        Ok(())
+    }
+
+    // Count the pawns crowned by the runner's last move.
+    pub fn record_promotions(&mut self, promotions: u32) {
+        self.stats.promotions += promotions;
     }
 
-    pub fn get_move(&mut self, board: &mut Board, player: Player) -> Option<Movement> {
+    // Note a movement played in the game by either player.
+    pub fn record_move(&mut self, movement: &Movement, info: PromotionInfo) {
+        if let RunnerKind::Human = self.kind {
+            self.history.push((movement.clone(), info));
+        }
+    }
+
+    // Take back the last move of the human and the reply of the engine, demoting any piece
+    // crowned by them. Returns false when there is no move pair to take back.
+    pub fn take_back(&mut self, board: &mut Board) -> bool {
+        if self.history.len() < 2 {
+            return false;
+        }
+        for _ in 0..2 {
+            let (movement, info) = self.history.pop().unwrap();
+            board.undo_movement_checked(&movement, info);
+        }
+        self.taken_back += 2;
+        true
+    }
+
+    // The number of plies taken back since the last call, so that the game can rewind its own
+    // state to match the board.
+    pub fn taken_back(&mut self) -> u32 {
+        std::mem::take(&mut self.taken_back)
+    }
+
+    // The score of the last move chosen by an AI, from its own perspective. None before its
+    // first move, and when the move was found while pondering.
+    #[allow(dead_code)]
+    pub fn last_score(&self) -> Option<i32> {
+        self.last.as_ref().map(|result| result.score)
+    }
+
+    // The principal variation of the last move chosen by an AI, starting with that move. Empty
+    // when [Runner::last_score] is None.
+    #[allow(dead_code)]
+    pub fn last_pv(&self) -> &[Movement] {
+        self.last.as_ref().map_or(&[], |result| &result.pv)
+    }
+
+    pub fn into_stats(mut self) -> Stats {
+        self.stop_pondering();
+        std::mem::replace(&mut self.stats, Stats::new())
+    }
+
+    // If the runner searches on the opponent's time.
+    pub fn ponders(&self) -> bool {
+        match self.kind {
+            RunnerKind::AI => self.context.as_ref().unwrap().ponder,
+            _ => false,
+        }
+    }
+
+    // Start searching on the opponent's time on a worker thread. `board` is the position after
+    // `player` has moved. The worker fills the table of the runner while the opponent chooses a
+    // reply, see [Runner::finish_pondering].
+    pub fn ponder(&mut self, board: &Board, player: Player) {
+        if !self.ponders() {
+            return;
+        }
+        self.stop_pondering();
+        let context = self.context.unwrap();
+        let table = self.table.clone().unwrap();
+        let mut board = board.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let signal = Arc::clone(&stop);
+        let (sender, prediction) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            ponder(
+                &context,
+                &mut board,
+                player,
+                &mut table.lock(),
+                &signal,
+                &mut |hash| {
+                    let _ = sender.send(hash);
+                },
+            )
+        });
+        self.pondering = Some(PonderWorker {
+            prediction,
+            stop,
+            handle,
+        });
+    }
+
+    // Once the opponent has moved, returns the movement found by the worker started with
+    // [Runner::ponder] if it searched the position on `board` with `player` to move. The worker
+    // is left to finish its search when the opponent played the predicted reply, and stopped
+    // otherwise.
+    fn finish_pondering(&mut self, board: &Board, player: Player) -> Option<Movement> {
+        let worker = self.pondering.as_ref()?;
+        if worker.prediction.recv().ok() != Some(board.hash_with_turn(player)) {
+            self.stop_pondering();
+            return None;
+        }
+        let worker = self.pondering.take()?;
+        let (_, movement) = worker.handle.join().expect("the ponder thread panicked")?;
+        board.is_legal(&movement, player).then_some(movement)
+    }
+
+    // Stop the worker started with [Runner::ponder], if any.
+    fn stop_pondering(&mut self) {
+        if let Some(worker) = self.pondering.take() {
+            worker.stop.store(true, Ordering::Relaxed);
+            let _ = worker.handle.join();
+        }
+    }
+
+    // Choose the move of `player`. Any output of the runner (the search output of an AI, or the
+    // board and messages shown to a human) is written to `out`.
+    pub fn get_move(
+        &mut self,
+        board: &mut Board,
+        player: Player,
+        out: &mut dyn Write,
+    ) -> Option<Movement> {
         match self.kind {
             RunnerKind::Random => {
-                let movements = board.movements(player);
-                if movements.is_empty() {
-                    return None;
+                let movement = board.legal_moves(player).choose(&mut rand::thread_rng());
+                if movement.is_some() {
+                    self.stats.moves += 1;
                 }
-                self.stats.moves += 1;
-                movements.choose(&mut rand::thread_rng()).cloned()
+                movement
+            }
+            RunnerKind::AI => {
+                self.last = None;
+                if self.pondering.is_some() {
+                    if let Some(movement) = self.finish_pondering(board, player) {
+                        self.stats.ponder_hit += 1;
+                        self.stats.moves += 1;
+                        return Some(movement);
+                    }
+                    self.stats.ponder_miss += 1;
+                }
+                self.last = search_to(
+                    &mut self.stats,
+                    self.context.as_ref().unwrap(),
+                    board,
+                    player,
+                    &mut self.table.as_ref().unwrap().lock(),
+                    None,
+                    out,
+                );
+                self.last
+                    .as_ref()
+                    .and_then(|result| result.movement.clone())
             }
-            RunnerKind::AI => get_movement(
-                &mut self.stats,
-                self.context.as_ref().unwrap(),
-                board,
-                player,
-                self.table.as_mut().unwrap(),
-            ),
             RunnerKind::Human => {
-                let movements = board.movements(Player::Player1);
-                if movements.is_empty() {
-                    return None;
+                // no legal moves means the game is over
+                board.legal_moves(Player::Player1).next()?;
+                writeln!(out, "{}", &board).unwrap();
+                if let Some(loss) = board.forced_loss_after_capture(Player::Player1) {
+                    if loss < 0 {
+                        writeln!(out, "warning: every capture loses {} piece(s)", -loss).unwrap();
+                    }
                 }
-                println!("{}", &board);
                 loop {
-                    let movement = get_user_input(board, self.map.as_ref().unwrap());
-                    if let Some(movement) = movement {
-                        if movements.iter().any(|m| *m == movement) {
+                    let map = self.map.as_ref().unwrap();
+                    let input = match self.input.as_mut() {
+                        Some(reader) => read_input(board, map, reader),
+                        None => get_user_input(board, map),
+                    };
+                    match input {
+                        Ok(Input::Takeback) => {
+                            if self.take_back(board) {
+                                writeln!(out, "{}", &board).unwrap();
+                            } else {
+                                writeln!(out, "nothing to take back").unwrap();
+                            }
+                        }
+                        Ok(Input::Move(movement)) if board.is_legal(&movement, Player::Player1) => {
+                            let hanging = board.pieces_hanging_after(&movement, Player::Player1);
+                            if hanging > 0 {
+                                writeln!(out, "warning: this move hangs {} piece(s)", hanging)
+                                    .unwrap();
+                            }
                             self.stats.moves += 1;
                             return Some(movement);
                         }
+                        Ok(Input::Move(movement)) => {
+                            writeln!(
+                                out,
+                                "{}",
+                                explain_illegal(board, &movement, Player::Player1)
+                            )
+                            .unwrap();
+                        }
+                        Ok(Input::Resign) => return None,
+                        Err(e) => writeln!(out, "{}", e).unwrap(),
+                    }
+                }
+            }
+            RunnerKind::Scripted => {
+                let notation = self.script.pop_front()?;
+                match board.parse_move(player, &notation) {
+                    Ok(movement) => {
+                        self.stats.moves += 1;
+                        Some(movement)
+                    }
+                    Err(e) => {
+                        writeln!(out, "scripted move {}: {}", notation, e).unwrap();
+                        None
                     }
                 }
             }
         }
     }
 }
+
+// A runner left pondering when the game ends stops its worker instead of leaving it running.
+impl Drop for Runner<'_> {
+    fn drop(&mut self) {
+        self.stop_pondering();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display_stats() {
+        let mut runner = Runner::random();
+        let mut board = Board::new();
+        let mut out = Vec::new();
+        assert!(runner
+            .get_move(&mut board, Player::Player1, &mut out)
+            .is_some());
+        runner.record_promotions(2);
+        // a random runner writes nothing while choosing a move
+        assert!(out.is_empty());
+
+        let gameid = Uuid::new_v4();
+        runner.display_stats(&mut out, "player1", &gameid).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 13);
+        assert!(out.contains(&format!("game.{}.player1.moves = 1\n", gameid)));
+        assert!(out.contains(&format!("game.{}.player1.explored = 0\n", gameid)));
+        assert!(out.contains(&format!("game.{}.player1.promotions = 2\n", gameid)));
+    }
+
+    #[test]
+    fn test_last_search() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 4,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let mut runner = Runner::ai(ctx, SharedTable::new());
+        assert_eq!(runner.last_score(), None);
+        assert!(runner.last_pv().is_empty());
+
+        // 10x19 wins a piece, the replies on the table follow it
+        let (mut board, player) = Board::from_fen("B:W15,30:B10,27").unwrap();
+        let movement = runner
+            .get_move(&mut board, player, &mut std::io::sink())
+            .unwrap();
+        assert_eq!(movement.to_string(), "10x19");
+        assert!(runner.last_score().unwrap() > 0);
+        assert_eq!(runner.last_pv().first(), Some(&movement));
+        assert!(runner.last_pv().len() > 1);
+    }
+
+    #[test]
+    fn test_ponder() {
+        let ctx = MinimaxContext {
+            table: true,
+            depth: 4,
+            alpha_beta: true,
+            ponder: true,
+            ..Default::default()
+        };
+        let mut runner = Runner::ai(ctx, SharedTable::new());
+        // white has to reply 22x15, so the worker searches the position the game reaches
+        let (mut board, _) = Board::from_fen("W:W22:B1,18").unwrap();
+        runner.ponder(&board, Player::Player1);
+        let reply = board.parse_move(Player::Player2, "22x15").unwrap();
+        board.apply_and_promote(&reply, Player::Player2);
+        let movement = runner
+            .get_move(&mut board, Player::Player1, &mut std::io::sink())
+            .unwrap();
+        assert!(board.is_legal(&movement, Player::Player1));
+        // the movement came from the worker, without a search of its own
+        assert_eq!(runner.last_score(), None);
+
+        // a position the worker did not predict is searched from scratch
+        runner.ponder(&board, Player::Player1);
+        let movement = runner
+            .get_move(&mut Board::new(), Player::Player1, &mut std::io::sink())
+            .unwrap();
+        assert!(Board::new().is_legal(&movement, Player::Player1));
+        assert!(runner.last_score().is_some());
+
+        let stats = runner.into_stats();
+        assert_eq!((stats.ponder_hit, stats.ponder_miss), (1, 1));
+    }
+
+    #[test]
+    fn test_human_illegal_move() {
+        let (mut board, player) = Board::from_fen("B:W18:B9,14").unwrap();
+        // G6-H5 is refused while F5 can jump E4, then the jump is played
+        let input = "S: G6 H5\nJ: F5 E4 D3\n".as_bytes();
+        let mut runner = Runner::human_with_input(MovementMap::new(), input);
+        let mut out = Vec::new();
+        let movement = runner.get_move(&mut board, player, &mut out).unwrap();
+        assert_eq!(movement.to_string(), "14x23");
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("you must capture: a jump is available from F5\n"));
+
+        // the human resigns when the input ends
+        let (mut board, player) = Board::from_fen("B:W18:B9,14").unwrap();
+        let mut runner = Runner::human_with_input(MovementMap::new(), "".as_bytes());
+        assert!(runner
+            .get_move(&mut board, player, &mut std::io::sink())
+            .is_none());
+    }
+
+    #[test]
+    fn test_scripted() {
+        let mut runner = Runner::scripted(["11-15", "15-19", "9-13"]);
+        let mut board = Board::new();
+        let mut out = Vec::new();
+        let movement = runner.get_move(&mut board, Player::Player1, &mut out);
+        assert_eq!(movement.unwrap().to_string(), "11-15");
+        // 15-19 is not a movement in the opening
+        assert!(runner
+            .get_move(&mut board, Player::Player1, &mut out)
+            .is_none());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "scripted move 15-19: illegal move\n"
+        );
+        let movement = runner.get_move(&mut board, Player::Player1, &mut Vec::new());
+        assert_eq!(movement.unwrap().to_string(), "9-13");
+        assert!(runner
+            .get_move(&mut board, Player::Player1, &mut Vec::new())
+            .is_none());
+        assert_eq!(runner.into_stats().moves, 2);
+    }
+
+    #[test]
+    fn test_take_back() {
+        let mut runner = Runner::human(MovementMap::new());
+        let (mut board, _) = Board::from_fen("B:WK1:B24,28").unwrap();
+        let start = (board.to_string(), board.hash());
+
+        let play = |board: &mut Board, runner: &mut Runner, player, notation| {
+            let movement = board.parse_move(player, notation).unwrap();
+            let info = board.do_movement_checked(&movement, player).unwrap();
+            runner.record_move(&movement, info);
+        };
+        // the first move crowns the pawn on 28
+        play(&mut board, &mut runner, Player::Player1, "28-32");
+        play(&mut board, &mut runner, Player::Player2, "1-6");
+        assert_eq!(board.king_count(Player::Player1), 1);
+        let after_first = (board.to_string(), board.hash());
+        play(&mut board, &mut runner, Player::Player1, "24-27");
+        play(&mut board, &mut runner, Player::Player2, "6-1");
+
+        assert!(runner.take_back(&mut board));
+        assert_eq!((board.to_string(), board.hash()), after_first);
+        assert!(runner.take_back(&mut board));
+        assert_eq!((board.to_string(), board.hash()), start);
+        assert_eq!(board.king_count(Player::Player1), 0);
+        assert!(!runner.take_back(&mut board));
+        assert_eq!(runner.taken_back(), 4);
+        assert_eq!(runner.taken_back(), 0);
+    }
+}
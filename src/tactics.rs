@@ -0,0 +1,68 @@
+// This module contains a suite of tactical positions with known best moves, used to guard the
+// engine against evaluation and search regressions. Positions are given in FEN (Player 1 is
+// black and moves first) and moves in standard checkers notation.
+use crate::{
+    checkers::Board,
+    minimax::{
+        evaluation1, evaluation2, evaluation3, evaluation4, evaluation5, get_movement,
+        MinimaxContext, Stats, TranspositionTable,
+    },
+};
+
+// Assert that every evaluation function finds `expected` in the position described by `fen`.
+fn assert_best_move(fen: &str, depth: u32, expected: &str) {
+    for heuristic in [
+        evaluation1 as fn(&Board, _) -> _,
+        evaluation2,
+        evaluation3,
+        evaluation4,
+        evaluation5,
+    ] {
+        let ctx = MinimaxContext {
+            table: true,
+            depth,
+            alpha_beta: true,
+            heuristic,
+            ..Default::default()
+        };
+        let (mut board, player) = Board::from_fen(fen).unwrap();
+        let movement = get_movement(
+            &mut Stats::new(),
+            &ctx,
+            &mut board,
+            player,
+            &mut TranspositionTable::new(),
+            None,
+        );
+        assert_eq!(movement.unwrap().to_string(), expected, "position {}", fen);
+    }
+}
+
+// Black can capture with 2x11 or with the double jump 9x18x25. 2x11 is answered by 14x5, while
+// the double jump leaves white with a single pawn. It crowns at once, but the pawn on 25 crowns
+// right after, two pieces against one (2-7 instead would lose the pawn to 3x10):
+//
+//      9x18x25 7-3 25-30
+#[test]
+fn test_multi_jump_win() {
+    assert_best_move("B:W7,14,22:B2,9", 6, "9x18x25");
+}
+
+// Both sides run for the king row, but the white pawn on 9 cannot pass 1 (9-6 is answered by
+// 1x10), so black crowns first if it runs down the side that 30 does not guard. Moving 1 instead
+// lets white crown first, and 18-22 is stopped by 30 (22-25 30x21, 22-26 30x23):
+//
+//      18-23 9-5 23-27 30-25 27-32
+#[test]
+fn test_promotion_race() {
+    assert_best_move("B:W9,30:B1,18", 6, "18-23");
+}
+
+// Black gives up the pawn on 16 so that 23 is pulled away to 16, after which 12 takes two
+// pieces. 24 cannot take instead because 15 blocks the landing square:
+//
+//      16-19 23x16 12x19x28
+#[test]
+fn test_two_for_one_shot() {
+    assert_best_move("B:W23,24,32:B12,15,16", 6, "16-19");
+}
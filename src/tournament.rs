@@ -0,0 +1,217 @@
+// This module contains a round-robin tournament between engine configurations, with an estimate
+// of the Elo rating of each engine from the results. Every pair of engines plays the same number
+// of games. Each opening is made of a few random plies drawn from a seeded generator and is
+// played twice, once with each engine moving first, so that neither engine gets the better side
+// of an opening. With iterative deepening and pondering disabled (both depend on timing) the
+// results are the same for the same seed.
+use std::io::Write;
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    checkers::{Board, Player, Rules},
+    game::play_game,
+    minimax::{MinimaxContext, SharedTable},
+    runner::Runner,
+};
+
+// The random plies played at the start of every game.
+const OPENING_PLIES: u32 = 4;
+// The number of gradient steps taken when fitting the ratings.
+const ELO_ITERATIONS: u32 = 2_000;
+// The step size used when fitting the ratings.
+const ELO_RATE: f64 = 20.0;
+
+// The results of a tournament. `wins[i][j]` is the number of games engine `i` won against
+// engine `j`, and `draws[i][j]` the number of games they drew.
+pub struct Results {
+    pub wins: Vec<Vec<u32>>,
+    pub draws: Vec<Vec<u32>>,
+}
+
+impl Results {
+    fn new(engines: usize) -> Self {
+        Self {
+            wins: vec![vec![0; engines]; engines],
+            draws: vec![vec![0; engines]; engines],
+        }
+    }
+
+    // The number of games engine `i` lost against engine `j`.
+    pub fn losses(&self, i: usize, j: usize) -> u32 {
+        self.wins[j][i]
+    }
+
+    // The number of games played between engines `i` and `j`.
+    pub fn games(&self, i: usize, j: usize) -> u32 {
+        self.wins[i][j] + self.draws[i][j] + self.losses(i, j)
+    }
+
+    // Estimate the Elo rating of each engine, relative to an average of 0, with a logistic fit of
+    // the results: the expected score of `i` against `j` is `1 / (1 + 10^((r_j - r_i) / 400))`.
+    // Every pairing counts one extra draw so that a perfect score still gives a finite rating.
+    pub fn elo(&self) -> Vec<f64> {
+        let engines = self.wins.len();
+        let mut ratings = vec![0.0; engines];
+        for _ in 0..ELO_ITERATIONS {
+            let mut gradient = vec![0.0; engines];
+            for i in 0..engines {
+                for j in 0..engines {
+                    if i == j || self.games(i, j) == 0 {
+                        continue;
+                    }
+                    let games = self.games(i, j) as f64 + 1.0;
+                    let score = self.wins[i][j] as f64 + (self.draws[i][j] as f64 + 1.0) / 2.0;
+                    let expected = 1.0 / (1.0 + 10f64.powf((ratings[j] - ratings[i]) / 400.0));
+                    gradient[i] += (score - expected * games) / games;
+                }
+            }
+            for i in 0..engines {
+                ratings[i] += ELO_RATE * gradient[i];
+            }
+            let mean = ratings.iter().sum::<f64>() / engines as f64;
+            for rating in ratings.iter_mut() {
+                *rating -= mean;
+            }
+        }
+        ratings
+    }
+
+    // Write the results matrix and the ratings to `out`, naming the engines with `names`.
+    pub fn display(&self, names: &[String], out: &mut dyn Write) -> std::io::Result<()> {
+        for (i, name) in names.iter().enumerate() {
+            for (j, other) in names.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                writeln!(
+                    out,
+                    "tournament.{}.{}.wins = {}",
+                    name, other, self.wins[i][j]
+                )?;
+                writeln!(
+                    out,
+                    "tournament.{}.{}.draws = {}",
+                    name, other, self.draws[i][j]
+                )?;
+                writeln!(
+                    out,
+                    "tournament.{}.{}.losses = {}",
+                    name,
+                    other,
+                    self.losses(i, j)
+                )?;
+            }
+        }
+        for (name, elo) in names.iter().zip(self.elo()) {
+            writeln!(out, "tournament.{}.elo = {:.1}", name, elo)?;
+        }
+        Ok(())
+    }
+}
+
+// Play the random opening for game number `game` of a pairing onto `board`.
+fn play_opening(board: &mut Board, seed: u64, game: u32) {
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(game as u64 / 2));
+    let mut player = Player::Player1;
+    for _ in 0..OPENING_PLIES {
+        let movements = board.movements(player);
+        match movements.choose(&mut rng) {
+            Some(movement) => board.apply_and_promote(movement, player),
+            None => return,
+        };
+        player = player.other();
+    }
+}
+
+// Play `games` games between every pair of `engines` and collect the results.
+pub fn run(
+    engines: &[MinimaxContext],
+    games: u32,
+    seed: u64,
+    rules: Rules,
+    max_plies: u32,
+) -> Results {
+    let mut results = Results::new(engines.len());
+    for i in 0..engines.len() {
+        for j in (i + 1)..engines.len() {
+            for game in 0..games {
+                // the engines swap sides after each game so that both play each opening
+                let (first, second) = if game % 2 == 0 { (i, j) } else { (j, i) };
+                let mut player1 = Runner::ai(engines[first], SharedTable::new());
+                let mut player2 = Runner::ai(engines[second], SharedTable::new());
+
+                let mut board = Board::new().with_rules(rules);
+                play_opening(&mut board, seed, game);
+                let (winner, _) = play_game(
+                    &mut player1,
+                    &mut player2,
+                    &mut board,
+                    max_plies,
+                    false,
+                    &mut std::io::sink(),
+                );
+                match winner {
+                    Some(Player::Player1) => results.wins[first][second] += 1,
+                    Some(Player::Player2) => results.wins[second][first] += 1,
+                    None => {
+                        results.draws[i][j] += 1;
+                        results.draws[j][i] += 1;
+                    }
+                }
+            }
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod test {
+    use crate::minimax::evaluation2;
+
+    use super::*;
+
+    #[test]
+    fn test_tournament() {
+        let engine = MinimaxContext {
+            depth: 2,
+            alpha_beta: true,
+            ..Default::default()
+        };
+        let engines = [
+            engine,
+            MinimaxContext {
+                heuristic: evaluation2,
+                ..engine
+            },
+        ];
+
+        let results = run(&engines, 2, 7, Rules::default(), 200);
+        assert_eq!(results.games(0, 1), 2);
+        assert_eq!(results.games(1, 0), 2);
+        assert_eq!(results.wins[0][1], results.losses(1, 0));
+        assert_eq!(results.draws[0][1], results.draws[1][0]);
+        assert_eq!(results.games(0, 0), 0);
+
+        // the ratings are relative to an average of 0 and favour the engine that scored more
+        let elo = results.elo();
+        assert!((elo[0] + elo[1]).abs() < 1e-6);
+        let score = results.wins[0][1] as f64 + results.draws[0][1] as f64 / 2.0;
+        assert_eq!(elo[0] > elo[1], score > 1.0);
+
+        // the same seed replays the same tournament
+        let replay = run(&engines, 2, 7, Rules::default(), 200);
+        assert_eq!(replay.wins, results.wins);
+        assert_eq!(replay.draws, results.draws);
+
+        // three lines for each ordered pair of engines, then a rating for each
+        let names = ["v1".to_string(), "v2".to_string()];
+        let mut out = Vec::new();
+        results.display(&names, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 8);
+        let wins = format!("tournament.v1.v2.wins = {}", results.wins[0][1]);
+        assert!(out.lines().any(|line| line == wins));
+        assert!(out.contains("tournament.v2.elo = "));
+    }
+}
@@ -0,0 +1,157 @@
+// This module contains an offline tuner for the weights of the [WeightedEval]. Positions are
+// collected from self-play games and labelled with the result of their game, then the weights are
+// fitted so that the evaluation predicts the results (a Texel-style least-squares fit): each
+// weight in turn is nudged up and down, and the change is kept when it lowers the mean squared
+// error between the predicted and the actual results.
+use rand::{seq::SliceRandom, thread_rng};
+
+use crate::{
+    checkers::{Board, Player},
+    minimax::{
+        get_movement, MinimaxContext, Stats, TranspositionTable, WeightedEval, WEIGHTED_EVAL,
+        WEIGHTED_FEATURES,
+    },
+};
+
+// The names of the features scored by a [WeightedEval], in the order of its weights.
+const FEATURE_NAMES: [&str; WEIGHTED_FEATURES] = [
+    "pawn",
+    "king",
+    "runaway",
+    "back_row",
+    "center",
+    "dog_hole",
+    "double_corner",
+    "trade",
+];
+// Random plies played at the start of each game so that the games differ.
+const OPENING_PLIES: u32 = 4;
+// Plies after which a self-play game is scored as a draw.
+const MAX_PLIES: u32 = 200;
+// How many centipawns of advantage make a win about 10 times as likely as a loss.
+const SCORE_SCALE: f64 = 400.0;
+// The step, in centipawns, by which a weight is nudged.
+const STEP: i32 = 5;
+// The maximum number of passes over the weights.
+const MAX_ITERATIONS: u32 = 100;
+
+// A position labelled with the result of its game.
+pub struct Sample {
+    // The features of the position from Player 1's perspective.
+    pub features: [i32; WEIGHTED_FEATURES],
+    // The result of the game for Player 1: 1 for a win, 0.5 for a draw and 0 for a loss.
+    pub result: f64,
+}
+
+// The expected result for Player 1 of a position with the given features.
+fn predict(weights: &[i32; WEIGHTED_FEATURES], features: &[i32; WEIGHTED_FEATURES]) -> f64 {
+    let score: i32 = features.iter().zip(weights).map(|(f, w)| f * w).sum();
+    1.0 / (1.0 + 10f64.powf(-(score as f64) / SCORE_SCALE))
+}
+
+// The mean squared error of the predicted results over `samples`.
+pub fn error(weights: &[i32; WEIGHTED_FEATURES], samples: &[Sample]) -> f64 {
+    let total: f64 = samples
+        .iter()
+        .map(|s| (s.result - predict(weights, &s.features)).powi(2))
+        .sum();
+    total / samples.len().max(1) as f64
+}
+
+// Make one pass over the weights, keeping every nudge that lowers the error. Returns true if any
+// weight changed.
+pub fn tune_step(weights: &mut [i32; WEIGHTED_FEATURES], samples: &[Sample]) -> bool {
+    let mut best = error(weights, samples);
+    let mut improved = false;
+    for i in 0..WEIGHTED_FEATURES {
+        for step in [STEP, -STEP] {
+            weights[i] += step;
+            let e = error(weights, samples);
+            if e < best {
+                best = e;
+                improved = true;
+                break;
+            }
+            weights[i] -= step;
+        }
+    }
+    improved
+}
+
+// Play a game of the engine against itself, returning the quiet positions reached along with the
+// result of the game.
+fn self_play(ctx: &MinimaxContext) -> Vec<Sample> {
+    let mut rng = thread_rng();
+    let mut table = TranspositionTable::new();
+    let mut board = Board::new();
+    let mut player = Player::Player1;
+    let mut positions = Vec::new();
+    let mut result = 0.5;
+    for ply in 0..MAX_PLIES {
+        let movements = board.movements(player);
+        if movements.is_empty() {
+            result = if player == Player::Player1 { 0.0 } else { 1.0 };
+            break;
+        }
+        let movement = if ply < OPENING_PLIES {
+            movements.choose(&mut rng).unwrap().clone()
+        } else {
+            if !board.legal_jump_exists(player) {
+                positions.push(WeightedEval::features(&board, Player::Player1));
+            }
+            get_movement(&mut Stats::new(), ctx, &mut board, player, &mut table, None).unwrap()
+        };
+        board.apply_and_promote(&movement, player);
+        player = player.other();
+    }
+    positions
+        .into_iter()
+        .map(|features| Sample { features, result })
+        .collect()
+}
+
+// Collect samples from `games` self-play games, fit the weights of [WEIGHTED_EVAL] to them and
+// print the result.
+pub fn run(ctx: &MinimaxContext, games: u32) {
+    let mut samples = Vec::new();
+    for _ in 0..games {
+        samples.append(&mut self_play(ctx));
+    }
+    println!("tuning.samples = {}", samples.len());
+
+    let mut weights = WEIGHTED_EVAL.weights;
+    println!("tuning.initial_error = {:.6}", error(&weights, &samples));
+    let mut iterations = 0;
+    while iterations < MAX_ITERATIONS && tune_step(&mut weights, &samples) {
+        iterations += 1;
+    }
+    println!("tuning.iterations = {}", iterations);
+    println!("tuning.final_error = {:.6}", error(&weights, &samples));
+    for (name, weight) in FEATURE_NAMES.iter().zip(weights) {
+        println!("tuning.weights.{} = {}", name, weight);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tune_step() {
+        // Player 1 wins the games where it is a pawn up and loses those where it is a pawn down.
+        let samples: Vec<Sample> = [(1, 1.0), (-1, 0.0), (2, 1.0), (-2, 0.0)]
+            .into_iter()
+            .map(|(pawns, result)| Sample {
+                features: [pawns, 0, 0, 0, 0, 0, 0, 0],
+                result,
+            })
+            .collect();
+
+        // a pawn is wrongly scored as a disadvantage
+        let mut weights = [-100, 150, 40, 10, 5, -20, 10, 2];
+        let before = error(&weights, &samples);
+        assert!(tune_step(&mut weights, &samples));
+        assert_eq!(weights, [-100 + STEP, 150, 40, 10, 5, -20, 10, 2]);
+        assert!(error(&weights, &samples) < before);
+    }
+}
@@ -0,0 +1,38 @@
+// Integration tests that run the compiled binary.
+use std::process::Command;
+
+#[test]
+fn test_analyze() {
+    // 9x18x25 takes two pieces, 2x11 only one
+    let output = Command::new(env!("CARGO_BIN_EXE_checkers-redux"))
+        .args([
+            "--p1-eval",
+            "v4",
+            "analyze",
+            "--fen",
+            "B:W7,14,22:B2,9",
+            "--depth",
+            "4",
+            "--top",
+            "2",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(lines[0], "analyze.1.move = 9x18x25");
+    assert!(lines[2].starts_with("analyze.1.pv = 9x18x25"));
+    assert!(lines[3].starts_with("analyze.1.pdn = 1. 9x18x25"));
+    assert_eq!(lines[4], "analyze.2.move = 2x11");
+}
+
+#[test]
+fn test_analyze_invalid_fen() {
+    let output = Command::new(env!("CARGO_BIN_EXE_checkers-redux"))
+        .args(["analyze", "--fen", "X:W1"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}